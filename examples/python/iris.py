@@ -1,42 +1,93 @@
 import enum
 import struct
 import itertools
+import math
 class Iris:
+    @staticmethod
+    def string_to_bytes(s: str, size: int) -> bytes:
+        raw = s.encode('utf-8')
+        if len(raw) > size:
+            raise ValueError(f'String too long, {len(raw)} bytes but the capacity is {size}.')
+        return raw
+    class Truncated(list):
+        """Bounded array received with a length larger than its maximum, only the maximum number of elements is decoded"""
+    @staticmethod
+    def check_length(items: list, size: int) -> int:
+        if len(items) > size:
+            raise ValueError(f'Array too long, {len(items)} elements but the capacity is {size}.')
+        return len(items)
+    @staticmethod
+    def string_from_bytes(raw: bytes) -> str:
+        return raw.split(b'\0', 1)[0].decode('utf-8', errors='ignore')
+    @staticmethod
+    def to_fixed(value: float, scale: float, offset: float, min: int, max: int) -> int:
+        raw = (value - offset) / scale
+        if math.isnan(raw):
+            return 0
+        if raw <= min:
+            return min
+        if raw >= max:
+            return max
+        return int(math.copysign(math.floor(abs(raw) + 0.5), raw))
+    @staticmethod
+    def pack_bits(size: int, fields: list) -> bytes:
+        value = 0
+        width = 0
+        for (v, w) in fields:
+            value = (value << w) | (int(v) & ((1 << w) - 1))
+            width += w
+        return (value << (size * 8 - width)).to_bytes(size, 'big')
+    @staticmethod
+    def read_bits(raw: bytes, offset: int, width: int) -> int:
+        value = int.from_bytes(raw, 'big')
+        return (value >> (len(raw) * 8 - offset - width)) & ((1 << width) - 1)
+    @staticmethod
+    def reshape(data, dims: list):
+        if len(dims) == 1:
+            return list(data)
+        size = len(data) // dims[0]
+        return [Iris.reshape(data[i:i + size], dims[1:]) for i in range(0, len(data), size)]
     class Packages:
         class Telemetry:
             class Status(enum.IntEnum):
-                FLIGHT = 2
-                IDLE = 3
                 SLEEP = 0
                 ACTIVE = 1
+                FLIGHT = 2
+                IDLE = 3
                 def to_be_bytes(self) -> bytes:
                     return struct.pack('>I', self)
                 @staticmethod
                 def from_be_bytes(raw: bytes):
                     match struct.unpack('>I', raw)[0]:
-                        case 2:
-                            return Iris.Packages.Telemetry.Status.FLIGHT
-                        case 3:
-                            return Iris.Packages.Telemetry.Status.IDLE
                         case 0:
                             return Iris.Packages.Telemetry.Status.SLEEP
                         case 1:
                             return Iris.Packages.Telemetry.Status.ACTIVE
+                        case 2:
+                            return Iris.Packages.Telemetry.Status.FLIGHT
+                        case 3:
+                            return Iris.Packages.Telemetry.Status.IDLE
                         case _:
-                            raise 'No variant found.'
+                            raise ValueError('No variant found.')
             class Battery:
                 NAME_HASH = 2215305518
                 BYTES_LENGTH = 4 + 4
-                def __init__(self, charge):
+                def __init__(self, charge=0.0):
                     self.charge = charge
                 def encode(self) -> bytes:
                     return struct.pack('>I4B', self.NAME_HASH, *self.to_be_bytes())
                 def to_be_bytes(self) -> bytes:
                     return struct.pack('>f', self.charge)
                 @staticmethod
-                def decode(raw: bytes):
+                def decode(raw: bytes, validate: bool = False):
                     data = struct.unpack('>I4B', raw)
-                    return Iris.Packages.Telemetry.Battery.from_be_bytes(bytes(data[1:]))
+                    out = Iris.Packages.Telemetry.Battery.from_be_bytes(bytes(data[1:]))
+                    if validate and (field := out.validate()) is not None:
+                        raise ValueError(f'Field `{field}` is not valid.')
+                    return out
+                def validate(self):
+                    """Check the limits set with `@range`, `@min` and `@max` and that the enum values are variants, return the first field failing it or `None`"""
+                    return None
                 @staticmethod
                 def from_be_bytes(raw: bytes):
                     data = struct.unpack('>f', raw)
@@ -46,39 +97,56 @@ class Iris:
             class Computer:
                 NAME_HASH = 3613607352
                 BYTES_LENGTH = 16 + 4
-                def __init__(self, id, batteries, status):
+                def __init__(self, id=0, batteries=None, status=None):
                     self.id = id
-                    self.batteries = batteries
-                    self.status = status
+                    self.batteries = batteries if batteries is not None else [Iris.Packages.Telemetry.Battery() for _ in range(2)]
+                    self.status = status if status is not None else Iris.Packages.Telemetry.Status.SLEEP
                 def encode(self) -> bytes:
                     return struct.pack('>I16B', self.NAME_HASH, *self.to_be_bytes())
                 def to_be_bytes(self) -> bytes:
-                    return struct.pack('>I4B4BI', self.id, *itertools.chain.from_iterable([i.to_be_bytes() for i in self.batteries]), self.status)
+                    return struct.pack('>I4B4BI', self.id, *itertools.chain.from_iterable(x0.to_be_bytes() for x0 in self.batteries), self.status)
                 @staticmethod
-                def decode(raw: bytes):
+                def decode(raw: bytes, validate: bool = False):
                     data = struct.unpack('>I16B', raw)
-                    return Iris.Packages.Telemetry.Computer.from_be_bytes(bytes(data[1:]))
+                    out = Iris.Packages.Telemetry.Computer.from_be_bytes(bytes(data[1:]))
+                    if validate and (field := out.validate()) is not None:
+                        raise ValueError(f'Field `{field}` is not valid.')
+                    return out
+                def validate(self):
+                    """Check the limits set with `@range`, `@min` and `@max` and that the enum values are variants, return the first field failing it or `None`"""
+                    if not (self.status in (0, 1, 2, 3,)):
+                        return 'status'
+                    return None
                 @staticmethod
                 def from_be_bytes(raw: bytes):
                     data = struct.unpack('>I4B4BI', raw)
                     return Iris.Packages.Telemetry.Computer(
                         id=data[0],
-                        batteries=[Iris.Packages.Telemetry.Battery.from_be_bytes(bytes(data[i:i+4])) for i in range(1, 8, 4)],
+                        batteries=[Iris.Packages.Telemetry.Battery.from_be_bytes(bytes(data[i:i+4])) for i in range(1, 9, 4)],
                         status=data[9],
                     )
             class Data:
                 NAME_HASH = 1062369733
                 BYTES_LENGTH = 32 + 4
-                def __init__(self, computers):
-                    self.computers = computers
+                def __init__(self, computers=None):
+                    self.computers = computers if computers is not None else [Iris.Packages.Telemetry.Computer() for _ in range(2)]
                 def encode(self) -> bytes:
                     return struct.pack('>I32B', self.NAME_HASH, *self.to_be_bytes())
                 def to_be_bytes(self) -> bytes:
-                    return struct.pack('>16B16B', *itertools.chain.from_iterable([i.to_be_bytes() for i in self.computers]))
+                    return struct.pack('>16B16B', *itertools.chain.from_iterable(x0.to_be_bytes() for x0 in self.computers))
                 @staticmethod
-                def decode(raw: bytes):
+                def decode(raw: bytes, validate: bool = False):
                     data = struct.unpack('>I32B', raw)
-                    return Iris.Packages.Telemetry.Data.from_be_bytes(bytes(data[1:]))
+                    out = Iris.Packages.Telemetry.Data.from_be_bytes(bytes(data[1:]))
+                    if validate and (field := out.validate()) is not None:
+                        raise ValueError(f'Field `{field}` is not valid.')
+                    return out
+                def validate(self):
+                    """Check the limits set with `@range`, `@min` and `@max` and that the enum values are variants, return the first field failing it or `None`"""
+                    for x0_0 in self.computers:
+                        if not (x0_0.status in (0, 1, 2, 3,)):
+                            return 'computers.status'
+                    return None
                 @staticmethod
                 def from_be_bytes(raw: bytes):
                     data = struct.unpack('>16B16B', raw)
@@ -1,30 +1,238 @@
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct FixedString<const CAP: usize> {
+    data: [u8; CAP],
+}
+impl<const CAP: usize> FixedString<{ CAP }> {
+    pub const CAPACITY: usize = CAP;
+    pub const fn new() -> FixedString<{ CAP }> {
+        FixedString { data: [0; CAP] }
+    }
+    pub fn set(&mut self, s: &str) -> Result<(), &'static str> {
+        if s.len() > CAP {
+            return Err("String too long.");
+        }
+        self.data = [0; CAP];
+        self.data[..s.len()].copy_from_slice(s.as_bytes());
+        Ok(())
+    }
+    pub fn len(&self) -> usize {
+        self.data.iter().position(|b| *b == 0).unwrap_or(CAP)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The string up to the first zero byte, invalid UTF-8 received on the wire is cut off
+    pub fn as_str(&self) -> &str {
+        let bytes = &self.data[..self.len()];
+        match core::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(),
+        }
+    }
+    pub fn to_be_bytes(&self) -> [u8; CAP] {
+        self.data
+    }
+    pub fn from_be_bytes(data: [u8; CAP]) -> FixedString<{ CAP }> {
+        FixedString { data }
+    }
+}
+impl<const CAP: usize> Default for FixedString<{ CAP }> {
+    fn default() -> FixedString<{ CAP }> {
+        FixedString::new()
+    }
+}
+impl<const CAP: usize> TryFrom<&str> for FixedString<{ CAP }> {
+    type Error = &'static str;
+    fn try_from(s: &str) -> Result<FixedString<{ CAP }>, &'static str> {
+        let mut out = FixedString::new();
+        out.set(s)?;
+        Ok(out)
+    }
+}
+impl<const CAP: usize> core::fmt::Debug for FixedString<{ CAP }> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+#[derive(Copy, Clone)]
+pub struct BoundedArray<Item, const CAP: usize> {
+    data: [Item; CAP],
+    len: usize,
+}
+impl<Item: Copy + Default, const CAP: usize> BoundedArray<Item, { CAP }> {
+    pub const CAPACITY: usize = CAP;
+    pub fn new() -> BoundedArray<Item, { CAP }> {
+        BoundedArray {
+            data: [Item::default(); CAP],
+            len: 0,
+        }
+    }
+    pub fn push(&mut self, value: Item) -> Result<(), &'static str> {
+        if self.len == CAP {
+            return Err("Array full.");
+        }
+        self.data[self.len] = value;
+        self.len += 1;
+        Ok(())
+    }
+    pub fn pop(&mut self) -> Option<Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.data[self.len])
+    }
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+    pub fn as_slice(&self) -> &[Item] {
+        &self.data[..self.len]
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [Item] {
+        &mut self.data[..self.len]
+    }
+}
+impl<Item: Copy + Default, const CAP: usize> Default for BoundedArray<Item, { CAP }> {
+    fn default() -> BoundedArray<Item, { CAP }> {
+        BoundedArray::new()
+    }
+}
+impl<Item: Copy + Default, const CAP: usize> core::ops::Deref for BoundedArray<Item, { CAP }> {
+    type Target = [Item];
+    fn deref(&self) -> &[Item] {
+        self.as_slice()
+    }
+}
+impl<Item: Copy + Default, const CAP: usize> core::ops::DerefMut for BoundedArray<Item, { CAP }> {
+    fn deref_mut(&mut self) -> &mut [Item] {
+        self.as_mut_slice()
+    }
+}
+impl<Item: Copy + Default, const CAP: usize> TryFrom<&[Item]> for BoundedArray<Item, { CAP }> {
+    type Error = &'static str;
+    fn try_from(s: &[Item]) -> Result<BoundedArray<Item, { CAP }>, &'static str> {
+        if s.len() > CAP {
+            return Err("Too many elements.");
+        }
+        let mut out = BoundedArray::new();
+        out.data[..s.len()].copy_from_slice(s);
+        out.len = s.len();
+        Ok(out)
+    }
+}
+impl<Item: Copy + Default + core::fmt::Debug, const CAP: usize> core::fmt::Debug
+    for BoundedArray<Item, { CAP }>
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_slice(), f)
+    }
+}
+pub fn write_bits(data: &mut [u8], offset: usize, width: usize, value: u64) {
+    for b in 0..width {
+        let bit = offset + b;
+        let mask = 0x80 >> (bit % 8);
+        if (value >> (width - 1 - b)) & 1 == 1 {
+            data[bit / 8] |= mask;
+        } else {
+            data[bit / 8] &= !mask;
+        }
+    }
+}
+pub fn read_bits(data: &[u8], offset: usize, width: usize) -> u64 {
+    let mut value = 0;
+    for b in 0..width {
+        let bit = offset + b;
+        value = (value << 1) | ((data[bit / 8] >> (7 - bit % 8)) & 1) as u64;
+    }
+    value
+}
 pub mod Telemetry {
     #[derive(Copy, Clone)]
+    #[repr(u32)]
     pub enum Status {
         SLEEP = 0,
-        FLIGHT = 2,
         ACTIVE = 1,
+        FLIGHT = 2,
         IDLE = 3,
     }
     impl Status {
         pub fn to_be_bytes(&self) -> [u8; 4] {
-            (match self {
-                Status::SLEEP => 0,
-                Status::FLIGHT => 2,
-                Status::ACTIVE => 1,
-                Status::IDLE => 3,
-            } as u32)
-                .to_be_bytes()
+            (*self as u32).to_be_bytes()
         }
-        pub fn from_be_bytes(data: [u8; 4]) -> Status {
+        /// The variant with the value of `data`, `None` if there is none
+        pub fn try_from_be_bytes(data: [u8; 4]) -> Option<Status> {
             match u32::from_be_bytes(data) {
-                0 => Status::SLEEP,
-                2 => Status::FLIGHT,
-                1 => Status::ACTIVE,
-                3 => Status::IDLE,
-                _ => panic!("No variant found."),
+                0 => Some(Status::SLEEP),
+                1 => Some(Status::ACTIVE),
+                2 => Some(Status::FLIGHT),
+                3 => Some(Status::IDLE),
+                _ => None,
             }
         }
+        /// The variant with the value of `data`, the default one if there is none
+        pub fn from_be_bytes(data: [u8; 4]) -> Status {
+            Status::try_from_be_bytes(data).unwrap_or_default()
+        }
+    }
+    impl Default for Status {
+        fn default() -> Status {
+            Status::SLEEP
+        }
+    }
+    #[derive(Copy, Clone)]
+    pub struct Battery {
+        pub charge: f32,
+    }
+    impl Battery {
+        pub const NAME_HASH: u32 = 2215305518;
+        pub const BYTES_LENGTH: usize = 4 + 4;
+        pub fn encode(&self) -> [u8; Battery::BYTES_LENGTH] {
+            let mut data: [u8; Battery::BYTES_LENGTH] = [0; Battery::BYTES_LENGTH];
+            let mut index = 0;
+            for x in u32::to_be_bytes(Battery::NAME_HASH) {
+                data[index] = x;
+                index += 1;
+            }
+            for x in self.to_be_bytes() {
+                data[index] = x;
+                index += 1;
+            }
+            data
+        }
+        pub fn to_be_bytes(&self) -> [u8; Battery::BYTES_LENGTH - 4] {
+            let mut data: [u8; Battery::BYTES_LENGTH - 4] = [0; Battery::BYTES_LENGTH - 4];
+            let mut index = 0;
+            for x in self.charge.to_be_bytes() {
+                data[index] = x;
+                index += 1;
+            }
+            data
+        }
+        pub fn decode(data: &[u8]) -> Battery {
+            Battery::from_be_bytes(data[4..data.len()].try_into().unwrap())
+        }
+        pub fn from_be_bytes(data: [u8; Battery::BYTES_LENGTH - 4]) -> Battery {
+            let mut out = Battery { charge: 0.0 };
+            let mut index = 0;
+            out.charge = f32::from_be_bytes(data[index..index + 4].try_into().unwrap());
+            index += 4;
+            out
+        }
+        /// Check the limits set with `@range`, `@min` and `@max`, return the first field out of them
+        pub fn validate(&self) -> Result<(), &'static str> {
+            Ok(())
+        }
+        /// Decode the struct and check its limits, the received values that can't be stored, like an enum value that isn't a variant, are reported like a value out of them
+        pub fn decode_validated(data: &[u8]) -> Result<Battery, &'static str> {
+            let out = Battery::decode(data);
+            out.validate()?;
+            Ok(out)
+        }
+    }
+    impl Default for Battery {
+        fn default() -> Battery {
+            Battery { charge: 0.0 }
+        }
     }
     #[derive(Copy, Clone)]
     pub struct Computer {
@@ -68,26 +276,63 @@ pub mod Telemetry {
             data
         }
         pub fn decode(data: &[u8]) -> Computer {
-            Computer::from_be_bytes(data[4..data.len()].try_into().unwrap())
+            Computer::decode_checked(data, &mut None)
+        }
+        fn decode_checked(data: &[u8], invalid: &mut Option<&'static str>) -> Computer {
+            Computer::from_be_bytes_checked(data[4..data.len()].try_into().unwrap(), invalid)
         }
         pub fn from_be_bytes(data: [u8; Computer::BYTES_LENGTH - 4]) -> Computer {
+            Computer::from_be_bytes_checked(data, &mut None)
+        }
+        /// Decode the struct, the first value that can't be stored is set to the default and its path is set in `invalid`
+        pub fn from_be_bytes_checked(
+            data: [u8; Computer::BYTES_LENGTH - 4],
+            invalid: &mut Option<&'static str>,
+        ) -> Computer {
             let mut out = Computer {
                 id: 0,
-                batteries: [Battery { charge: 0.0 }; 2],
+                batteries: [Battery::default(); 2],
                 status: Status::SLEEP,
             };
             let mut index = 0;
             out.id = u32::from_be_bytes(data[index..index + 4].try_into().unwrap());
             index += 4;
-            for i in 0..2 {
-                out.batteries[i] =
+            for i0 in 0..2 {
+                out.batteries[i0] =
                     Battery::from_be_bytes(data[index..index + 4].try_into().unwrap());
                 index += 4;
             }
-            out.status = Status::from_be_bytes(data[index..index + 4].try_into().unwrap());
+            out.status = Status::try_from_be_bytes(data[index..index + 4].try_into().unwrap())
+                .unwrap_or_else(|| {
+                    invalid.get_or_insert("status");
+                    Status::default()
+                });
             index += 4;
             out
         }
+        /// Check the limits set with `@range`, `@min` and `@max`, return the first field out of them
+        pub fn validate(&self) -> Result<(), &'static str> {
+            Ok(())
+        }
+        /// Decode the struct and check its limits, the received values that can't be stored, like an enum value that isn't a variant, are reported like a value out of them
+        pub fn decode_validated(data: &[u8]) -> Result<Computer, &'static str> {
+            let mut invalid = None;
+            let out = Computer::decode_checked(data, &mut invalid);
+            if let Some(field) = invalid {
+                return Err(field);
+            }
+            out.validate()?;
+            Ok(out)
+        }
+    }
+    impl Default for Computer {
+        fn default() -> Computer {
+            Computer {
+                id: 0,
+                batteries: [Battery::default(); 2],
+                status: Status::SLEEP,
+            }
+        }
     }
     #[derive(Copy, Clone)]
     pub struct Data {
@@ -121,83 +366,82 @@ pub mod Telemetry {
             data
         }
         pub fn decode(data: &[u8]) -> Data {
-            Data::from_be_bytes(data[4..data.len()].try_into().unwrap())
+            Data::decode_checked(data, &mut None)
+        }
+        fn decode_checked(data: &[u8], invalid: &mut Option<&'static str>) -> Data {
+            Data::from_be_bytes_checked(data[4..data.len()].try_into().unwrap(), invalid)
         }
         pub fn from_be_bytes(data: [u8; Data::BYTES_LENGTH - 4]) -> Data {
+            Data::from_be_bytes_checked(data, &mut None)
+        }
+        /// Decode the struct, the first value that can't be stored is set to the default and its path is set in `invalid`
+        pub fn from_be_bytes_checked(
+            data: [u8; Data::BYTES_LENGTH - 4],
+            invalid: &mut Option<&'static str>,
+        ) -> Data {
             let mut out = Data {
-                computers: [Computer {
-                    status: Status::SLEEP,
-                    batteries: [Battery { charge: 0.0 }; 2],
-                    id: 0,
-                }; 2],
+                computers: [Computer::default(); 2],
             };
             let mut index = 0;
-            for i in 0..2 {
-                out.computers[i] =
-                    Computer::from_be_bytes(data[index..index + 16].try_into().unwrap());
+            for i0 in 0..2 {
+                out.computers[i0] = {
+                    let mut e = None;
+                    let x = Computer::from_be_bytes_checked(
+                        data[index..index + 16].try_into().unwrap(),
+                        &mut e,
+                    );
+                    if let Some(e) = e {
+                        invalid.get_or_insert(match e {
+                            "status" => "computers.status",
+                            _ => "computers",
+                        });
+                    }
+                    x
+                };
                 index += 16;
             }
             out
         }
-    }
-    #[derive(Copy, Clone)]
-    pub struct Battery {
-        pub charge: f32,
-    }
-    impl Battery {
-        pub const NAME_HASH: u32 = 2215305518;
-        pub const BYTES_LENGTH: usize = 4 + 4;
-        pub fn encode(&self) -> [u8; Battery::BYTES_LENGTH] {
-            let mut data: [u8; Battery::BYTES_LENGTH] = [0; Battery::BYTES_LENGTH];
-            let mut index = 0;
-            for x in u32::to_be_bytes(Battery::NAME_HASH) {
-                data[index] = x;
-                index += 1;
-            }
-            for x in self.to_be_bytes() {
-                data[index] = x;
-                index += 1;
-            }
-            data
+        /// Check the limits set with `@range`, `@min` and `@max`, return the first field out of them
+        pub fn validate(&self) -> Result<(), &'static str> {
+            Ok(())
         }
-        pub fn to_be_bytes(&self) -> [u8; Battery::BYTES_LENGTH - 4] {
-            let mut data: [u8; Battery::BYTES_LENGTH - 4] = [0; Battery::BYTES_LENGTH - 4];
-            let mut index = 0;
-            for x in self.charge.to_be_bytes() {
-                data[index] = x;
-                index += 1;
+        /// Decode the struct and check its limits, the received values that can't be stored, like an enum value that isn't a variant, are reported like a value out of them
+        pub fn decode_validated(data: &[u8]) -> Result<Data, &'static str> {
+            let mut invalid = None;
+            let out = Data::decode_checked(data, &mut invalid);
+            if let Some(field) = invalid {
+                return Err(field);
             }
-            data
+            out.validate()?;
+            Ok(out)
         }
-        pub fn decode(data: &[u8]) -> Battery {
-            Battery::from_be_bytes(data[4..data.len()].try_into().unwrap())
-        }
-        pub fn from_be_bytes(data: [u8; Battery::BYTES_LENGTH - 4]) -> Battery {
-            let mut out = Battery { charge: 0.0 };
-            let mut index = 0;
-            out.charge = f32::from_be_bytes(data[index..index + 4].try_into().unwrap());
-            index += 4;
-            out
+    }
+    impl Default for Data {
+        fn default() -> Data {
+            Data {
+                computers: [Computer::default(); 2],
+            }
         }
     }
 }
 pub enum Structs {
+    Battery(Telemetry::Battery),
     Computer(Telemetry::Computer),
     Data(Telemetry::Data),
-    Battery(Telemetry::Battery),
 }
 pub fn decode(data: &[u8]) -> Result<Structs, &str> {
     let struct_name_hash = u32::from_be_bytes(data[0..4].try_into().unwrap());
     match struct_name_hash {
+        Telemetry::Battery::NAME_HASH if data.len() == Telemetry::Battery::BYTES_LENGTH => {
+            Ok(Structs::Battery(Telemetry::Battery::decode(&data)))
+        }
         Telemetry::Computer::NAME_HASH if data.len() == Telemetry::Computer::BYTES_LENGTH => {
             Ok(Structs::Computer(Telemetry::Computer::decode(&data)))
         }
         Telemetry::Data::NAME_HASH if data.len() == Telemetry::Data::BYTES_LENGTH => {
             Ok(Structs::Data(Telemetry::Data::decode(&data)))
         }
-        Telemetry::Battery::NAME_HASH if data.len() == Telemetry::Battery::BYTES_LENGTH => {
-            Ok(Structs::Battery(Telemetry::Battery::decode(&data)))
-        }
         _ => Err("Unknown data."),
     }
 }
@@ -1,5 +1,7 @@
 use iris::{decode, Telemetry::{Battery, Computer, Data, Status}, Structs};
 
+// Generated code, not meant to be linted.
+#[allow(non_snake_case, dead_code, unused_assignments, clippy::all)]
 mod iris;
 
 fn main() {
@@ -35,4 +37,8 @@ fn main() {
     let en = data.encode();
 
     let de = decode(&en);
+
+    if let Ok(Structs::Data(d)) = de {
+        assert_eq!(d.computers[1].id, 1337);
+    }
 }
\ No newline at end of file
@@ -3,7 +3,11 @@ pub mod generators;
 pub mod token_types;
 pub mod tokenizer;
 pub mod parser;
+pub mod resolver;
+pub mod loader;
 pub mod ast;
 pub mod error;
 
-pub mod kronos_code_gen;
\ No newline at end of file
+pub mod kronos_code_gen;
+pub mod compat;
+pub mod build;
\ No newline at end of file
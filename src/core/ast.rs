@@ -1,22 +1,35 @@
-use std::collections::HashMap;
+use std::{collections::HashSet, path::PathBuf};
 
+use indexmap::IndexMap;
 use semver::{BuildMetadata, Prerelease, Version, VersionReq};
 
+use super::error::Span;
+
 pub struct AST {
-    pub package: Package
+    pub package: Package,
+    /// Files imported by the schema, directly or through other files
+    pub imports: Vec<PathBuf>
 }
 
+#[derive(Debug, Clone)]
 pub struct Package {
     pub name: Option<String>,
     pub version: Option<String>,
-    pub structs: HashMap<String, Struct>,
-    pub enums: HashMap<String, Enum>
+    /// Structs in declaration order
+    pub structs: IndexMap<String, Struct>,
+    /// Enums in declaration order
+    pub enums: IndexMap<String, Enum>,
+    /// Unions in declaration order
+    pub unions: IndexMap<String, Union>,
+    /// Constants in declaration order
+    pub constants: IndexMap<String, Constant>,
+    /// Packages imported with `import "file.iris";`, by name, their types are used as `Package.Type`
+    pub imports: IndexMap<String, Package>
 }
 
 impl Package {
     pub fn add_struct_field(&mut self, struct_name: &String, field: StructField) {
         let s = self.structs.get_mut(struct_name).unwrap();
-        s.fields_order.push(field.name.clone());
         s.fields.insert(field.name.clone(), field);
     }
 
@@ -25,7 +38,107 @@ impl Package {
         e.variants.insert(variant.name.clone(), variant);
     }
 
-    pub fn check_version(&self) -> bool {
+    pub fn add_union_variant(&mut self, union_name: &String, variant: UnionVariant) {
+        let u = self.unions.get_mut(union_name).unwrap();
+        u.variants.insert(variant.name.clone(), variant);
+    }
+
+    /// Structs, enums, unions and constants share the same names, they are all declared in the same namespace of the generated code
+    pub fn is_declared(&self, name: &str) -> bool {
+        self.structs.contains_key(name) || self.enums.contains_key(name) || self.unions.contains_key(name) || self.constants.contains_key(name)
+    }
+
+    /// Package declaring the type `name` used by a field of this package and the name of the type in it.
+    /// `Fix` is declared by this package, `Gnss.Fix` by the imported package `Gnss`.
+    pub fn owner<'a>(&self, name: &'a str) -> (&Package, &'a str) {
+        match name.split_once('.') {
+            Some((package, name)) => (self.imports.get(package).unwrap(), name),
+            None => (self, name)
+        }
+    }
+
+    /// Return the struct used by a field and the package declaring it, the types of its fields are relative to that package
+    pub fn get_struct(&self, name: &str) -> (&Package, &Struct) {
+        let (package, name) = self.owner(name);
+        (package, package.structs.get(name).unwrap())
+    }
+
+    pub fn get_enum(&self, name: &str) -> &Enum {
+        let (package, name) = self.owner(name);
+        package.enums.get(name).unwrap()
+    }
+
+    /// Return the union used by a field and the package declaring it, the types of its variants are relative to that package
+    pub fn get_union(&self, name: &str) -> (&Package, &Union) {
+        let (package, name) = self.owner(name);
+        (package, package.unions.get(name).unwrap())
+    }
+
+    /// This package and the ones it imports, directly or through other packages.
+    /// Every package comes once and after the packages it imports.
+    pub fn all_packages(&self) -> Vec<&Package> {
+        let mut out = Vec::new();
+        self.visit_imports(&mut out);
+        out
+    }
+
+    /// A field of this package or of the imported ones is declared with `@deprecated`
+    pub fn has_deprecated(&self) -> bool {
+        self.all_packages().iter().any(|p| p.structs.values().any(|s| s.fields.values().any(|f| f.deprecated.is_some())))
+    }
+
+    fn visit_imports<'a>(&'a self, out: &mut Vec<&'a Package>) {
+        for p in self.imports.values() {
+            p.visit_imports(out);
+        }
+
+        if !out.iter().any(|p| p.name == self.name) {
+            out.push(self);
+        }
+    }
+
+    /// Structs and unions sorted so that every one comes after the structs and unions it contains,
+    /// ties are broken by declaration order, structs first.
+    pub fn dependency_order(&self) -> Vec<Declaration<'_>> {
+        let mut out = Vec::new();
+        let mut visited = HashSet::new();
+
+        for s in self.structs.values() {
+            self.visit_dependencies(Declaration::Struct(s), &mut visited, &mut out);
+        }
+        for u in self.unions.values() {
+            self.visit_dependencies(Declaration::Union(u), &mut visited, &mut out);
+        }
+
+        out
+    }
+
+    fn visit_dependencies<'a>(&'a self, d: Declaration<'a>, visited: &mut HashSet<&'a str>, out: &mut Vec<Declaration<'a>>) {
+        let (name, types): (&str, Vec<&FieldType>) = match d {
+            Declaration::Struct(s) => (&s.name, s.fields.values().map(|f| &f.t).collect()),
+            Declaration::Union(u) => (&u.name, u.variants.values().map(|v| &v.t).collect())
+        };
+        if !visited.insert(name) {
+            return;
+        }
+
+        for t in types {
+            match t {
+                FieldType::COMPLEX(ComplexTypes::Struct(name)) => if let Some(dep) = self.structs.get(name) {
+                    self.visit_dependencies(Declaration::Struct(dep), visited, out);
+                },
+                FieldType::COMPLEX(ComplexTypes::Union(name)) => if let Some(dep) = self.unions.get(name) {
+                    self.visit_dependencies(Declaration::Union(dep), visited, out);
+                },
+                _ => {}
+            }
+        }
+
+        out.push(d);
+    }
+
+    /// Check if the declared version requirement is satisfied by this compiler
+    pub fn check_version(&self) -> Result<bool, semver::Error> {
         let version = Version {
             major: env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap(),
             minor: env!("CARGO_PKG_VERSION_MINOR").parse().unwrap(),
@@ -34,17 +147,29 @@ impl Package {
             build: BuildMetadata::EMPTY
         };
 
-        let req = VersionReq::parse(self.version.clone().unwrap().as_str());
+        let req = VersionReq::parse(self.version.clone().unwrap().as_str())?;
         
-        req.unwrap().matches(&version)
+        Ok(req.matches(&version))
     }
 }
 
+/// Struct or union, the declarations that can contain each other
+#[derive(Debug, Clone, Copy)]
+pub enum Declaration<'a> {
+    Struct(&'a Struct),
+    Union(&'a Union)
+}
+
 #[derive(Debug, Clone)]
 pub struct Struct {
     pub name: String,
-    pub fields: HashMap<String, StructField>,
-    pub fields_order: Vec<String>
+    pub span: Span,
+    /// Id set with `@id(...)`, used instead of the hash of the name
+    pub explicit_id: Option<u32>,
+    /// Doc comment written with `##` before the struct
+    pub doc: Option<String>,
+    /// Fields in declaration order, which is also the encoding order
+    pub fields: IndexMap<String, StructField>
 }
 
 impl Struct {
@@ -65,47 +190,264 @@ impl Struct {
         hash
     }
 
+    /// Id written at the beginning of every encoded struct
+    pub fn id(&self) -> u32 {
+        self.explicit_id.unwrap_or_else(|| self.fnv_1a())
+    }
+
+    /// Encoded size in bytes, bitfields next to each other are packed together and padded to a byte.
+    /// It's the largest size for a struct with a variable size.
     pub fn size(&self, pkg: &Package) -> u32 {
-        let mut counter = 0;
-        for f in self.fields.values() {
-            counter += f.size(pkg);
+        let mut counter = self.presence_bytes();
+        for (f, bits) in self.fields.values().zip(self.bit_ranges()) {
+            counter += match bits {
+                Some(range) => range.run_bytes.unwrap_or(0),
+                None => f.size(pkg)
+            };
         }
 
         counter
     }
+
+    /// The encoded size depends on the values, because of a bounded array, an optional field or a nested struct with a variable size
+    pub fn is_variable(&self, pkg: &Package) -> bool {
+        self.fields.values().any(|f| f.is_variable(pkg))
+    }
+
+    /// Bytes taken by the struct whatever the values, the generated code adds the used elements of the bounded arrays,
+    /// the optional fields that are present and the size of the nested structs with a variable size
+    pub fn base_size(&self, pkg: &Package) -> u32 {
+        let mut counter = self.presence_bytes();
+        for (f, bits) in self.fields.values().zip(self.bit_ranges()) {
+            counter += match bits {
+                Some(range) => range.run_bytes.unwrap_or(0),
+                None if f.optional => 0,
+                None if f.bounded => f.length_type().unwrap().size(),
+                None if f.t.is_variable(pkg) => 0,
+                None => f.size(pkg)
+            };
+        }
+
+        counter
+    }
+
+    /// Bytes of the presence bitmap encoded before the fields, one bit for every optional field
+    pub fn presence_bytes(&self) -> u32 {
+        (self.fields.values().filter(|f| f.optional).count() as u32).div_ceil(8)
+    }
+
+    /// Bit of every optional field in the presence bitmap, in the same order of the fields, `None` for the other fields.
+    /// The first optional field is the most significant bit of the first byte.
+    pub fn presence_bits(&self) -> Vec<Option<u32>> {
+        let mut bit = 0;
+        self.fields.values().map(|f| {
+            match f.optional {
+                true => {
+                    bit += 1;
+                    Some(bit - 1)
+                },
+                false => None
+            }
+        }).collect()
+    }
+
+    /// Position of every bitfield, in the same order of the fields, `None` for the other fields.
+    /// Consecutive bitfields are packed MSB first, the first one starts from the most significant bit of a new byte.
+    pub fn bit_ranges(&self) -> Vec<Option<BitRange>> {
+        let mut out: Vec<Option<BitRange>> = Vec::new();
+        let mut offset = 0;
+
+        for f in self.fields.values() {
+            match f.bits {
+                Some(width) => {
+                    out.push(Some(BitRange { offset, width, run_bytes: None }));
+                    offset += width;
+                },
+                None => {
+                    close_run(&mut out, offset);
+                    offset = 0;
+                    out.push(None);
+                }
+            }
+        }
+        close_run(&mut out, offset);
+
+        out
+    }
+}
+
+/// Set the number of bytes of the packed bitfields on the last one
+fn close_run(ranges: &mut [Option<BitRange>], bits: u32) {
+    if let Some(Some(last)) = ranges.last_mut() {
+        last.run_bytes = Some(bits.div_ceil(8));
+    }
+}
+
+/// Default value of a field or value of a constant, already checked against its type.
+/// Scaled fields have a `Float` in engineering units, enums and flags a `Variant`.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Variant(String)
+}
+
+/// Constant declared with `const u8 MAX_ENGINES = 4;`, it can be used as the size of arrays and strings
+#[derive(Debug, Clone)]
+pub struct Constant {
+    pub name: String,
+    pub span: Span,
+    pub t: PrimitiveTypes,
+    pub value: Value,
+    /// Doc comment written with `##` before the constant
+    pub doc: Option<String>
+}
+
+/// Values accepted by a field, every element of an array is checked
+#[derive(Debug, Clone)]
+pub enum Limits {
+    /// `@range(min, max)` of a number, in engineering units for scaled fields
+    Range(f64, f64),
+    /// `@min(VARIANT)` and `@max(VARIANT)` of an enum, with the position of the attributes
+    Variants(Option<(String, Span)>, Option<(String, Span)>)
+}
+
+/// Integer field exposed as a float in engineering units, `value = raw * scale + offset`.
+/// When encoding, the value is rounded to the nearest integer (halfway away from zero) and saturated to the limits of the type, NaN is encoded as 0.
+#[derive(Debug, Clone, Copy)]
+pub struct Scaling {
+    pub scale: f64,
+    pub offset: f64
+}
+
+impl Scaling {
+    /// Smallest and largest value that can be encoded with the integer type `t`
+    pub fn limits(&self, t: &PrimitiveTypes) -> (f64, f64) {
+        let (min, max) = t.limits().unwrap();
+        let min = min as f64 * self.scale + self.offset;
+        let max = max as f64 * self.scale + self.offset;
+
+        if min <= max { (min, max) } else { (max, min) }
+    }
+}
+
+/// Bits taken by a bitfield
+#[derive(Debug, Clone, Copy)]
+pub struct BitRange {
+    /// First bit of the field, counted from the most significant bit of the first byte of the packed bitfields
+    pub offset: u32,
+    pub width: u32,
+    /// Bytes taken by the packed bitfields, set only on the last one
+    pub run_bytes: Option<u32>
 }
 
 #[derive(Debug, Clone)]
 pub struct StructField {
     pub name: String,
+    pub span: Span,
     pub t: FieldType,
-    pub array: Option<u32>
+    pub type_span: Span,
+    /// Size of every dimension of the array, `f32[3][4]` is `[3, 4]`, empty when the field is not an array
+    pub array: Vec<u32>,
+    /// Declared with `u16[<=64] name;`, `array` has the maximum number of elements and only the used ones are encoded
+    pub bounded: bool,
+    /// Declared with `optional f32 name;`, the value is encoded only when its bit of `Struct::presence_bits` is set
+    pub optional: bool,
+    /// Padding declared with `reserved 4;`, `array` has the number of bytes. They're zero and skipped when decoding,
+    /// the name is `reserved#` followed by a counter so it can't be used by another field.
+    pub reserved: bool,
+    /// Set with `@deprecated` or `@deprecated("note")`, the note is empty without an argument.
+    /// The generated code warns when the field is used, it's still encoded.
+    pub deprecated: Option<String>,
+    /// Width of a bitfield declared with `u8:3 name;`
+    pub bits: Option<u32>,
+    /// Set with `@scale` and `@offset`, the integer is exposed as a float
+    pub scaling: Option<Scaling>,
+    /// Doc comment written with `##` before the field
+    pub doc: Option<String>,
+    /// Unit of the value, set with `@unit("V")`
+    pub unit: Option<String>,
+    /// Values accepted by the generated `validate` methods
+    pub limits: Option<Limits>,
+    /// Value declared with `u16 rate = 50;`, given to every element of an array
+    pub default: Option<(Value, Span)>
 }
 
 impl StructField {
+    /// Bytes taken by the field, bitfields are packed with the ones next to them and counted by `Struct::size`.
+    /// Bounded arrays take the number of elements and all the elements they can hold.
     pub fn size(&self, pkg: &Package) -> u32 {
-        self.t.size(pkg) * self.array.unwrap_or(1)
+        match (self.bits, self.length_type()) {
+            (Some(_), _) => 0,
+            (None, Some(l)) => l.size() + self.t.size(pkg) * self.elements(),
+            (None, None) => self.t.size(pkg) * self.elements()
+        }
+    }
+
+    /// Type of the number of elements encoded before a bounded array, the smallest one that fits the maximum
+    pub fn length_type(&self) -> Option<PrimitiveTypes> {
+        if !self.bounded {
+            return None;
+        }
+
+        match self.elements() {
+            n if n <= u8::MAX as u32 => Some(PrimitiveTypes::U8),
+            n if n <= u16::MAX as u32 => Some(PrimitiveTypes::U16),
+            _ => Some(PrimitiveTypes::U32)
+        }
+    }
+
+    /// The encoded size of the field depends on its value
+    pub fn is_variable(&self, pkg: &Package) -> bool {
+        self.bounded || self.optional || self.t.is_variable(pkg)
+    }
+
+    pub fn is_array(&self) -> bool {
+        !self.array.is_empty()
+    }
+
+    /// Number of values stored in the field, 1 if it's not an array
+    pub fn elements(&self) -> u32 {
+        self.array.iter().product()
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum FieldType {
     COMPLEX(ComplexTypes),
-    PRIMITIVE(PrimitiveTypes)
+    PRIMITIVE(PrimitiveTypes),
+    /// UTF-8 string with the given capacity in bytes, zero padded on the wire
+    STRING(u32)
 }
 
 impl FieldType {
     pub fn str(&self) -> &str {
         match self {
             FieldType::PRIMITIVE(p) => p.str(),
-            FieldType::COMPLEX(c) => c.str()
+            FieldType::COMPLEX(c) => c.str(),
+            FieldType::STRING(_) => "string"
         }
     }
 
     pub fn size(&self, pkg: &Package) -> u32 {
         match self {
             FieldType::PRIMITIVE(p) => p.size(),
-            FieldType::COMPLEX(c) => c.size(pkg)
+            FieldType::COMPLEX(c) => c.size(pkg),
+            FieldType::STRING(n) => *n
+        }
+    }
+
+    /// Structs with a bounded array, directly or in a nested struct, have a variable size.
+    /// Unions always take the size of the largest struct.
+    pub fn is_variable(&self, pkg: &Package) -> bool {
+        match self {
+            FieldType::COMPLEX(ComplexTypes::Struct(s)) => {
+                let (owner, s) = pkg.get_struct(s);
+                s.is_variable(owner)
+            },
+            _ => false
         }
     }
 }
@@ -114,23 +456,44 @@ impl FieldType {
 pub enum ComplexTypes {
     Struct(String),
     Enum(String),
+    Union(String),
+    /// Type not resolved yet, after the semantic analysis there are none left
     Unknown(String)
 }
 
 impl ComplexTypes {
+    /// Name of the type, `Package.Type` for the types of imported packages
     pub fn str(&self) -> &str {
         match self {
             ComplexTypes::Struct(s) => s.as_str(),
             ComplexTypes::Enum(e) => e.as_str(),
+            ComplexTypes::Union(u) => u.as_str(),
             ComplexTypes::Unknown(u) => u.as_str()
         }
     }
 
+    /// Imported package declaring the type, `None` for the types of the same package
+    pub fn package(&self) -> Option<&str> {
+        self.str().split_once('.').map(|(package, _)| package)
+    }
+
+    /// Name of the type without the package
+    pub fn name(&self) -> &str {
+        self.str().split_once('.').map_or(self.str(), |(_, name)| name)
+    }
+
     pub fn size(&self, pkg: &Package) -> u32 {
         match self {
-            ComplexTypes::Struct(s) => pkg.structs.get(s).unwrap().size(pkg), 
-            ComplexTypes::Enum(e) => pkg.enums.get(e).unwrap().size(),
-            ComplexTypes::Unknown(_) => panic!("Can't return size of unknown data types.")
+            ComplexTypes::Struct(s) => {
+                let (owner, s) = pkg.get_struct(s);
+                s.size(owner)
+            },
+            ComplexTypes::Enum(e) => pkg.get_enum(e).size(),
+            ComplexTypes::Union(u) => {
+                let (owner, u) = pkg.get_union(u);
+                u.size(owner)
+            },
+            ComplexTypes::Unknown(_) => unreachable!("Types are resolved by the semantic analysis.")
         }
     }
 }
@@ -140,10 +503,13 @@ pub enum PrimitiveTypes {
     U8,
     U16,
     U32,
+    U64,
     I8,
     I16,
     I32,
+    I64,
     F32,
+    F64,
     Bool
 }
 
@@ -153,25 +519,47 @@ impl PrimitiveTypes {
             PrimitiveTypes::U8 => "u8",
             PrimitiveTypes::U16 => "u16",
             PrimitiveTypes::U32 => "u32",
+            PrimitiveTypes::U64 => "u64",
             PrimitiveTypes::I8 => "i8",
             PrimitiveTypes::I16 => "i16",
             PrimitiveTypes::I32 => "i32",
+            PrimitiveTypes::I64 => "i64",
             PrimitiveTypes::F32 => "f32",
+            PrimitiveTypes::F64 => "f64",
             PrimitiveTypes::Bool => "bool"
         }
     }
 
-    pub fn new(s: String) -> PrimitiveTypes {
-        match s.as_str() {
-            "u8" => PrimitiveTypes::U8,
-            "u16" => PrimitiveTypes::U16,
-            "u32" => PrimitiveTypes::U32,
-            "i8" => PrimitiveTypes::I8,
-            "i16" => PrimitiveTypes::I16,
-            "i32" => PrimitiveTypes::I32,
-            "f32" => PrimitiveTypes::F32,
-            "bool" => PrimitiveTypes::Bool,
-            _ => panic!("Unknown primitive type.")
+    /// Return the primitive type with the given name, if there is one
+    pub fn new(s: &str) -> Option<PrimitiveTypes> {
+        match s {
+            "u8" => Some(PrimitiveTypes::U8),
+            "u16" => Some(PrimitiveTypes::U16),
+            "u32" => Some(PrimitiveTypes::U32),
+            "u64" => Some(PrimitiveTypes::U64),
+            "i8" => Some(PrimitiveTypes::I8),
+            "i16" => Some(PrimitiveTypes::I16),
+            "i32" => Some(PrimitiveTypes::I32),
+            "i64" => Some(PrimitiveTypes::I64),
+            "f32" => Some(PrimitiveTypes::F32),
+            "f64" => Some(PrimitiveTypes::F64),
+            "bool" => Some(PrimitiveTypes::Bool),
+            _ => None
+        }
+    }
+
+    /// Smallest and largest value of an integer type, `None` for the other types
+    pub fn limits(&self) -> Option<(i128, i128)> {
+        match self {
+            PrimitiveTypes::U8 => Some((0, u8::MAX as i128)),
+            PrimitiveTypes::U16 => Some((0, u16::MAX as i128)),
+            PrimitiveTypes::U32 => Some((0, u32::MAX as i128)),
+            PrimitiveTypes::U64 => Some((0, u64::MAX as i128)),
+            PrimitiveTypes::I8 => Some((i8::MIN as i128, i8::MAX as i128)),
+            PrimitiveTypes::I16 => Some((i16::MIN as i128, i16::MAX as i128)),
+            PrimitiveTypes::I32 => Some((i32::MIN as i128, i32::MAX as i128)),
+            PrimitiveTypes::I64 => Some((i64::MIN as i128, i64::MAX as i128)),
+            PrimitiveTypes::F32 | PrimitiveTypes::F64 | PrimitiveTypes::Bool => None
         }
     }
 
@@ -179,7 +567,8 @@ impl PrimitiveTypes {
         match self {
             PrimitiveTypes::U8 | PrimitiveTypes::I8 | PrimitiveTypes::Bool => 1,
             PrimitiveTypes::U16 | PrimitiveTypes::I16 => 2,
-            PrimitiveTypes::U32 | PrimitiveTypes::I32 | PrimitiveTypes::F32 => 4
+            PrimitiveTypes::U32 | PrimitiveTypes::I32 | PrimitiveTypes::F32 => 4,
+            PrimitiveTypes::U64 | PrimitiveTypes::I64 | PrimitiveTypes::F64 => 8
         }
     }
 }
@@ -188,17 +577,87 @@ impl PrimitiveTypes {
 #[derive(Debug, Clone)]
 pub struct Enum {
     pub name: String,
-    pub variants: HashMap<String, EnumVariant>
+    pub span: Span,
+    /// Type used to encode the value, `u32` unless declared with `enum Name : u8`
+    pub repr: PrimitiveTypes,
+    /// Declared with `flags`, every variant is a bit and any combination of them is a valid value
+    pub flags: bool,
+    /// Variants in declaration order
+    pub variants: IndexMap<String, EnumVariant>
 }
 
 impl Enum {
     pub fn size(&self) -> u32 {
-        4
+        self.repr.size()
+    }
+
+    /// Largest value that fits in the type of the enum
+    pub fn max_value(&self) -> u32 {
+        match self.repr {
+            PrimitiveTypes::U8 => u8::MAX as u32,
+            PrimitiveTypes::U16 => u16::MAX as u32,
+            _ => u32::MAX
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct EnumVariant {
     pub name: String,
+    pub span: Span,
+    /// Value on the wire, set with `NAME = value;` or the value of the previous variant plus 1
     pub value: u32
-}
\ No newline at end of file
+}
+
+/// Tagged union declared with `union Payload : u8 { Ignite ignite; }`.
+/// It's encoded as the value of the variant followed by its struct, padded with zeros to the largest one.
+#[derive(Debug, Clone)]
+pub struct Union {
+    pub name: String,
+    pub span: Span,
+    /// Type used to encode the value of the variant, `u32` unless declared with `union Name : u8`
+    pub repr: PrimitiveTypes,
+    /// Doc comment written with `##` before the union
+    pub doc: Option<String>,
+    /// Variants in declaration order
+    pub variants: IndexMap<String, UnionVariant>
+}
+
+impl Union {
+    /// Encoded size in bytes, the value of the variant and the largest struct
+    pub fn size(&self, pkg: &Package) -> u32 {
+        self.repr.size() + self.variants.values().map(|v| v.t.size(pkg)).max().unwrap_or(0)
+    }
+
+    /// Largest value that fits in the type of the union
+    pub fn max_value(&self) -> u32 {
+        self.repr.limits().unwrap().1 as u32
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnionVariant {
+    pub name: String,
+    pub span: Span,
+    /// Struct carried by the variant
+    pub t: FieldType,
+    pub type_span: Span,
+    /// Value on the wire, set with `Ignite ignite = value;` or the value of the previous variant plus 1
+    pub value: u32,
+    /// Doc comment written with `##` before the variant
+    pub doc: Option<String>
+}
+
+impl UnionVariant {
+    /// Name of a variant in UpperCamelCase, `set_rate` is `SetRate`. It's the name of the Rust variant,
+    /// so two variants of a union can't have the same one.
+    pub fn camel_case(name: &str) -> String {
+        name.split('_')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let mut chars = p.chars();
+                chars.next().unwrap().to_uppercase().chain(chars).collect::<String>()
+            })
+            .collect()
+    }
+}
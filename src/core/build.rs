@@ -0,0 +1,111 @@
+//! Compile .iris files from a `build.rs` script.
+//!
+//! ```no_run
+//! // in the main function of build.rs
+//! if let Err(e) = iris::build::compile_schemas(&["telemetry.iris"]) {
+//!     panic!("{}", e);
+//! }
+//! ```
+//! The generated file is named after the schema, so it can be included in a module with:
+//! ```ignore
+//! #[allow(non_snake_case, non_camel_case_types, dead_code, unused_variables, unused_assignments, unused_mut, clippy::all)]
+//! mod telemetry {
+//!     include!(concat!(env!("OUT_DIR"), "/telemetry.rs"));
+//! }
+//! ```
+//! The lints are allowed because the generated code follows the names of the schema and has functions for every struct,
+//! an included file can't allow them with inner attributes.
+
+use std::{env, fs, io, path::{Path, PathBuf}};
+
+use super::{ast::Package, generators::{code_gen::CodeGen, rust::Rust}};
+
+
+/// Compile the schemas with the default configuration, the code is generated in `OUT_DIR`.
+pub fn compile_schemas<P: AsRef<Path>>(schemas: &[P]) -> io::Result<()> {
+    Config::new().compile(schemas)
+}
+
+/// Configuration used to compile the schemas
+#[derive(Debug, Clone)]
+pub struct Config {
+    out_dir: Option<PathBuf>,
+    include_dirs: Vec<PathBuf>,
+    max_errors: usize
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            out_dir: None,
+            include_dirs: Vec::new(),
+            max_errors: crate::DEFAULT_MAX_ERRORS
+        }
+    }
+
+    /// Directory where the code is generated, by default `OUT_DIR` set by cargo
+    pub fn out_dir<P: Into<PathBuf>>(&mut self, path: P) -> &mut Config {
+        self.out_dir = Some(path.into());
+        self
+    }
+
+    /// Directory where the imported files are searched, after the directory of the importing file
+    pub fn include_dir<P: Into<PathBuf>>(&mut self, path: P) -> &mut Config {
+        self.include_dirs.push(path.into());
+        self
+    }
+
+    /// Number of errors reported for every schema, 0 means no limit
+    pub fn max_errors(&mut self, max_errors: usize) -> &mut Config {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Generate the Rust code for every schema in `<out_dir>/<schema name>.rs`.
+    /// Cargo is told to run the build script again when a schema or a file it imports changes.
+    /// The structs of all the schemas and of the packages they import must have different ids.
+    pub fn compile<P: AsRef<Path>>(&self, schemas: &[P]) -> io::Result<()> {
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set, use `Config::out_dir`."))?
+        };
+
+        let mut packages = Vec::new();
+
+        for schema in schemas {
+            let schema = schema.as_ref();
+            println!("cargo:rerun-if-changed={}", schema.display());
+
+            let src = fs::read_to_string(schema)?;
+            let ast = crate::parse_with_imports(schema, &src, &self.include_dirs, self.max_errors)
+                .map_err(|files| io::Error::new(io::ErrorKind::InvalidData, files.iter().map(|f| f.render()).collect::<String>()))?;
+            for import in &ast.imports {
+                println!("cargo:rerun-if-changed={}", import.display());
+            }
+
+            let others: Vec<&Package> = packages.iter().flat_map(|p: &Package| p.all_packages()).collect();
+            crate::check_id_collisions(&ast.package, &others)
+                .map_err(|d| io::Error::new(io::ErrorKind::InvalidData, d.render(&schema.display().to_string(), &src)))?;
+
+            let stem = schema.file_stem()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("`{}` is not a file.", schema.display())))?;
+
+            fs::write(
+                out_dir.join(format!("{}.rs", stem.to_string_lossy())),
+                Rust {  }.gen_code(&ast.package)
+            )?;
+
+            packages.push(ast.package);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+
+use super::{ast::{Enum, EnumVariant, FieldType, Package, Struct, StructField, Union, UnionVariant}, error::{error_at, Diagnostic, Diagnostics, ErrorType, Span}};
+
+/// Wire-breaking changes between two versions of a schema.
+/// The removed declarations are shown in the old file, every other change in the new one.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub old: Diagnostics,
+    pub new: Diagnostics
+}
+
+impl Report {
+    /// Number of breaking changes
+    pub fn len(&self) -> usize {
+        self.old.len() + self.new.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Compare two versions of a package and report the changes that make the messages of one unreadable by the other.
+/// Names are not encoded, so renamed fields, variants, enums and unions are compatible, renamed structs are not because their id changes.
+/// Only the declarations of the two packages are compared, a change of an imported struct shows up as a different size.
+pub fn check(old: &Package, new: &Package) -> Report {
+    let mut report = Report::default();
+
+    let enums = pair(&old.enums, &new.enums, &[&same_enum]);
+    let unions = pair(&old.unions, &new.unions, &[&same_union]);
+
+    // Fields of the old schema are compared with their types renamed, so the layouts of renamed structs can be matched
+    let mut renames = HashMap::new();
+    for (o, n) in enums.iter().filter_map(|(o, n)| n.map(|n| (&o.name, &n.name))) {
+        renames.insert(o.clone(), n.clone());
+    }
+    for (o, n) in unions.iter().filter_map(|(o, n)| n.map(|n| (&o.name, &n.name))) {
+        renames.insert(o.clone(), n.clone());
+    }
+
+    let same_id = |o: &Struct, n: &Struct| o.id() == n.id();
+    let same_layout = |o: &Struct, n: &Struct| layout(o, &renames) == layout(n, &HashMap::new());
+    let structs = pair(&old.structs, &new.structs, &[&same_id, &same_layout]);
+    for (o, n) in structs.iter().filter_map(|(o, n)| n.map(|n| (&o.name, &n.name))) {
+        renames.insert(o.clone(), n.clone());
+    }
+
+    for (o, n) in structs {
+        match n {
+            Some(n) => check_struct(o, n, old, new, &renames, &mut report),
+            None => report.old.push(breaking(format!("Struct `{}` removed.", o.name).as_str(), 22, o.span)
+                .with_help("the new schema doesn't decode the messages with its id."))
+        }
+    }
+    for (o, n) in enums {
+        if let Some(n) = n {
+            check_enum(o, n, &mut report);
+        }
+    }
+    for (o, n) in unions {
+        if let Some(n) = n {
+            check_union(o, n, &renames, &mut report);
+        }
+    }
+
+    report.old.sort();
+    report.new.sort();
+    report
+}
+
+/// Error for a breaking change, the same kind of diagnostic of the parser
+fn breaking(msg: &str, code: u32, span: Span) -> Diagnostic {
+    error_at(ErrorType::Compatibility, msg, code, span)
+}
+
+/// Test telling if two declarations of the old and of the new package are the same one with a different name
+type Same<'a, T> = &'a dyn Fn(&T, &T) -> bool;
+
+/// Pair every declaration of the old package with the one of the new package with the same name.
+/// The ones left are paired with the first declaration left that satisfies one of `same`, tried in order.
+fn pair<'a, T>(old: &'a IndexMap<String, T>, new: &'a IndexMap<String, T>, same: &[Same<T>]) -> Vec<(&'a T, Option<&'a T>)> {
+    let mut used: HashSet<&str> = old.keys().filter(|k| new.contains_key(*k)).map(|k| k.as_str()).collect();
+    let mut out: Vec<(&T, Option<&T>)> = old.iter().map(|(k, o)| (o, new.get(k))).collect();
+
+    for same in same {
+        for (o, n) in out.iter_mut().filter(|(_, n)| n.is_none()) {
+            if let Some((k, found)) = new.iter().find(|(k, found)| !used.contains(k.as_str()) && same(o, found)) {
+                used.insert(k);
+                *n = Some(found);
+            }
+        }
+    }
+
+    out
+}
+
+fn same_enum(o: &Enum, n: &Enum) -> bool {
+    o.variants.len() == n.variants.len()
+        && o.variants.values().zip(n.variants.values()).all(|(a, b)| a.name == b.name && a.value == b.value)
+}
+
+fn same_union(o: &Union, n: &Union) -> bool {
+    o.variants.len() == n.variants.len()
+        && o.variants.values().zip(n.variants.values()).all(|(a, b)| a.name == b.name && a.value == b.value && a.t.str() == b.t.str())
+}
+
+/// Type of a field as it's encoded, like `u16[<=64]`, `i16 @scale(0.1)` or `reserved[4]`, with the types renamed by `renames`.
+/// The scale and offset don't change the bytes but what they mean.
+fn wire_type(f: &StructField, renames: &HashMap<String, String>) -> String {
+    if f.reserved {
+        return format!("reserved[{}]", f.elements());
+    }
+
+    let mut out = String::new();
+    if f.optional {
+        out.push_str("optional ");
+    }
+    match &f.t {
+        FieldType::STRING(n) => out.push_str(format!("string[{}]", n).as_str()),
+        FieldType::COMPLEX(c) => out.push_str(renames.get(c.str()).map_or(c.str(), |n| n.as_str())),
+        FieldType::PRIMITIVE(p) => out.push_str(p.str())
+    }
+    if let Some(bits) = f.bits {
+        out.push_str(format!(":{}", bits).as_str());
+    }
+    if f.bounded {
+        out.push_str(format!("[<={}]", f.elements()).as_str());
+    }
+    else {
+        for n in &f.array {
+            out.push_str(format!("[{}]", n).as_str());
+        }
+    }
+    if let Some(s) = f.scaling {
+        if s.scale != 1.0 {
+            out.push_str(format!(" @scale({})", s.scale).as_str());
+        }
+        if s.offset != 0.0 {
+            out.push_str(format!(" @offset({})", s.offset).as_str());
+        }
+    }
+
+    out
+}
+
+fn layout(s: &Struct, renames: &HashMap<String, String>) -> Vec<String> {
+    s.fields.values().map(|f| wire_type(f, renames)).collect()
+}
+
+/// Size of a struct for the messages, `12 bytes` or `at most 12 bytes`
+fn size_str(s: &Struct, pkg: &Package) -> String {
+    let size = s.size(pkg);
+    let bytes = if size == 1 { "byte" } else { "bytes" };
+    match s.is_variable(pkg) {
+        true => format!("at most {} {}", size, bytes),
+        false => format!("{} {}", size, bytes)
+    }
+}
+
+/// Two fields at the same position are encoded the same way: renamed fields, or reserved bytes taking the place of a field with the same size
+fn interchangeable(o: &StructField, n: &StructField, old: &Package, new: &Package, renames: &HashMap<String, String>) -> bool {
+    let fixed = |f: &StructField, pkg: &Package| f.bits.is_none() && !f.is_variable(pkg);
+
+    match o.reserved || n.reserved {
+        true => fixed(o, old) && fixed(n, new) && o.size(old) == n.size(new),
+        false => wire_type(o, renames) == wire_type(n, &HashMap::new())
+    }
+}
+
+fn check_struct(o: &Struct, n: &Struct, old: &Package, new: &Package, renames: &HashMap<String, String>, report: &mut Report) {
+    if o.id() != n.id() {
+        let msg = match o.name == n.name {
+            true => format!("The id of `{}` changed from {:#010X} to {:#010X}.", n.name, o.id(), n.id()),
+            false => format!("Struct `{}` renamed to `{}`, its id changed from {:#010X} to {:#010X}.", o.name, n.name, o.id(), n.id())
+        };
+        report.new.push(breaking(msg.as_str(), 22, n.span)
+            .with_help(format!("keep the old id with `@id({:#010X})`.", o.id()).as_str()));
+    }
+
+    if size_str(o, old) != size_str(n, new) {
+        report.new.push(breaking(format!("The size of `{}` changed from {} to {}.", n.name, size_str(o, old), size_str(n, new)).as_str(), 23, n.span));
+    }
+
+    let old_fields: Vec<&StructField> = o.fields.values().collect();
+    let new_fields: Vec<&StructField> = n.fields.values().collect();
+    let mut old_paired = vec![false; old_fields.len()];
+    let mut new_paired = vec![false; new_fields.len()];
+    // Index of the field in the old struct and in the new one
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+
+    for (i, f) in old_fields.iter().enumerate().filter(|(_, f)| !f.reserved) {
+        if let Some(j) = n.fields.get_index_of(&f.name) {
+            pairs.push((i, j));
+            old_paired[i] = true;
+            new_paired[j] = true;
+        }
+    }
+    for i in 0..old_fields.len().min(new_fields.len()) {
+        if !old_paired[i] && !new_paired[i] && interchangeable(old_fields[i], new_fields[i], old, new, renames) {
+            pairs.push((i, i));
+            old_paired[i] = true;
+            new_paired[i] = true;
+        }
+    }
+    pairs.sort();
+
+    for (f, _) in old_fields.iter().zip(&old_paired).filter(|(_, paired)| !**paired) {
+        let d = match f.reserved {
+            true => breaking(format!("Reserved bytes of `{}` removed.", o.name).as_str(), 24, f.span),
+            false => breaking(format!("Field `{}.{}` removed.", o.name, f.name).as_str(), 24, f.span)
+        };
+        let d = match !f.reserved && f.bits.is_none() && !f.is_variable(old) {
+            true => d.with_help(format!("replace it with `reserved {};` to keep the position of the fields after it.", f.size(old)).as_str()),
+            false => d
+        };
+        report.old.push(d);
+    }
+
+    for (f, _) in new_fields.iter().zip(&new_paired).filter(|(_, paired)| !**paired) {
+        let what = match f.reserved {
+            true => format!("Reserved bytes of `{}`", n.name),
+            false => format!("Field `{}.{}`", n.name, f.name)
+        };
+        report.new.push(breaking(format!("{} added.", what).as_str(), 24, f.span)
+            .with_help("new fields can only take the place of reserved bytes with the same size."));
+    }
+
+    for &(i, j) in &pairs {
+        let (of, nf) = (old_fields[i], new_fields[j]);
+        if !interchangeable(of, nf, old, new, renames) {
+            report.new.push(breaking(
+                format!("Field `{}.{}` changed type from `{}` to `{}`.", n.name, nf.name, wire_type(of, renames), wire_type(nf, &HashMap::new())).as_str(),
+                24,
+                nf.type_span
+            ));
+        }
+    }
+
+    // The fields that keep their order are the longest increasing run of new positions, the other ones moved
+    let kept = longest_increasing(&pairs.iter().map(|(_, j)| *j).collect::<Vec<usize>>());
+    for (k, &(i, j)) in pairs.iter().enumerate() {
+        if kept.contains(&k) {
+            continue;
+        }
+
+        let nf = new_fields[j];
+        let d = breaking(format!("Field `{}.{}` moved.", n.name, nf.name).as_str(), 24, nf.span);
+        let d = match old_fields[..i].iter().rev().find(|p| !p.reserved) {
+            Some(prev) => d.with_help(format!("fields are encoded in declaration order, it was after `{}`.", prev.name).as_str()),
+            None => d.with_help("fields are encoded in declaration order, it was the first field.")
+        };
+        report.new.push(d);
+    }
+}
+
+/// Indexes of the longest strictly increasing subsequence of `values`
+fn longest_increasing(values: &[usize]) -> HashSet<usize> {
+    // Length of the longest run ending at every element and the element before it
+    let mut len = vec![1; values.len()];
+    let mut prev: Vec<Option<usize>> = vec![None; values.len()];
+    for i in 0..values.len() {
+        for j in 0..i {
+            if values[j] < values[i] && len[j] + 1 > len[i] {
+                len[i] = len[j] + 1;
+                prev[i] = Some(j);
+            }
+        }
+    }
+
+    let mut out = HashSet::new();
+    let mut last = (0..values.len()).max_by_key(|i| (len[*i], std::cmp::Reverse(*i)));
+    while let Some(i) = last {
+        out.insert(i);
+        last = prev[i];
+    }
+
+    out
+}
+
+fn check_enum(o: &Enum, n: &Enum, report: &mut Report) {
+    if o.repr.str() != n.repr.str() {
+        report.new.push(breaking(format!("The type of `{}` changed from `{}` to `{}`.", n.name, o.repr.str(), n.repr.str()).as_str(), 25, n.span));
+    }
+    if o.flags != n.flags {
+        let kind = |flags: bool| if flags { "flags" } else { "an enum" };
+        report.new.push(breaking(format!("`{}` changed from {} to {}.", n.name, kind(o.flags), kind(n.flags)).as_str(), 25, n.span));
+    }
+
+    let same_value = |a: &EnumVariant, b: &EnumVariant| a.value == b.value;
+    for (ov, nv) in pair(&o.variants, &n.variants, &[&same_value]) {
+        check_variant(&o.name, (&ov.name, ov.value, ov.span), nv.map(|nv| (nv.name.as_str(), nv.value, nv.span)), report);
+    }
+}
+
+fn check_union(o: &Union, n: &Union, renames: &HashMap<String, String>, report: &mut Report) {
+    if o.repr.str() != n.repr.str() {
+        report.new.push(breaking(format!("The type of `{}` changed from `{}` to `{}`.", n.name, o.repr.str(), n.repr.str()).as_str(), 25, n.span));
+    }
+
+    let same_value = |a: &UnionVariant, b: &UnionVariant| a.value == b.value;
+    for (ov, nv) in pair(&o.variants, &n.variants, &[&same_value]) {
+        check_variant(&o.name, (&ov.name, ov.value, ov.span), nv.map(|nv| (nv.name.as_str(), nv.value, nv.span)), report);
+
+        if let Some(nv) = nv {
+            let t = renames.get(ov.t.str()).map_or(ov.t.str(), |t| t.as_str());
+            if t != nv.t.str() {
+                report.new.push(breaking(format!("The struct of `{}.{}` changed from `{}` to `{}`.", n.name, nv.name, t, nv.t.str()).as_str(), 25, nv.type_span));
+            }
+        }
+    }
+}
+
+/// Compare the name, value and position of a variant of an enum or of a union with the new one, if there is one
+fn check_variant(parent: &str, (name, value, span): (&str, u32, Span), new: Option<(&str, u32, Span)>, report: &mut Report) {
+    match new {
+        None => report.old.push(breaking(format!("Variant `{}.{}` removed.", parent, name).as_str(), 25, span)
+            .with_help("the values encoded with it can't be decoded by the new schema.")),
+        Some((new_name, new_value, new_span)) if new_value != value => report.new.push(
+            breaking(format!("The value of `{}.{}` changed from {} to {}.", parent, new_name, value, new_value).as_str(), 25, new_span)
+                .with_help(format!("set it explicitly with `{} = {};`.", new_name, value).as_str())
+        ),
+        Some(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compare two schemas with the structs, enums and unions declared in `old` and `new`
+    fn compare(old: &str, new: &str) -> Report {
+        let parse = |body: &str| {
+            let src = format!("version 4.0.0;\npackage Test;\n{}", body);
+            crate::parse(&src).unwrap_or_else(|d| panic!("{}", d.render("test.iris", &src))).package
+        };
+
+        check(&parse(old), &parse(new))
+    }
+
+    /// Messages of the breaking changes, the removed declarations first
+    fn messages(report: &Report) -> Vec<String> {
+        report.old.iter().chain(report.new.iter()).map(|d| d.message.clone()).collect()
+    }
+
+    #[test]
+    fn scale_change_is_breaking() {
+        let report = compare("struct S { i16 t @scale(0.1); }", "struct S { i16 t @scale(0.01); }");
+        assert_eq!(messages(&report), ["Field `S.t` changed type from `i16 @scale(0.1)` to `i16 @scale(0.01)`."]);
+        assert_eq!(report.new.list[0].code, 24);
+    }
+
+    #[test]
+    fn offset_change_is_breaking() {
+        let report = compare("struct S { i16 t @scale(0.1) @offset(-40); }", "struct S { i16 t @scale(0.1); }");
+        assert_eq!(messages(&report), ["Field `S.t` changed type from `i16 @scale(0.1) @offset(-40)` to `i16 @scale(0.1)`."]);
+    }
+
+    #[test]
+    fn scaling_added_is_breaking() {
+        let report = compare("struct S { u16 t; }", "struct S { u16 t @offset(5); }");
+        assert_eq!(messages(&report), ["Field `S.t` changed type from `u16` to `u16 @offset(5)`."]);
+    }
+
+    #[test]
+    fn moved_field() {
+        let report = compare("struct S { u8 a; u16 b; u32 c; u8 d; }", "struct S { u8 a; u32 c; u16 b; u8 d; }");
+        assert_eq!(messages(&report), ["Field `S.c` moved."]);
+        assert_eq!(report.new.list[0].help.as_deref(), Some("fields are encoded in declaration order, it was after `b`."));
+    }
+
+    #[test]
+    fn renamed_fields_are_compatible() {
+        let report = compare("struct S { u8 a; u16[2] b; Mode m; } enum Mode { A; }", "struct S { u8 x; u16[2] y; State m; } enum State { A; }");
+        assert!(report.is_empty(), "{:?}", messages(&report));
+    }
+
+    #[test]
+    fn retyped_field() {
+        let report = compare("struct S { u8 a; u16 b; }", "struct S { u8 a; u32 b; }");
+        assert_eq!(messages(&report), ["The size of `S` changed from 3 bytes to 5 bytes.", "Field `S.b` changed type from `u16` to `u32`."]);
+    }
+
+    #[test]
+    fn removed_field() {
+        let report = compare("struct S { u8 a; u16 b; u8 c; }", "struct S { u8 a; u8 c; }");
+        assert_eq!(messages(&report), ["Field `S.b` removed.", "The size of `S` changed from 4 bytes to 2 bytes."]);
+        assert_eq!(report.old.list[0].help.as_deref(), Some("replace it with `reserved 2;` to keep the position of the fields after it."));
+    }
+
+    #[test]
+    fn reserved_replacement() {
+        // A field replaced by reserved bytes and reserved bytes used by a new field, with the same size
+        let report = compare("struct S { u8 a; u32 old; reserved 2; }", "struct S { u8 a; reserved 4; u16 new; }");
+        assert!(report.is_empty(), "{:?}", messages(&report));
+
+        let report = compare("struct S { u8 a; u32 old; u8 b; }", "struct S { u8 a; reserved 2; u8 b; }");
+        assert_eq!(messages(&report), ["Field `S.old` removed.", "The size of `S` changed from 6 bytes to 4 bytes.", "Reserved bytes of `S` added."]);
+    }
+
+    #[test]
+    fn append_only_change_is_compatible() {
+        let report = compare(
+            "enum Mode : u8 { IDLE; ARMED; } struct A { u8 a; } union P { A a; } struct S { Mode m; P p; }",
+            "enum Mode : u8 { IDLE; ARMED; FLIGHT; } struct A { u8 a; } struct B { u8 b; } union P { A a; B b; } struct S { Mode m; P p; } enum New { X; }"
+        );
+        assert!(report.is_empty(), "{:?}", messages(&report));
+    }
+
+    #[test]
+    fn appended_field_changes_the_size() {
+        let report = compare("struct S { u8 a; }", "struct S { u8 a; u8 b; }");
+        assert_eq!(messages(&report), ["The size of `S` changed from 1 byte to 2 bytes.", "Field `S.b` added."]);
+    }
+
+    #[test]
+    fn changed_enum_values() {
+        let report = compare("enum E : u8 { A; B; C; D; }", "enum E : u16 { A; B = 5; RENAMED = 2; }");
+        assert_eq!(messages(&report), [
+            "Variant `E.D` removed.",
+            "The type of `E` changed from `u8` to `u16`.",
+            "The value of `E.B` changed from 1 to 5."
+        ]);
+        assert_eq!(report.new.list[1].help.as_deref(), Some("set it explicitly with `B = 1;`."));
+    }
+
+    #[test]
+    fn flags_and_enums_are_different() {
+        let report = compare("enum E { A; B; }", "flags E { A; B; }");
+        assert_eq!(messages(&report), ["`E` changed from an enum to flags.", "The value of `E.A` changed from 0 to 1.", "The value of `E.B` changed from 1 to 2."]);
+    }
+
+    #[test]
+    fn changed_union() {
+        let report = compare(
+            "struct A { u8 a; } struct B { u8 b; } struct C { u16 c; } union P : u8 { A a; B b; C c; }",
+            "struct A { u8 a; } struct B { u8 b; } struct C { u16 c; } union P : u16 { A a = 3; C b = 1; }"
+        );
+        assert_eq!(messages(&report), [
+            "Variant `P.c` removed.",
+            "The type of `P` changed from `u8` to `u16`.",
+            "The value of `P.a` changed from 0 to 3.",
+            "The struct of `P.b` changed from `B` to `C`."
+        ]);
+    }
+
+    #[test]
+    fn renamed_struct() {
+        let report = compare("struct Old { u8 a; u16 b; }", "struct New { u8 a; u16 b; }");
+        assert_eq!(report.new.list.len(), 1);
+        assert!(report.new.list[0].message.starts_with("Struct `Old` renamed to `New`, its id changed"));
+
+        let report = compare("@id(7) struct Old { u8 a; }", "@id(7) struct New { u8 a; }");
+        assert!(report.is_empty(), "{:?}", messages(&report));
+    }
+
+    #[test]
+    fn removed_struct() {
+        let report = compare("struct A { u8 a; } struct B { u16 b; }", "struct A { u8 a; }");
+        assert_eq!(messages(&report), ["Struct `B` removed."]);
+        assert_eq!(report.old.list[0].span.row, 3);
+    }
+
+    #[test]
+    fn longest_increasing_keeps_first_run() {
+        assert_eq!(longest_increasing(&[0, 2, 1, 3]), HashSet::from([0, 1, 3]));
+        assert_eq!(longest_increasing(&[3, 0, 1, 2]), HashSet::from([1, 2, 3]));
+        assert_eq!(longest_increasing(&[]), HashSet::new());
+    }
+}
@@ -1,10 +1,29 @@
-pub fn error(ctx: ErrorType, msg: &str, code: u32, row: u32, col: u32) {
-    panic!("{}:{}:{} Error E{}: {}", ctx.str(), row, col, code, msg);
+use std::fmt;
+
+/// Create an error diagnostic pointing at the given position of the source file.
+pub fn error(ctx: ErrorType, msg: &str, code: u32, row: u32, col: u32) -> Diagnostic {
+    Diagnostic {
+        ctx,
+        code,
+        severity: Severity::Error,
+        span: Span { row, col, len: 1 },
+        message: msg.to_string(),
+        help: None
+    }
+}
+
+/// Create an error diagnostic covering the given span of the source file.
+pub fn error_at(ctx: ErrorType, msg: &str, code: u32, span: Span) -> Diagnostic {
+    error(ctx, msg, code, span.row, span.col).with_len(span.len)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ErrorType {
     Tokenizer,
     Parser,
+    Semantic,
+    /// Breaking change found comparing two versions of a schema
+    Compatibility,
     //CodeGenerator
 }
 
@@ -13,7 +32,283 @@ impl ErrorType {
         match self {
             ErrorType::Tokenizer => "Tokenizer",
             ErrorType::Parser => "Parser",
+            ErrorType::Semantic => "Semantic",
+            ErrorType::Compatibility => "Compatibility",
             //ErrorType::CodeGenerator => "Code generator"
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+impl Severity {
+    pub fn str(&self) -> &str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        }
+    }
+}
+
+/// Position in the source file, rows and columns start from 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub row: u32,
+    pub col: u32,
+    pub len: u32
+}
+
+/// A problem found in the source file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub ctx: ErrorType,
+    pub code: u32,
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+    pub help: Option<String>
+}
+
+impl Diagnostic {
+    pub fn with_help(mut self, help: &str) -> Diagnostic {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    pub fn with_len(mut self, len: u32) -> Diagnostic {
+        self.span.len = len.max(1);
+        self
+    }
+
+    /// Format the diagnostic showing the offending line of `src` with a caret under the error.
+    pub fn render(&self, file: &str, src: &str) -> String {
+        let mut out = String::new();
+
+        let gutter = " ".repeat(self.span.row.to_string().len());
+
+        out.push_str(format!("{}[E{}]: {}\n", self.severity.str(), self.code, self.message).as_str());
+        out.push_str(format!("{}--> {}:{}:{}\n", gutter, file, self.span.row, self.span.col).as_str());
+
+        if let Some(line) = src.lines().nth(self.span.row.saturating_sub(1) as usize) {
+            out.push_str(format!("{} |\n", gutter).as_str());
+            out.push_str(format!("{} | {}\n", self.span.row, line).as_str());
+            out.push_str(format!(
+                "{} | {}{}\n",
+                gutter,
+                " ".repeat(self.span.col.saturating_sub(1) as usize),
+                "^".repeat(self.span.len as usize)
+            ).as_str());
+        }
+
+        if let Some(help) = &self.help {
+            out.push_str(format!("{} = help: {}\n", gutter, help).as_str());
+        }
+
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{} {} E{}: {}", self.ctx.str(), self.span.row, self.span.col, self.severity.str(), self.code, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// All the problems found in a source file, sorted by position.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    pub list: Vec<Diagnostic>,
+    /// Set when the analysis stopped early because too many errors were found
+    pub truncated: bool
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics { list: Vec::new(), truncated: false }
+    }
+
+    pub fn push(&mut self, d: Diagnostic) {
+        self.list.push(d);
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        self.list.extend(other.list);
+        self.truncated |= other.truncated;
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn errors(&self) -> usize {
+        self.list.iter().filter(|d| d.severity == Severity::Error).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors() > 0
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.list.iter()
+    }
+
+    pub fn sort(&mut self) {
+        self.list.sort_by_key(|d| (d.span.row, d.span.col));
+    }
+
+    /// Format every diagnostic followed by a summary line
+    pub fn render(&self, file: &str, src: &str) -> String {
+        let mut out = String::new();
+
+        for d in &self.list {
+            out.push_str(d.render(file, src).as_str());
+            out.push('\n');
+        }
+
+        let errors = self.errors();
+        if errors > 0 {
+            out.push_str(format!(
+                "error: could not compile `{}` due to {} previous error{}{}\n",
+                file,
+                errors,
+                if errors == 1 { "" } else { "s" },
+                if self.truncated { ", stopped early" } else { "" }
+            ).as_str());
+        }
+
+        out
+    }
+}
+
+/// The diagnostics of one of the files of a schema, with the file needed to show them
+#[derive(Debug, Clone)]
+pub struct FileDiagnostics {
+    pub path: String,
+    pub src: String,
+    pub diagnostics: Diagnostics
+}
+
+impl FileDiagnostics {
+    pub fn render(&self) -> String {
+        self.diagnostics.render(&self.path, &self.src)
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(d: Diagnostic) -> Diagnostics {
+        Diagnostics { list: vec![d], truncated: false }
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.iter()
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for d in &self.list {
+            writeln!(f, "{}", d)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "version 4.0.0;\npackage Test;\nstruct Engine {\n    u16 rpm\n}\n";
+
+    #[test]
+    fn render_points_at_the_column() {
+        let d = error(ErrorType::Parser, "Expected a semicolon `;`.", 3, 4, 12);
+
+        assert_eq!(d.render("engine.iris", SRC), concat!(
+            "error[E3]: Expected a semicolon `;`.\n",
+            " --> engine.iris:4:12\n",
+            "  |\n",
+            "4 |     u16 rpm\n",
+            "  |            ^\n"
+        ));
+    }
+
+    #[test]
+    fn render_underlines_the_span() {
+        let d = error_at(ErrorType::Semantic, "Unknown type.", 10, Span { row: 4, col: 5, len: 3 })
+            .with_help("declare the type.");
+
+        assert_eq!(d.render("engine.iris", SRC), concat!(
+            "error[E10]: Unknown type.\n",
+            " --> engine.iris:4:5\n",
+            "  |\n",
+            "4 |     u16 rpm\n",
+            "  |     ^^^\n",
+            "  = help: declare the type.\n"
+        ));
+    }
+
+    /// The gutter is as wide as the number of the row
+    #[test]
+    fn render_aligns_the_gutter() {
+        let src = format!("{}struct S {{ u8 a; }}\n", "\n".repeat(11));
+        let d = error_at(ErrorType::Parser, "Name already used.", 6, Span { row: 12, col: 8, len: 1 });
+
+        assert_eq!(d.render("s.iris", &src), concat!(
+            "error[E6]: Name already used.\n",
+            "  --> s.iris:12:8\n",
+            "   |\n",
+            "12 | struct S { u8 a; }\n",
+            "   |        ^\n"
+        ));
+    }
+
+    /// Errors at the end of the file can be after the last line
+    #[test]
+    fn render_without_the_line() {
+        let d = error(ErrorType::Parser, "Opened curly brackets not closed.", 5, 9, 1).with_help("add the missing `}`.");
+
+        assert_eq!(d.render("engine.iris", SRC), concat!(
+            "error[E5]: Opened curly brackets not closed.\n",
+            " --> engine.iris:9:1\n",
+            "  = help: add the missing `}`.\n"
+        ));
+    }
+
+    #[test]
+    fn with_len_is_at_least_one() {
+        assert_eq!(error(ErrorType::Parser, "", 2, 1, 1).with_len(0).span.len, 1);
+    }
+
+    #[test]
+    fn render_all_sorted_with_summary() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(error(ErrorType::Parser, "Second.", 2, 4, 5));
+        diagnostics.push(error(ErrorType::Parser, "First.", 2, 3, 1));
+        diagnostics.sort();
+        diagnostics.truncated = true;
+
+        let rendered = diagnostics.render("engine.iris", SRC);
+        assert!(rendered.find("First.").unwrap() < rendered.find("Second.").unwrap());
+        assert!(rendered.ends_with("|     ^\n\nerror: could not compile `engine.iris` due to 2 previous errors, stopped early\n"), "{}", rendered);
+
+        let rendered = Diagnostics::from(error(ErrorType::Parser, "Only.", 2, 1, 1)).render("engine.iris", SRC);
+        assert!(rendered.ends_with("\nerror: could not compile `engine.iris` due to 1 previous error\n"), "{}", rendered);
+    }
+}
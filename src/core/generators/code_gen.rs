@@ -1,8 +1,9 @@
-use crate::core::ast::Package;
+use crate::core::ast::{ComplexTypes, FieldType, Limits, Package, Struct, StructField};
 
 use super::{cpp, python, rust};
 
 
+/// Languages supported for code generation
 pub enum Langs {
     RUST(rust::Rust),
     PYTHON(python::Python),
@@ -10,15 +11,22 @@ pub enum Langs {
 }
 
 impl Langs {
-    pub fn from_string(s: &str) -> Langs {
+    /// Names accepted by `Langs::from_string`, one for every language
+    pub const NAMES: [&'static str; 3] = ["rust", "python", "c++"];
+
+    pub fn from_string(s: &str) -> Option<Langs> {
         match s {
-            "rust" | "rs" => Langs::RUST(rust::Rust {  }),
-            "python" | "py" => Langs::PYTHON(python::Python {  }),
-            "c++" | "cpp" => Langs::CPP(cpp::CPP {  }),
-            _ => panic!("Error, specified language is not supported.")
+            "rust" | "rs" => Some(Langs::RUST(rust::Rust {  })),
+            "python" | "py" => Some(Langs::PYTHON(python::Python {  })),
+            "c++" | "cpp" => Some(Langs::CPP(cpp::CPP {  })),
+            _ => None
         }
     }
 
+    pub fn all() -> Vec<Langs> {
+        Langs::NAMES.iter().map(|n| Langs::from_string(n).unwrap()).collect()
+    }
+
     pub fn ext(&self) -> &str {
         match self {
             Langs::RUST(_) => "rs",
@@ -28,6 +36,188 @@ impl Langs {
     }
 }
 
+impl CodeGen for Langs {
+    fn gen_code(&self, package: &Package) -> String {
+        match self {
+            Langs::RUST(l) => l.gen_code(package),
+            Langs::PYTHON(l) => l.gen_code(package),
+            Langs::CPP(l) => l.gen_code(package)
+        }
+    }
+}
+
 pub trait CodeGen {
     fn gen_code(&self, package: &Package) -> String;
-}
\ No newline at end of file
+}
+
+/// Limits checked by `validate`, as literals that are valid in every language.
+/// Enums are compared with the value of the variant. A limit that can't be exceeded by the type of the field is `None`.
+pub fn gen_limits(field: &StructField, package: &Package) -> (Option<String>, Option<String>) {
+    match (&field.limits, &field.t) {
+        (Some(Limits::Range(min, max)), FieldType::PRIMITIVE(p)) => match p.limits() {
+            Some((low, high)) if field.scaling.is_none() => (
+                Some(*min as i128).filter(|v| *v > low).map(|v| v.to_string()),
+                Some(*max as i128).filter(|v| *v < high).map(|v| v.to_string())
+            ),
+            _ => (Some(format!("{:?}", min)), Some(format!("{:?}", max)))
+        },
+        (Some(Limits::Variants(min, max)), FieldType::COMPLEX(ComplexTypes::Enum(e))) => {
+            let enmn = package.get_enum(e);
+            let value = |limit: &Option<(String, _)>| limit.as_ref().map(|(v, _)| enmn.variants.get(v).unwrap().value);
+
+            (
+                value(min).filter(|v| *v > 0).map(|v| v.to_string()),
+                value(max).filter(|v| *v < enmn.max_value()).map(|v| v.to_string())
+            )
+        },
+        _ => (None, None)
+    }
+}
+
+/// Values of the variants of an enum field, as literals, a value received on the wire that is not one of them is invalid.
+/// `None` for the other types and for flags, any combination of flags is valid.
+pub fn gen_variant_values(field: &StructField, package: &Package) -> Option<Vec<String>> {
+    match &field.t {
+        FieldType::COMPLEX(ComplexTypes::Enum(e)) if !package.get_enum(e).flags => Some(package.get_enum(e).variants.values().map(|v| v.value.to_string()).collect()),
+        _ => None
+    }
+}
+
+/// The field, or one of the fields of its struct, has limits checked by `validate`.
+/// With `received` also the values that can't be stored by Rust but are kept as received by C++ and Python are checked:
+/// enum values and union tags that aren't variants, bounded arrays received with more elements than their maximum.
+pub fn is_checked(field: &StructField, package: &Package, received: bool) -> bool {
+    (received && field.bounded) || is_value_checked(field, package, received)
+}
+
+/// The values of the field, every element of an array, are checked by `validate`, like `is_checked` without the length of bounded arrays
+pub fn is_value_checked(field: &StructField, package: &Package, received: bool) -> bool {
+    match &field.t {
+        FieldType::COMPLEX(ComplexTypes::Struct(s)) => {
+            let (owner, s) = package.get_struct(s);
+            s.fields.values().any(|f| is_checked(f, owner, received))
+        },
+        FieldType::COMPLEX(ComplexTypes::Union(u)) => {
+            let (owner, u) = package.get_union(u);
+            received || u.variants.values().any(|v| is_variant_checked(&v.t, owner, received))
+        },
+        _ => gen_limits(field, package) != (None, None) || (received && gen_variant_values(field, package).is_some())
+    }
+}
+
+/// One of the fields of the struct of a union variant is checked by `validate`, like `is_checked`
+pub fn is_variant_checked(t: &FieldType, package: &Package, received: bool) -> bool {
+    let FieldType::COMPLEX(ComplexTypes::Struct(s)) = t else {
+        unreachable!("Union variants are checked by the semantic analysis.");
+    };
+    let (owner, s) = package.get_struct(s);
+
+    s.fields.values().any(|f| is_checked(f, owner, received))
+}
+
+/// Paths of the values of a struct that can be invalid in the received bytes, relative to the struct:
+/// the enum fields that aren't flags, the tags of the union fields and the length of the bounded arrays,
+/// also in nested structs (`engine.mode`) and variants (`payload.ignite.mode`).
+/// The Rust decoders can't store them, they report these paths and `decode_validated` returns them.
+pub fn invalid_paths(strc: &Struct, package: &Package) -> Vec<String> {
+    strc.fields.values()
+        .flat_map(|f| field_invalid_paths(f, package).into_iter().map(|p| join_path(&f.name, &p)))
+        .collect()
+}
+
+/// Like `invalid_paths` for a field, relative to it, the empty path is the field itself
+pub fn field_invalid_paths(field: &StructField, package: &Package) -> Vec<String> {
+    let mut paths = match field.reserved {
+        true => Vec::new(),
+        false => type_invalid_paths(&field.t, package)
+    };
+    // A length larger than the maximum is reported with the path of the field
+    if field.bounded && !paths.contains(&String::new()) {
+        paths.insert(0, String::new());
+    }
+
+    paths
+}
+
+/// Like `invalid_paths` for a value of type `t`, the empty path is the value itself
+pub fn type_invalid_paths(t: &FieldType, package: &Package) -> Vec<String> {
+    match t {
+        FieldType::COMPLEX(ComplexTypes::Struct(s)) => {
+            let (owner, s) = package.get_struct(s);
+            invalid_paths(s, owner)
+        },
+        FieldType::COMPLEX(ComplexTypes::Enum(e)) if !package.get_enum(e).flags => vec![String::new()],
+        FieldType::COMPLEX(ComplexTypes::Union(u)) => {
+            let (owner, u) = package.get_union(u);
+            let mut paths = vec![String::new()];
+            for v in u.variants.values() {
+                let (owner, s) = owner.get_struct(v.t.str());
+                paths.extend(invalid_paths(s, owner).into_iter().map(|p| join_path(&v.name, &p)));
+            }
+            paths
+        },
+        _ => Vec::new()
+    }
+}
+
+/// `name.path`, or `name` when the path is empty
+pub fn join_path(name: &str, path: &str) -> String {
+    match path.is_empty() {
+        true => name.to_string(),
+        false => format!("{}.{}", name, path)
+    }
+}
+
+/// Lines of the documentation of a struct or field, the doc comment followed by the unit
+pub fn doc_lines(doc: &Option<String>, unit: &Option<String>) -> Vec<String> {
+    let mut lines: Vec<String> = doc.iter().flat_map(|d| d.lines()).map(String::from).collect();
+
+    if let Some(unit) = unit {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("Unit: {}", unit));
+    }
+
+    lines
+}
+
+/// `///` comments for C++, safe from the preprocessor: a backslash at the end of a line would join the next line of code
+/// to the comment, so the trailing backslashes are removed, and `*/` is split in case the comment ends up in a block comment
+pub fn gen_cpp_comment(lines: &[String]) -> String {
+    let lines: Vec<String> = lines.iter()
+        .map(|l| l.trim_end_matches(|c: char| c == '\\' || c.is_whitespace()).replace("*/", "* /"))
+        .collect();
+
+    gen_comment(&lines, "///")
+}
+
+/// One comment starting with `prefix` for every line, e.g. `///` for Rust
+pub fn gen_comment(lines: &[String], prefix: &str) -> String {
+    let mut out = String::new();
+
+    for l in lines {
+        match l.is_empty() {
+            true => out.push_str(format!("{}\n", prefix).as_str()),
+            false => out.push_str(format!("{} {}\n", prefix, l).as_str())
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpp_comment_drops_trailing_backslash() {
+        let lines = vec![String::from("Path C:\\logs\\"), String::from("end \\  "), String::new(), String::from("a \\ b")];
+        assert_eq!(gen_cpp_comment(&lines), "/// Path C:\\logs\n/// end\n///\n/// a \\ b\n");
+    }
+
+    #[test]
+    fn cpp_comment_splits_block_comment_end() {
+        assert_eq!(gen_cpp_comment(&[String::from("a */ b")]), "/// a * / b\n");
+    }
+}
@@ -9,6 +9,8 @@ pub mod types;
 pub mod field;
 pub mod r#struct;
 pub mod enumeration;
+pub mod union;
+pub mod constant;
 pub mod package;
 
 pub struct CPP {  }
@@ -19,8 +21,18 @@ impl CodeGen for CPP {
         let mut out = String::new();
 
         out.push_str("#pragma once\n");
-        out.push_str("#include <stddef.h>");
-        out.push_str("#include <stdint.h>");
+        out.push_str("#include <stddef.h>\n");
+        out.push_str("#include <stdint.h>\n");
+        // The generated code uses the deprecated fields, only the uses outside of this file warn
+        if package.has_deprecated() {
+            out.push_str("#if defined(__GNUC__)\n");
+            out.push_str("#pragma GCC diagnostic push\n");
+            out.push_str("#pragma GCC diagnostic ignored \"-Wdeprecated-declarations\"\n");
+            out.push_str("#elif defined(_MSC_VER)\n");
+            out.push_str("#pragma warning(push)\n");
+            out.push_str("#pragma warning(disable: 4996)\n");
+            out.push_str("#endif\n");
+        }
         out.push_str("namespace iris {\n");
         
         out.push_str("typedef uint8_t byte;\n");
@@ -39,8 +51,70 @@ impl CodeGen for CPP {
         out.push_str("}\n");
         out.push_str("return data;\n");
         out.push_str("}\n");
+        out.push_str("inline void write_bits(byte *buffer, size_t offset, size_t width, uint64_t value) {\n");
+        out.push_str("for (size_t b = 0; b < width; b++) {\n");
+        out.push_str("size_t bit = offset + b;\n");
+        out.push_str("byte mask = 0x80 >> (bit % 8);\n");
+        out.push_str("if ((value >> (width - 1 - b)) & 1) { buffer[bit / 8] |= mask; }\n");
+        out.push_str("else { buffer[bit / 8] &= ~mask; }\n");
+        out.push_str("}\n");
+        out.push_str("}\n");
+        out.push_str("inline uint64_t read_bits(const byte *buffer, size_t offset, size_t width) {\n");
+        out.push_str("uint64_t value = 0;\n");
+        out.push_str("for (size_t b = 0; b < width; b++) {\n");
+        out.push_str("size_t bit = offset + b;\n");
+        out.push_str("value = (value << 1) | ((buffer[bit / 8] >> (7 - bit % 8)) & 1);\n");
+        out.push_str("}\n");
+        out.push_str("return value;\n");
+        out.push_str("}\n");
+        out.push_str("template <typename T>\n");
+        out.push_str("inline T to_fixed(double value, double scale, double offset, T min, T max) {\n");
+        out.push_str("double raw = (value - offset) / scale;\n");
+        out.push_str("if (raw != raw) { return 0; }\n");
+        out.push_str("if (raw <= (double)min) { return min; }\n");
+        out.push_str("if (raw >= (double)max) { return max; }\n");
+        out.push_str("return (T)(raw < 0 ? raw - 0.5 : raw + 0.5);\n");
+        out.push_str("}\n");
+        // `dest` has a char more than the encoded bytes, it's always the terminator
+        out.push_str("inline bool set_string(char *dest, const char *src, size_t size) {\n");
+        out.push_str("size_t len = 0;\n");
+        out.push_str("while (src[len] != '\\0') { if (++len > size) { return false; } }\n");
+        out.push_str("for (size_t i = 0; i <= size; i++) { dest[i] = i < len ? src[i] : '\\0'; }\n");
+        out.push_str("return true;\n");
+        out.push_str("}\n");
+        // Used for `T[<=N]` fields, only the first `length()` elements are encoded
+        out.push_str("template <typename T, size_t N>\n");
+        out.push_str("class BoundedArray {\n");
+        out.push_str("size_t len = 0;\n");
+        out.push_str("bool cut = false;\n");
+        out.push_str("public:\n");
+        out.push_str("static const size_t CAPACITY = N;\n");
+        out.push_str("T data[N];\n");
+        out.push_str("size_t length() const { return len; }\n");
+        out.push_str("/// Set the number of elements, return false and keep N elements if there are more\n");
+        out.push_str("bool resize(size_t n) { len = n < N ? n : N; cut = n > N; return !cut; }\n");
+        out.push_str("/// The last `resize` had more than N elements, like a received length larger than the maximum\n");
+        out.push_str("bool truncated() const { return cut; }\n");
+        out.push_str("bool push_back(const T &value) {\n");
+        out.push_str("if (len == N) { return false; }\n");
+        out.push_str("data[len++] = value;\n");
+        out.push_str("return true;\n");
+        out.push_str("}\n");
+        out.push_str("void clear() { len = 0; cut = false; }\n");
+        out.push_str("T &operator[](size_t k) { return data[k]; }\n");
+        out.push_str("const T &operator[](size_t k) const { return data[k]; }\n");
+        out.push_str("};\n");
 
-        out.push_str(gen_code(&package).as_str());
+        // Imported packages come first, every package is a namespace
+        for p in package.all_packages() {
+            out.push_str(gen_code(p).as_str());
+        }
+
+        // A struct with a variable size can be shorter than the largest one
+        let length = |s: &str| match package.structs.get(s).unwrap().is_variable(package) {
+            true => format!("len <= {}::{}::MAX_BYTES_LENGTH", package.name.clone().unwrap(), s),
+            false => format!("len == {}::{}::BYTES_LENGTH", package.name.clone().unwrap(), s)
+        };
 
         out.push_str("template <typename T>\n");
         out.push_str("T decode(byte *raw, size_t len) {\n");
@@ -54,9 +128,9 @@ impl CodeGen for CPP {
             else {
                 out.push_str("else if");
             }
-            out.push_str(format!(" (struct_name_hash == {}::{}::NAME_HASH && len == {}::{}::BYTES_LENGTH) {{ return T::decode(raw); }}\n", package.name.clone().unwrap(), s, package.name.clone().unwrap(), s).as_str());
+            out.push_str(format!(" (struct_name_hash == {}::{}::NAME_HASH && {}) {{ return T::decode(raw); }}\n", package.name.clone().unwrap(), s, length(s)).as_str());
         }
-        if package.structs.len() > 0 {
+        if !package.structs.is_empty() {
             out.push_str("else { throw 1; }\n");            
         }
         out.push_str("}\n");
@@ -77,15 +151,23 @@ impl CodeGen for CPP {
             else {
                 out.push_str("else if");
             }
-            out.push_str(format!(" (struct_name_hash == {}::{}::NAME_HASH && len == {}::{}::BYTES_LENGTH) {{ return Structs::{}_{}; }}\n", package.name.clone().unwrap(), s, package.name.clone().unwrap(), s, package.name.clone().unwrap(), s).as_str());
+            out.push_str(format!(" (struct_name_hash == {}::{}::NAME_HASH && {}) {{ return Structs::{}_{}; }}\n", package.name.clone().unwrap(), s, length(s), package.name.clone().unwrap(), s).as_str());
         }
-        if package.structs.len() > 0 {
+        if !package.structs.is_empty() {
             out.push_str("else { throw 1; }\n");            
         }
         out.push_str("}\n");
 
         out.push_str("}\n");
 
+        if package.has_deprecated() {
+            out.push_str("#if defined(__GNUC__)\n");
+            out.push_str("#pragma GCC diagnostic pop\n");
+            out.push_str("#elif defined(_MSC_VER)\n");
+            out.push_str("#pragma warning(pop)\n");
+            out.push_str("#endif\n");
+        }
+
         out
     }
 }
\ No newline at end of file
@@ -0,0 +1,15 @@
+use crate::core::ast::{Constant, FieldType};
+
+use crate::core::generators::code_gen::{doc_lines, gen_cpp_comment};
+
+use super::{field::gen_value, types::gen_type_def};
+
+pub fn gen_code(constant: &Constant) -> String {
+    let mut out = String::new();
+    let t = FieldType::PRIMITIVE(constant.t.clone());
+
+    out.push_str(gen_cpp_comment(&doc_lines(&constant.doc, &None)).as_str());
+    out.push_str(format!("constexpr {} {} = {};\n", gen_type_def(&t), constant.name, gen_value(&constant.value, &t)).as_str());
+
+    out
+}
@@ -1,22 +1,44 @@
-use crate::core::ast::{Enum, Package};
+use crate::core::ast::{Enum, FieldType, Package};
+
+use super::types::gen_type_def;
 
 pub fn gen_code(enmn: &Enum, _package: &Package) -> String {
     let mut out = String::new();
+    let repr = gen_type_def(&FieldType::PRIMITIVE(enmn.repr.clone()));
 
     out.push_str(format!("class {} {{\n", enmn.name).as_str());
     out.push_str("public:\n");
-    out.push_str("enum Value : uint32_t {\n");
+    out.push_str(format!("enum Value : {} {{\n", repr).as_str());
     for v in enmn.variants.values() {
         out.push_str(format!("{} = {},\n", v.name, v.value).as_str());
     }
     out.push_str("};\n");
-    out.push_str("static const size_t BYTES_LENGTH = 4;\n");
-    out.push_str("iris::byte DATA_BUFFER[4] = {0};\n");
-    out.push_str("Value value;\n");
-    out.push_str(format!("{}() {{ }}\n", enmn.name).as_str());
-    out.push_str(format!("{}(uint32_t value) {{\n", enmn.name).as_str());
-    out.push_str("this->value = Value(value);\n");
-    out.push_str("}\n");
+    out.push_str(format!("static const size_t BYTES_LENGTH = {};\n", enmn.size()).as_str());
+    out.push_str(format!("iris::byte DATA_BUFFER[{}] = {{0}};\n", enmn.size()).as_str());
+    if enmn.flags {
+        // Any combination of the flags is valid, so the value is the integer and not `Value`
+        out.push_str(format!("{} value = 0;\n", repr).as_str());
+        out.push_str(format!("{}() {{ }}\n", enmn.name).as_str());
+        out.push_str(format!("{}({} value) {{\n", enmn.name, repr).as_str());
+        out.push_str("this->value = value;\n");
+        out.push_str("}\n");
+        out.push_str(format!("bool contains({} flags) const {{\n", repr).as_str());
+        out.push_str("return (this->value & flags) == flags;\n");
+        out.push_str("}\n");
+        out.push_str(format!("void set({} flags) {{\n", repr).as_str());
+        out.push_str("this->value |= flags;\n");
+        out.push_str("}\n");
+        out.push_str(format!("void clear({} flags) {{\n", repr).as_str());
+        out.push_str("this->value &= ~flags;\n");
+        out.push_str("}\n");
+    }
+    else {
+        out.push_str("Value value;\n");
+        out.push_str(format!("{}() {{ }}\n", enmn.name).as_str());
+        out.push_str(format!("{}({} value) {{\n", enmn.name, repr).as_str());
+        out.push_str("this->value = Value(value);\n");
+        out.push_str("}\n");
+    }
     out.push_str("inline iris::byte *to_be_bytes() {\n");
     out.push_str("return this->to_be_bytes(this->DATA_BUFFER);\n");
     out.push_str("}\n");
@@ -24,8 +46,8 @@ pub fn gen_code(enmn: &Enum, _package: &Package) -> String {
     out.push_str("iris::to_be_bytes(this->value, buffer);\n");
     out.push_str("return buffer;\n");
     out.push_str("}\n");
-    out.push_str("static Status from_be_bytes(iris::byte *raw) {\n");
-    out.push_str("return Status(iris::from_be_bytes<uint32_t>(raw));\n");
+    out.push_str(format!("static {} from_be_bytes(iris::byte *raw) {{\n", enmn.name).as_str());
+    out.push_str(format!("return {}(iris::from_be_bytes<{}>(raw));\n", enmn.name, repr).as_str());
     out.push_str("}\n");
     out.push_str("};\n");
 
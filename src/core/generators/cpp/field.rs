@@ -1,14 +1,96 @@
-use crate::core::ast::StructField;
+use crate::core::ast::{BitRange, ComplexTypes, FieldType, Package, PrimitiveTypes, StructField, Value};
 
-use super::types::gen_type_def;
+use crate::core::generators::code_gen::{doc_lines, gen_cpp_comment, gen_limits, gen_variant_values, is_checked, is_value_checked, is_variant_checked};
+
+use super::types::{gen_type_def, gen_type_path};
+
+/// Dimensions of the array, `[3][4]` for `f32[3][4]`
+fn gen_dims(field: &StructField) -> String {
+    field.array.iter().map(|n| format!("[{}]", n)).collect()
+}
+
+/// Nested loops over every element of the array, the counters are `j0`, `j1`, ...
+/// A bounded array is iterated up to its length, `value` is the struct containing it followed by `.` or `->`.
+/// Return the opening of the loops, the index of the element (`[j0][j1]`) and the closing brackets.
+fn gen_loops(field: &StructField, value: &str) -> (String, String, String) {
+    let mut open = String::new();
+    let mut index = String::new();
+    let mut close = String::new();
+
+    if field.bounded {
+        open.push_str(format!("for (size_t j0 = 0; j0 < {}{}.length(); j0++) {{\n", value, field.name).as_str());
+        return (open, String::from("[j0]"), String::from("}\n"));
+    }
+
+    for (d, n) in field.array.iter().enumerate() {
+        open.push_str(format!("for (int j{} = 0; j{} < {}; j{}++) {{\n", d, d, n, d).as_str());
+        index.push_str(format!("[j{}]", d).as_str());
+        close.push_str("}\n");
+    }
+
+    (open, index, close)
+}
+
+/// Type of a field, bounded arrays are stored in `iris::BoundedArray`
+fn gen_declaration_type(field: &StructField) -> String {
+    let t = match field.t {
+        FieldType::STRING(n) => format!("char[{}]", n + 1),
+        _ => gen_field_type(field)
+    };
+    format!("iris::BoundedArray<{}, {}>", t, field.elements())
+}
+
+/// Scaled fields are exposed as `double`
+fn gen_field_type(field: &StructField) -> String {
+    match field.scaling {
+        Some(_) => String::from("double"),
+        None => gen_type_def(&field.t)
+    }
+}
+
+/// Limits of an integer type, from the macros of `stdint.h`
+fn gen_type_limits(p: &PrimitiveTypes) -> (String, String) {
+    match p {
+        PrimitiveTypes::U8 | PrimitiveTypes::U16 | PrimitiveTypes::U32 | PrimitiveTypes::U64 => (String::from("0"), format!("UINT{}_MAX", p.size() * 8)),
+        _ => (format!("INT{}_MIN", p.size() * 8), format!("INT{}_MAX", p.size() * 8))
+    }
+}
+
+/// `[[deprecated]]` attribute of a field declared with `@deprecated`, or of its methods
+fn gen_deprecated(field: &StructField) -> String {
+    match field.deprecated.as_deref() {
+        Some("") => String::from("[[deprecated]] "),
+        Some(note) => format!("[[deprecated({:?})]] ", note),
+        None => String::new()
+    }
+}
 
 pub fn gen_prop_declaration(field: &StructField) -> String {
     let mut out = String::new();
 
-    out.push_str(format!("{} {}", gen_type_def(&field.t), field.name).as_str());
-    match field.array {
-        Some(n) => out.push_str(format!("[{}]", n).as_str()),
-        None => {}
+    // Reserved bytes are only in the encoded struct
+    if field.reserved {
+        return out;
+    }
+
+    out.push_str(gen_cpp_comment(&doc_lines(&field.doc, &field.unit)).as_str());
+    // Optional fields are private, they're used with the accessors
+    if field.optional {
+        match field.t {
+            FieldType::STRING(n) => out.push_str(format!("char {}[{}]{{}};\n", field.name, n + 1).as_str()),
+            _ => out.push_str(format!("{} {}{{}};\n", gen_field_type(field), field.name).as_str())
+        }
+        return out;
+    }
+    out.push_str(gen_deprecated(field).as_str());
+    if field.bounded {
+        out.push_str(format!("{} {};\n", gen_declaration_type(field), field.name).as_str());
+        return out;
+    }
+    out.push_str(format!("{} {}{}", gen_field_type(field), field.name, gen_dims(field)).as_str());
+    // One more char than the encoded bytes, so that a string of N bytes is still terminated
+    if let FieldType::STRING(n) = field.t {
+        out.push_str(format!("[{}]", n + 1).as_str());
     }
     out.push_str(";\n");
 
@@ -18,121 +100,410 @@ pub fn gen_prop_declaration(field: &StructField) -> String {
 pub fn gen_arg_declaration(field: &StructField) -> String {
     let mut out = String::new();
 
-    out.push_str(format!("{} ", gen_type_def(&field.t)).as_str());
-    match field.array {
-        Some(_n) => out.push_str("*"),
-        None => {}
+    if field.bounded {
+        out.push_str(format!("const {} &{}", gen_declaration_type(field), field.name).as_str());
+        return out;
+    }
+
+    match field.t {
+        FieldType::STRING(_) => out.push_str("const char *"),
+        _ => out.push_str(format!("{} ", gen_field_type(field)).as_str())
     }
     out.push_str(field.name.as_str());
+    out.push_str(gen_dims(field).as_str());
 
     out
 }
 
-pub fn gen_prop_init(field: &StructField) -> String {
+/// `has_`, `get_`, `set_` and `clear_` methods of an optional field, `bit` is its bit in `PRESENCE`
+pub fn gen_optional_accessors(field: &StructField, bit: u32) -> String {
     let mut out = String::new();
+    let name = &field.name;
+    let deprecated = gen_deprecated(field);
 
-    match field.array {
-        Some(n) => {
-            out.push_str(format!("for (int i = 0; i < {}; i++) {{ this->{}[i] = {}[i]; }}\n", n, field.name, field.name).as_str());
+    out.push_str(format!("{}bool has_{}() const {{ return iris::read_bits(this->PRESENCE, {}, 1) != 0; }}\n", deprecated, name, bit).as_str());
+    match &field.t {
+        FieldType::STRING(n) => {
+            out.push_str(format!("{}const char *get_{}() const {{ return this->{}; }}\n", deprecated, name, name).as_str());
+            out.push_str(format!("{}bool set_{}(const char *{}) {{\n", deprecated, name, name).as_str());
+            out.push_str(format!("if (!iris::set_string(this->{}, {}, {})) {{ return false; }}\n", name, name, n).as_str());
+            out.push_str(format!("iris::write_bits(this->PRESENCE, {}, 1, 1);\n", bit).as_str());
+            out.push_str("return true;\n");
+            out.push_str("}\n");
+        },
+        FieldType::COMPLEX(ComplexTypes::Struct(_) | ComplexTypes::Union(_)) => {
+            let t = gen_field_type(field);
+            out.push_str(format!("{}const {} &get_{}() const {{ return this->{}; }}\n", deprecated, t, name, name).as_str());
+            out.push_str(format!("{}void set_{}(const {} &{}) {{ this->{} = {}; iris::write_bits(this->PRESENCE, {}, 1, 1); }}\n", deprecated, name, t, name, name, name, bit).as_str());
         },
-        None => {
-            out.push_str(format!("this->{} = {};\n", field.name, field.name).as_str());
+        _ => {
+            let t = gen_field_type(field);
+            out.push_str(format!("{}{} get_{}() const {{ return this->{}; }}\n", deprecated, t, name, name).as_str());
+            out.push_str(format!("{}void set_{}({} {}) {{ this->{} = {}; iris::write_bits(this->PRESENCE, {}, 1, 1); }}\n", deprecated, name, t, name, name, name, bit).as_str());
         }
     }
+    out.push_str(format!("{}void clear_{}() {{ iris::write_bits(this->PRESENCE, {}, 1, 0); }}\n", deprecated, name, bit).as_str());
 
     out
 }
 
-pub fn gen_to_be_bytes_conv(field: &StructField) -> String {
+/// Strings are set with a method that checks that they fit in the field
+pub fn gen_string_setter(field: &StructField) -> String {
     let mut out = String::new();
 
-    match field.array {
-        Some(n) => {
-            out.push_str(format!("for (int j = 0; j < {}; j++) {{\n", n).as_str());
-            match &field.t {
-                crate::core::ast::FieldType::COMPLEX(complex_types) => {
-                    out.push_str(format!("this->{}[j].to_be_bytes(buffer + i);\n", field.name).as_str());
-                    out.push_str(format!("i += this->{}[j].BYTES_LENGTH", field.name).as_str());
-                    match complex_types {
-                        crate::core::ast::ComplexTypes::Struct(_) => out.push_str(" - 4"),
-                        crate::core::ast::ComplexTypes::Enum(_) => {},
-                        crate::core::ast::ComplexTypes::Unknown(_) => todo!(),
-                    };
-                    out.push_str(";\n");
-                },
-                crate::core::ast::FieldType::PRIMITIVE(_primitive_types) => {
-                    out.push_str(format!("iris::to_be_bytes(this->{}[j], buffer + i);\n", field.name).as_str());
-                    out.push_str(format!("i += sizeof(this->{}[j]);\n", field.name).as_str());
-                },
+    if field.optional {
+        return out;
+    }
+
+    if let FieldType::STRING(n) = field.t {
+        let mut args = String::new();
+        let mut index = String::new();
+        for d in 0..field.array.len() {
+            args.push_str(format!("int j{}, ", d).as_str());
+            index.push_str(format!("[j{}]", d).as_str());
+        }
+
+        out.push_str(format!("{}bool set_{}({}const char *{}) {{\n", gen_deprecated(field), field.name, args, field.name).as_str());
+        out.push_str(format!("return iris::set_string(this->{}{}, {}, {});\n", field.name, index, field.name, n).as_str());
+        out.push_str("}\n");
+    }
+
+    out
+}
+
+pub fn gen_prop_init(field: &StructField) -> String {
+    let mut out = String::new();
+    let (open, index, close) = gen_loops(field, "");
+
+    if field.bounded {
+        out.push_str(format!("this->{} = {};\n", field.name, field.name).as_str());
+        return out;
+    }
+
+    out.push_str(open.as_str());
+    match field.t {
+        FieldType::STRING(_) => {
+            let args: String = (0..field.array.len()).map(|d| format!("j{}, ", d)).collect();
+            out.push_str(format!("this->set_{}({}{}{});\n", field.name, args, field.name, index).as_str());
+        },
+        _ => out.push_str(format!("this->{}{} = {}{};\n", field.name, index, field.name, index).as_str())
+    }
+    out.push_str(close.as_str());
+
+    out
+}
+
+/// Return the path of the field if its value is out of the limits.
+/// `value` is the expression of the struct containing the field followed by `.` or `->`, the fields of nested structs are checked with the path `outer.inner`.
+pub fn gen_validate(field: &StructField, package: &Package, value: &str, path: &str) -> String {
+    let mut out = String::new();
+
+    if !is_checked(field, package, true) {
+        return out;
+    }
+
+    // Every element of an array is checked, the counters are unique inside nested structs
+    let depth = path.matches('.').count();
+    let mut expr = format!("{}{}", value, field.name);
+    let mut close = String::new();
+    // A missing optional field is valid
+    if field.optional {
+        out.push_str(format!("if ({}has_{}()) {{\n", value, field.name).as_str());
+        expr = format!("{}get_{}()", value, field.name);
+        close.push_str("}\n");
+    }
+    if field.bounded {
+        out.push_str(format!("if ({}.truncated()) {{ return \"{}{}\"; }}\n", expr, path, field.name).as_str());
+    }
+    if !is_value_checked(field, package, true) {
+        out.push_str(close.as_str());
+        return out;
+    }
+    for (d, n) in field.array.iter().enumerate() {
+        let j = format!("j{}_{}", depth, d);
+        match field.bounded {
+            true => out.push_str(format!("for (size_t {} = 0; {} < {}.length(); {}++) {{\n", j, j, expr, j).as_str()),
+            false => out.push_str(format!("for (int {} = 0; {} < {}; {}++) {{\n", j, j, n, j).as_str())
+        }
+        expr.push_str(format!("[{}]", j).as_str());
+        close.push_str("}\n");
+    }
+
+    match &field.t {
+        FieldType::COMPLEX(ComplexTypes::Struct(s)) => {
+            let (owner, s) = package.get_struct(s);
+            for f in s.fields.values() {
+                out.push_str(gen_validate(f, owner, format!("{}.", expr).as_str(), format!("{}{}.", path, field.name).as_str()).as_str());
             }
-            out.push_str("}\n");
         },
-        None => {
-            match &field.t {
-                crate::core::ast::FieldType::COMPLEX(complex_types) => {
-                    out.push_str(format!("this->{}.to_be_bytes(buffer + i);\n", field.name).as_str());
-                    out.push_str(format!("i += this->{}.BYTES_LENGTH", field.name).as_str());
-                    match complex_types {
-                        crate::core::ast::ComplexTypes::Struct(_) => out.push_str(" - 4"),
-                        crate::core::ast::ComplexTypes::Enum(_) => {},
-                        crate::core::ast::ComplexTypes::Unknown(_) => todo!(),
-                    };
-                    out.push_str(";\n");
-                },
-                crate::core::ast::FieldType::PRIMITIVE(_primitive_types) => {
-                    out.push_str(format!("iris::to_be_bytes(this->{}, buffer + i);\n", field.name).as_str());
-                    out.push_str(format!("i += sizeof(this->{});\n", field.name).as_str());
-                },
+        FieldType::COMPLEX(c @ ComplexTypes::Union(u)) => {
+            // The tag is kept as received, then only the struct of the current variant is checked, the fields are named `union.variant.field`
+            let (owner, u) = package.get_union(u);
+            let tags: Vec<String> = u.variants.values().map(|v| format!("{}.tag == {}::Tag::{}", expr, gen_type_path(c, package), v.name)).collect();
+            out.push_str(format!("if (!({})) {{ return \"{}{}\"; }}\n", tags.join(" || "), path, field.name).as_str());
+            for v in u.variants.values().filter(|v| is_variant_checked(&v.t, owner, true)) {
+                out.push_str(format!("if ({}.tag == {}::Tag::{}) {{\n", expr, gen_type_path(c, package), v.name).as_str());
+                let (owner, s) = owner.get_struct(v.t.str());
+                for f in s.fields.values() {
+                    out.push_str(gen_validate(f, owner, format!("{}.{}.", expr, v.name).as_str(), format!("{}{}.{}.", path, field.name, v.name).as_str()).as_str());
+                }
+                out.push_str("}\n");
             }
+        },
+        _ => {
+            if let FieldType::COMPLEX(ComplexTypes::Enum(_)) = &field.t {
+                expr.push_str(".value");
+            }
+
+            let (min, max) = gen_limits(field, package);
+            let mut cond = Vec::new();
+            // The value is kept as received, it can be any integer
+            if let Some(values) = gen_variant_values(field, package) {
+                cond.push(format!("({})", values.iter().map(|v| format!("{} == {}", expr, v)).collect::<Vec<_>>().join(" || ")));
+            }
+            if let Some(min) = min {
+                cond.push(format!("{} >= {}", expr, min));
+            }
+            if let Some(max) = max {
+                cond.push(format!("{} <= {}", expr, max));
+            }
+
+            out.push_str(format!("if (!({})) {{ return \"{}{}\"; }}\n", cond.join(" && "), path, field.name).as_str());
         }
     }
+    out.push_str(close.as_str());
 
     out
 }
 
-pub fn gen_from_be_bytes_conv(field: &StructField) -> String {
+/// C++ expression of a default value or constant of type `t`
+pub fn gen_value(value: &Value, t: &FieldType) -> String {
+    match (value, t) {
+        (Value::Int(v), FieldType::PRIMITIVE(PrimitiveTypes::U64)) => format!("{}ULL", v),
+        (Value::Int(v), FieldType::PRIMITIVE(PrimitiveTypes::I64)) if *v == i64::MIN as i128 => String::from("INT64_MIN"),
+        (Value::Int(v), FieldType::PRIMITIVE(PrimitiveTypes::I64)) => format!("{}LL", v),
+        (Value::Int(v), _) => v.to_string(),
+        (Value::Float(v), _) => format!("{:?}", v),
+        (Value::Bool(v), _) => v.to_string(),
+        (Value::String(v), _) => format!("{:?}", v),
+        (Value::Variant(v), FieldType::COMPLEX(ComplexTypes::Enum(_))) => format!("{}({}::{})", gen_type_def(t), gen_type_def(t), v),
+        (Value::Variant(_), _) => unreachable!("Default values are checked by the semantic analysis.")
+    }
+}
+
+/// Set the default value of the field in the default constructor, the one declared in the schema or the value used by the Rust `Default`:
+/// zero, no flags, the first variant of an enum or the empty string.
+/// Optional fields and bounded arrays are cleared where they're declared, structs and unions by their own constructor.
+pub fn gen_prop_default(field: &StructField, package: &Package) -> String {
     let mut out = String::new();
+    let (open, index, close) = gen_loops(field, "this->");
 
-    match field.array {
-        Some(n) => {
-            out.push_str(format!("for (int j = 0; j < {}; j++) {{\n", n).as_str());
-            match &field.t {
-                crate::core::ast::FieldType::COMPLEX(complex_types) => {
-                    out.push_str(format!("out.{}[j] = {}::from_be_bytes(raw + i);\n", field.name, complex_types.str()).as_str());
-                    out.push_str(format!("i += {}::BYTES_LENGTH", complex_types.str()).as_str());
-                    match complex_types {
-                        crate::core::ast::ComplexTypes::Struct(_) => out.push_str(" - 4"),
-                        crate::core::ast::ComplexTypes::Enum(_) => {},
-                        crate::core::ast::ComplexTypes::Unknown(_) => todo!(),
-                    };
-                    out.push_str(";\n");
-                },
-                crate::core::ast::FieldType::PRIMITIVE(_primitive_types) => {
-                    out.push_str(format!("out.{}[j] = iris::from_be_bytes<{}>(raw + i);\n", field.name, gen_type_def(&field.t)).as_str());
-                    out.push_str(format!("i += sizeof({});\n", gen_type_def(&field.t)).as_str());
-                },
+    if field.reserved || field.optional || field.bounded {
+        return out;
+    }
+
+    let value = match (&field.default, &field.t) {
+        (Some((value, _)), t) => gen_value(value, t),
+        (None, FieldType::COMPLEX(ComplexTypes::Struct(_) | ComplexTypes::Union(_))) => return out,
+        (None, FieldType::COMPLEX(ComplexTypes::Enum(e))) => {
+            let enmn = package.get_enum(e);
+            match enmn.flags {
+                true => String::from("0"),
+                false => format!("{}({}::{})", gen_type_def(&field.t), gen_type_def(&field.t), enmn.variants.keys().next().unwrap())
             }
-            out.push_str("}\n");
         },
-        None => {
-            match &field.t {
-                crate::core::ast::FieldType::COMPLEX(complex_types) => {
-                    out.push_str(format!("out.{} = {}::from_be_bytes(raw + i);\n", field.name, complex_types.str()).as_str());
-                    out.push_str(format!("i += {}::BYTES_LENGTH", complex_types.str()).as_str());
-                    match complex_types {
-                        crate::core::ast::ComplexTypes::Struct(_) => out.push_str(" - 4"),
-                        crate::core::ast::ComplexTypes::Enum(_) => {},
-                        crate::core::ast::ComplexTypes::Unknown(_) => todo!(),
-                    };
-                    out.push_str(";\n");
-                },
-                crate::core::ast::FieldType::PRIMITIVE(_primitive_types) => {
-                    out.push_str(format!("out.{} = iris::from_be_bytes<{}>(raw + i);\n", field.name, gen_type_def(&field.t)).as_str());
+        (None, FieldType::COMPLEX(ComplexTypes::Unknown(_))) => unreachable!("Types are resolved by the semantic analysis."),
+        (None, FieldType::PRIMITIVE(PrimitiveTypes::Bool)) => String::from("false"),
+        (None, FieldType::PRIMITIVE(_)) => String::from("0"),
+        (None, FieldType::STRING(_)) => String::from("\"\"")
+    };
+
+    out.push_str(open.as_str());
+    match field.t {
+        FieldType::STRING(_) => {
+            let args: String = (0..field.array.len()).map(|d| format!("j{}, ", d)).collect();
+            out.push_str(format!("this->set_{}({}{});\n", field.name, args, value).as_str());
+        },
+        _ => out.push_str(format!("this->{}{} = {};\n", field.name, index, value).as_str())
+    }
+    out.push_str(close.as_str());
+
+    out
+}
+
+/// Add the bytes of the field to `n` in `bytes_length()`, nothing when the size of the field doesn't change
+pub fn gen_bytes_length(field: &StructField, package: &Package) -> String {
+    let mut out = String::new();
+
+    if !field.is_variable(package) {
+        return out;
+    }
+
+    if field.optional {
+        match field.t.is_variable(package) {
+            true => out.push_str(format!("if (this->has_{}()) {{ n += this->{}.bytes_length() - 4; }}\n", field.name, field.name).as_str()),
+            false => out.push_str(format!("if (this->has_{}()) {{ n += {}; }}\n", field.name, field.t.size(package)).as_str())
+        }
+        return out;
+    }
+
+    if !field.t.is_variable(package) {
+        match field.t.size(package) {
+            1 => out.push_str(format!("n += this->{}.length();\n", field.name).as_str()),
+            size => out.push_str(format!("n += this->{}.length() * {};\n", field.name, size).as_str())
+        }
+        return out;
+    }
+
+    let (open, index, close) = gen_loops(field, "this->");
+    out.push_str(open.as_str());
+    out.push_str(format!("n += this->{}{}.bytes_length() - 4;\n", field.name, index).as_str());
+    out.push_str(close.as_str());
+
+    out
+}
+
+/// Move to the byte after the packed bitfields, once the last one is done
+fn gen_bits_end(bits: BitRange) -> String {
+    match bits.run_bytes {
+        Some(n) => format!("i += {};\n", n),
+        None => String::new()
+    }
+}
+
+pub fn gen_to_be_bytes_conv(field: &StructField, bits: Option<BitRange>, package: &Package) -> String {
+    let mut out = String::new();
+    let (open, index, close) = gen_loops(field, "this->");
+
+    if let Some(bits) = bits {
+        out.push_str(format!("iris::write_bits(buffer + i, {}, {}, this->{});\n", bits.offset, bits.width, field.name).as_str());
+        out.push_str(gen_bits_end(bits).as_str());
+
+        return out;
+    }
+
+    if field.reserved {
+        out.push_str(format!("for (int k = 0; k < {}; k++) {{ buffer[i + k] = 0; }}\n", field.elements()).as_str());
+        out.push_str(format!("i += {};\n", field.elements()).as_str());
+
+        return out;
+    }
+
+    // A bounded array starts with the number of elements
+    if let Some(l) = field.length_type() {
+        out.push_str(format!("iris::to_be_bytes(({})this->{}.length(), buffer + i);\n", gen_type_def(&FieldType::PRIMITIVE(l.clone())), field.name).as_str());
+        out.push_str(format!("i += {};\n", l.size()).as_str());
+    }
+
+    // The presence bitmap is copied by the struct
+    if field.optional {
+        out.push_str(format!("if (this->has_{}()) {{\n", field.name).as_str());
+    }
+    out.push_str(open.as_str());
+    match &field.t {
+        crate::core::ast::FieldType::COMPLEX(_) if field.t.is_variable(package) => {
+            out.push_str(format!("this->{}{}.to_be_bytes(buffer + i);\n", field.name, index).as_str());
+            out.push_str(format!("i += this->{}{}.bytes_length() - 4;\n", field.name, index).as_str());
+        },
+        crate::core::ast::FieldType::COMPLEX(complex_types) => {
+            out.push_str(format!("this->{}{}.to_be_bytes(buffer + i);\n", field.name, index).as_str());
+            out.push_str(format!("i += this->{}{}.BYTES_LENGTH", field.name, index).as_str());
+            match complex_types {
+                crate::core::ast::ComplexTypes::Struct(_) => out.push_str(" - 4"),
+                crate::core::ast::ComplexTypes::Enum(_) | crate::core::ast::ComplexTypes::Union(_) => {},
+                crate::core::ast::ComplexTypes::Unknown(_) => unreachable!("Types are resolved by the semantic analysis."),
+            };
+            out.push_str(";\n");
+        },
+        crate::core::ast::FieldType::PRIMITIVE(primitive_types) => {
+            match field.scaling {
+                Some(s) => {
+                    let (min, max) = gen_type_limits(primitive_types);
+                    out.push_str(format!("iris::to_be_bytes(iris::to_fixed<{}>(this->{}{}, {:?}, {:?}, {}, {}), buffer + i);\n", gen_type_def(&field.t), field.name, index, s.scale, s.offset, min, max).as_str());
                     out.push_str(format!("i += sizeof({});\n", gen_type_def(&field.t)).as_str());
                 },
+                None => {
+                    out.push_str(format!("iris::to_be_bytes(this->{}{}, buffer + i);\n", field.name, index).as_str());
+                    out.push_str(format!("i += sizeof(this->{}{});\n", field.name, index).as_str());
+                }
             }
+        },
+        crate::core::ast::FieldType::STRING(size) => {
+            out.push_str(format!("for (int k = 0; k < {}; k++) {{ buffer[i + k] = (iris::byte)this->{}{}[k]; }}\n", size, field.name, index).as_str());
+            out.push_str(format!("i += {};\n", size).as_str());
+        },
+    }
+    out.push_str(close.as_str());
+    if field.optional {
+        out.push_str("}\n");
+    }
+
+    out
+}
+
+pub fn gen_from_be_bytes_conv(field: &StructField, bits: Option<BitRange>, package: &Package) -> String {
+    let mut out = String::new();
+    let (open, index, close) = gen_loops(field, "out.");
+
+    if let Some(bits) = bits {
+        match field.t {
+            FieldType::PRIMITIVE(crate::core::ast::PrimitiveTypes::Bool) => out.push_str(format!("out.{} = iris::read_bits(raw + i, {}, {}) != 0;\n", field.name, bits.offset, bits.width).as_str()),
+            _ => out.push_str(format!("out.{} = ({})iris::read_bits(raw + i, {}, {});\n", field.name, gen_type_def(&field.t), bits.offset, bits.width).as_str())
         }
+        out.push_str(gen_bits_end(bits).as_str());
+
+        return out;
+    }
+
+    if field.reserved {
+        out.push_str(format!("i += {};\n", field.elements()).as_str());
+
+        return out;
+    }
+
+    // Only the capacity is decoded from a larger number of elements, `validate` reports the field
+    if let Some(l) = field.length_type() {
+        out.push_str(format!("out.{}.resize(iris::from_be_bytes<{}>(raw + i));\n", field.name, gen_type_def(&FieldType::PRIMITIVE(l.clone()))).as_str());
+        out.push_str(format!("i += {};\n", l.size()).as_str());
+    }
+
+    if field.optional {
+        out.push_str(format!("if (out.has_{}()) {{\n", field.name).as_str());
+    }
+    out.push_str(open.as_str());
+    match &field.t {
+        crate::core::ast::FieldType::COMPLEX(_) if field.t.is_variable(package) => {
+            out.push_str(format!("out.{}{} = {}::from_be_bytes(raw + i);\n", field.name, index, gen_type_def(&field.t)).as_str());
+            out.push_str(format!("i += out.{}{}.bytes_length() - 4;\n", field.name, index).as_str());
+        },
+        crate::core::ast::FieldType::COMPLEX(complex_types) => {
+            out.push_str(format!("out.{}{} = {}::from_be_bytes(raw + i);\n", field.name, index, gen_type_def(&field.t)).as_str());
+            out.push_str(format!("i += {}::BYTES_LENGTH", gen_type_def(&field.t)).as_str());
+            match complex_types {
+                crate::core::ast::ComplexTypes::Struct(_) => out.push_str(" - 4"),
+                crate::core::ast::ComplexTypes::Enum(_) | crate::core::ast::ComplexTypes::Union(_) => {},
+                crate::core::ast::ComplexTypes::Unknown(_) => unreachable!("Types are resolved by the semantic analysis."),
+            };
+            out.push_str(";\n");
+        },
+        crate::core::ast::FieldType::PRIMITIVE(_primitive_types) => {
+            out.push_str(format!("out.{}{} = iris::from_be_bytes<{}>(raw + i)", field.name, index, gen_type_def(&field.t)).as_str());
+            if let Some(s) = field.scaling {
+                out.push_str(format!(" * {:?} + {:?}", s.scale, s.offset).as_str());
+            }
+            out.push_str(";\n");
+            out.push_str(format!("i += sizeof({});\n", gen_type_def(&field.t)).as_str());
+        },
+        crate::core::ast::FieldType::STRING(size) => {
+            out.push_str(format!("for (int k = 0; k < {}; k++) {{ out.{}{}[k] = (char)raw[i + k]; }}\n", size, field.name, index).as_str());
+            out.push_str(format!("out.{}{}[{}] = '\\0';\n", field.name, index, size).as_str());
+            out.push_str(format!("i += {};\n", size).as_str());
+        },
+    }
+    out.push_str(close.as_str());
+    if field.optional {
+        out.push_str("}\n");
     }
 
     out
-}
\ No newline at end of file
+}
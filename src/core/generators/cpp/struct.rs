@@ -1,34 +1,60 @@
 use crate::core::ast::{Package, Struct};
 
-use super::field::{gen_arg_declaration, gen_from_be_bytes_conv, gen_prop_declaration, gen_prop_init, gen_to_be_bytes_conv};
+use crate::core::generators::code_gen::{doc_lines, gen_cpp_comment};
+
+use super::field::{gen_arg_declaration, gen_bytes_length, gen_from_be_bytes_conv, gen_optional_accessors, gen_prop_declaration, gen_prop_default, gen_prop_init, gen_string_setter, gen_to_be_bytes_conv, gen_validate};
 
 pub fn gen_code(strc: &Struct, package: &Package) -> String {
     let mut out = String::new();
 
+    out.push_str(gen_cpp_comment(&doc_lines(&strc.doc, &None)).as_str());
     out.push_str(format!("class {} {{\n", strc.name).as_str());
     out.push_str("public:\n");
-    out.push_str(format!("static const uint32_t NAME_HASH = {};\n", strc.fnv_1a()).as_str());
-    out.push_str(format!("static const size_t BYTES_LENGTH = {} + 4;\n", strc.size(package)).as_str());
+    out.push_str(format!("static const uint32_t NAME_HASH = {};\n", strc.id()).as_str());
+    // With a variable size the buffer takes the largest struct, only the first `bytes_length()` bytes are used
+    match strc.is_variable(package) {
+        true => out.push_str(format!("static const size_t MAX_BYTES_LENGTH = {} + 4;\n", strc.size(package)).as_str()),
+        false => out.push_str(format!("static const size_t BYTES_LENGTH = {} + 4;\n", strc.size(package)).as_str())
+    }
     out.push_str(format!("iris::byte DATA_BUFFER[{} + 4] = {{0}};\n", strc.size(package)).as_str());
-    for f in strc.fields.values() {
+    for f in strc.fields.values().filter(|f| !f.optional) {
         out.push_str(gen_prop_declaration(f).as_str());
     }
-    out.push_str(format!("{}() {{}}\n", strc.name).as_str());
-    out.push_str(format!("{}(", strc.name).as_str());
-    let mut counter = 1;
-    //for f in strc.fields.values() {
-    for f in &strc.fields_order {
-        out.push_str(gen_arg_declaration(strc.fields.get(f).unwrap()).as_str());
-        if counter < strc.fields.len() {
-            out.push_str(",");
-            counter += 1;
+    out.push_str(format!("{}() {{\n", strc.name).as_str());
+    for f in strc.fields.values() {
+        out.push_str(gen_prop_default(f, package).as_str());
+    }
+    out.push_str("}\n");
+    // Without fields it would be the same as the default constructor, the optional fields are set with their accessors
+    let args: Vec<_> = strc.fields.values().filter(|f| !f.optional && !f.reserved).collect();
+    if !args.is_empty() {
+        out.push_str(format!("{}(", strc.name).as_str());
+        out.push_str(args.iter().map(|f| gen_arg_declaration(f)).collect::<Vec<_>>().join(",").as_str());
+        out.push_str(") {\n");
+        for f in args {
+            out.push_str(gen_prop_init(f).as_str());
         }
+        out.push_str("}\n");
     }
-    out.push_str(") {\n");
     for f in strc.fields.values() {
-        out.push_str(gen_prop_init(f).as_str());
+        out.push_str(gen_string_setter(f).as_str());
+    }
+    for (f, bit) in strc.fields.values().zip(strc.presence_bits()) {
+        if let Some(bit) = bit {
+            out.push_str(gen_optional_accessors(f, bit).as_str());
+        }
+    }
+
+    if strc.is_variable(package) {
+        out.push_str("/// Encoded size in bytes, only this many bytes of `encode` are sent\n");
+        out.push_str("size_t bytes_length() const {\n");
+        out.push_str(format!("size_t n = {} + 4;\n", strc.base_size(package)).as_str());
+        for f in strc.fields.values() {
+            out.push_str(gen_bytes_length(f, package).as_str());
+        }
+        out.push_str("return n;\n");
+        out.push_str("}\n");
     }
-    out.push_str("}\n");
 
     out.push_str("iris::byte *encode() {\n");
     out.push_str("iris::to_be_bytes(this->NAME_HASH, this->DATA_BUFFER);\n");
@@ -42,8 +68,11 @@ pub fn gen_code(strc: &Struct, package: &Package) -> String {
 
     out.push_str("iris::byte *to_be_bytes(iris::byte *buffer) {\n");
     out.push_str("int i = 0;\n");
-    for s in &strc.fields_order {
-        out.push_str(gen_to_be_bytes_conv(strc.fields.get(s).unwrap()).as_str());
+    if strc.presence_bytes() > 0 {
+        out.push_str(format!("for (; i < {}; i++) {{ buffer[i] = this->PRESENCE[i]; }}\n", strc.presence_bytes()).as_str());
+    }
+    for (f, bits) in strc.fields.values().zip(strc.bit_ranges()) {
+        out.push_str(gen_to_be_bytes_conv(f, bits, package).as_str());
     }
     out.push_str("return buffer;\n");
     out.push_str("}\n");
@@ -55,12 +84,37 @@ pub fn gen_code(strc: &Struct, package: &Package) -> String {
     out.push_str(format!("static {} from_be_bytes(iris::byte *raw) {{\n", strc.name).as_str());
     out.push_str(format!("{} out = {}();\n", strc.name, strc.name).as_str());
     out.push_str("int i = 0;\n");
-    for s in &strc.fields_order {
-        out.push_str(gen_from_be_bytes_conv(strc.fields.get(s).unwrap()).as_str());
+    if strc.presence_bytes() > 0 {
+        out.push_str(format!("for (; i < {}; i++) {{ out.PRESENCE[i] = raw[i]; }}\n", strc.presence_bytes()).as_str());
+    }
+    for (f, bits) in strc.fields.values().zip(strc.bit_ranges()) {
+        out.push_str(gen_from_be_bytes_conv(f, bits, package).as_str());
     }
     out.push_str("return out;\n");
     out.push_str("}\n");
 
+    out.push_str("/// Check the limits set with `@range`, `@min` and `@max` and that the enum values are variants, return the first field failing it or `nullptr`\n");
+    out.push_str("const char *validate() const {\n");
+    for f in strc.fields.values() {
+        out.push_str(gen_validate(f, package, "this->", "").as_str());
+    }
+    out.push_str("return nullptr;\n");
+    out.push_str("}\n");
+    out.push_str("/// Decode the struct in `out` and validate it, return the first field failing it or `nullptr`\n");
+    out.push_str(format!("static const char *decode_validated(iris::byte *raw, {} &out) {{\n", strc.name).as_str());
+    out.push_str(format!("out = {}::decode(raw);\n", strc.name).as_str());
+    out.push_str("return out.validate();\n");
+    out.push_str("}\n");
+
+    // A bit for every optional field, set when it's present
+    if strc.presence_bytes() > 0 {
+        out.push_str("private:\n");
+        out.push_str(format!("iris::byte PRESENCE[{}] = {{0}};\n", strc.presence_bytes()).as_str());
+        for f in strc.fields.values().filter(|f| f.optional) {
+            out.push_str(gen_prop_declaration(f).as_str());
+        }
+    }
+
     out.push_str("};\n");
 
     out
@@ -1,20 +1,33 @@
-use crate::core::ast::FieldType;
+use crate::core::ast::{ComplexTypes, FieldType, Package};
 
+/// Path of a struct, enum or union from any namespace of the file, used when the code is not in the namespace of `package`
+pub fn gen_type_path(c: &ComplexTypes, package: &Package) -> String {
+    format!("iris::{}::{}", c.package().unwrap_or(package.name.as_ref().unwrap()), c.name())
+}
+
+/// C++ type of a field, the types of imported packages are in the namespace of their package
 pub fn gen_type_def(t: &FieldType) -> String {
     match t {
-        FieldType::COMPLEX(complex_types) => String::from(complex_types.str()),
+        FieldType::COMPLEX(complex_types) => match complex_types.package() {
+            Some(p) => format!("iris::{}::{}", p, complex_types.name()),
+            None => String::from(complex_types.name())
+        },
         FieldType::PRIMITIVE(primitive_types) => {
             match primitive_types {
                 crate::core::ast::PrimitiveTypes::U8 => String::from("uint8_t"),
                 crate::core::ast::PrimitiveTypes::U16 => String::from("uint16_t"),
                 crate::core::ast::PrimitiveTypes::U32 => String::from("uint32_t"),
+                crate::core::ast::PrimitiveTypes::U64 => String::from("uint64_t"),
                 crate::core::ast::PrimitiveTypes::I8 => String::from("int8_t"),
                 crate::core::ast::PrimitiveTypes::I16 => String::from("int16_t"),
                 crate::core::ast::PrimitiveTypes::I32 => String::from("int32_t"),
+                crate::core::ast::PrimitiveTypes::I64 => String::from("int64_t"),
                 crate::core::ast::PrimitiveTypes::F32 => String::from("float"),
+                crate::core::ast::PrimitiveTypes::F64 => String::from("double"),
                 crate::core::ast::PrimitiveTypes::Bool => String::from("bool"),
             }
         },
+        FieldType::STRING(_) => String::from("char"),
     }
     
 }
\ No newline at end of file
@@ -0,0 +1,74 @@
+use crate::core::ast::{FieldType, Package, Union};
+
+use crate::core::generators::code_gen::{doc_lines, gen_cpp_comment};
+
+use super::types::gen_type_def;
+
+pub fn gen_code(union: &Union, package: &Package) -> String {
+    let mut out = String::new();
+    let repr = gen_type_def(&FieldType::PRIMITIVE(union.repr.clone()));
+    let size = union.size(package);
+    // The struct of the variant starts after its value
+    let start = union.repr.size();
+
+    out.push_str(gen_cpp_comment(&doc_lines(&union.doc, &None)).as_str());
+    out.push_str(format!("class {} {{\n", union.name).as_str());
+    out.push_str("public:\n");
+    out.push_str(format!("enum class Tag : {} {{\n", repr).as_str());
+    for v in union.variants.values() {
+        out.push_str(format!("{} = {},\n", v.name, v.value).as_str());
+    }
+    out.push_str("};\n");
+    out.push_str(format!("static const size_t BYTES_LENGTH = {};\n", size).as_str());
+    out.push_str(format!("iris::byte DATA_BUFFER[{}] = {{0}};\n", size).as_str());
+    out.push_str("Tag tag;\n");
+    out.push_str("union {\n");
+    for v in union.variants.values() {
+        out.push_str(gen_cpp_comment(&doc_lines(&v.doc, &None)).as_str());
+        out.push_str(format!("{} {};\n", gen_type_def(&v.t), v.name).as_str());
+    }
+    out.push_str("};\n");
+
+    // The default is the first variant
+    let (_, first) = union.variants.first().unwrap();
+    out.push_str(format!("{}() : {}() {{\n", union.name, first.name).as_str());
+    out.push_str(format!("this->tag = Tag::{};\n", first.name).as_str());
+    out.push_str("}\n");
+    for v in union.variants.values() {
+        out.push_str(format!("void set_{}({} {}) {{\n", v.name, gen_type_def(&v.t), v.name).as_str());
+        out.push_str(format!("this->tag = Tag::{};\n", v.name).as_str());
+        out.push_str(format!("this->{} = {};\n", v.name, v.name).as_str());
+        out.push_str("}\n");
+    }
+
+    out.push_str("inline iris::byte *to_be_bytes() {\n");
+    out.push_str("return this->to_be_bytes(this->DATA_BUFFER);\n");
+    out.push_str("}\n");
+    // The bytes after a struct smaller than the largest one are zero
+    out.push_str("iris::byte *to_be_bytes(iris::byte *buffer) {\n");
+    out.push_str(format!("iris::to_be_bytes(({})this->tag, buffer);\n", repr).as_str());
+    out.push_str(format!("for (size_t k = {}; k < BYTES_LENGTH; k++) {{ buffer[k] = 0; }}\n", start).as_str());
+    out.push_str("switch (this->tag) {\n");
+    for v in union.variants.values() {
+        out.push_str(format!("case Tag::{}: this->{}.to_be_bytes(buffer + {}); break;\n", v.name, v.name, start).as_str());
+    }
+    out.push_str("}\n");
+    out.push_str("return buffer;\n");
+    out.push_str("}\n");
+
+    // An unknown tag is kept with the default variant, `validate` of the struct reports it
+    out.push_str(format!("static {} from_be_bytes(iris::byte *raw) {{\n", union.name).as_str());
+    out.push_str(format!("{} out = {}();\n", union.name, union.name).as_str());
+    out.push_str(format!("out.tag = (Tag)iris::from_be_bytes<{}>(raw);\n", repr).as_str());
+    out.push_str("switch (out.tag) {\n");
+    for v in union.variants.values() {
+        out.push_str(format!("case Tag::{}: out.set_{}({}::from_be_bytes(raw + {})); break;\n", v.name, v.name, gen_type_def(&v.t), start).as_str());
+    }
+    out.push_str("default: break;\n");
+    out.push_str("}\n");
+    out.push_str("return out;\n");
+    out.push_str("}\n");
+    out.push_str("};\n");
+
+    out
+}
@@ -1,7 +1,6 @@
 /**
  * Code generation for Python.
  */
-
 use package::gen_code;
 
 use super::code_gen::CodeGen;
@@ -10,6 +9,8 @@ pub mod types;
 pub mod field;
 pub mod r#struct;
 pub mod enumeration;
+pub mod union;
+pub mod constant;
 pub mod package;
 
 pub struct Python { }
@@ -21,18 +22,75 @@ impl CodeGen for Python {
         out.push_str("import enum\n");
         out.push_str("import struct\n");
         out.push_str("import itertools\n");
+        out.push_str("import math\n");
+        if package.has_deprecated() {
+            out.push_str("import warnings\n");
+        }
 
         out.push_str("class Iris:\n");
+
+        out.push_str("    @staticmethod\n");
+        out.push_str("    def string_to_bytes(s: str, size: int) -> bytes:\n");
+        out.push_str("        raw = s.encode('utf-8')\n");
+        out.push_str("        if len(raw) > size:\n");
+        out.push_str("            raise ValueError(f'String too long, {len(raw)} bytes but the capacity is {size}.')\n");
+        out.push_str("        return raw\n");
+        out.push_str("    class Truncated(list):\n");
+        out.push_str("        \"\"\"Bounded array received with a length larger than its maximum, only the maximum number of elements is decoded\"\"\"\n");
+        out.push_str("    @staticmethod\n");
+        out.push_str("    def check_length(items: list, size: int) -> int:\n");
+        out.push_str("        if len(items) > size:\n");
+        out.push_str("            raise ValueError(f'Array too long, {len(items)} elements but the capacity is {size}.')\n");
+        out.push_str("        return len(items)\n");
+        out.push_str("    @staticmethod\n");
+        out.push_str("    def string_from_bytes(raw: bytes) -> str:\n");
+        out.push_str("        return raw.split(b'\\0', 1)[0].decode('utf-8', errors='ignore')\n");
+        out.push_str("    @staticmethod\n");
+        out.push_str("    def to_fixed(value: float, scale: float, offset: float, min: int, max: int) -> int:\n");
+        out.push_str("        raw = (value - offset) / scale\n");
+        out.push_str("        if math.isnan(raw):\n");
+        out.push_str("            return 0\n");
+        out.push_str("        if raw <= min:\n");
+        out.push_str("            return min\n");
+        out.push_str("        if raw >= max:\n");
+        out.push_str("            return max\n");
+        out.push_str("        return int(math.copysign(math.floor(abs(raw) + 0.5), raw))\n");
+        out.push_str("    @staticmethod\n");
+        out.push_str("    def pack_bits(size: int, fields: list) -> bytes:\n");
+        out.push_str("        value = 0\n");
+        out.push_str("        width = 0\n");
+        out.push_str("        for (v, w) in fields:\n");
+        out.push_str("            value = (value << w) | (int(v) & ((1 << w) - 1))\n");
+        out.push_str("            width += w\n");
+        out.push_str("        return (value << (size * 8 - width)).to_bytes(size, 'big')\n");
+        out.push_str("    @staticmethod\n");
+        out.push_str("    def read_bits(raw: bytes, offset: int, width: int) -> int:\n");
+        out.push_str("        value = int.from_bytes(raw, 'big')\n");
+        out.push_str("        return (value >> (len(raw) * 8 - offset - width)) & ((1 << width) - 1)\n");
+        out.push_str("    @staticmethod\n");
+        out.push_str("    def reshape(data, dims: list):\n");
+        out.push_str("        if len(dims) == 1:\n");
+        out.push_str("            return list(data)\n");
+        out.push_str("        size = len(data) // dims[0]\n");
+        out.push_str("        return [Iris.reshape(data[i:i + size], dims[1:]) for i in range(0, len(data), size)]\n");
         out.push_str("    class Packages:\n");
         
-        out.push_str(gen_code(&package).as_str());
+        // Imported packages come first, every package is a class
+        for p in package.all_packages() {
+            out.push_str(gen_code(p).as_str());
+        }
 
         out.push_str("    @staticmethod\n");
         out.push_str("    def decode(raw: bytes):\n");
         out.push_str("        name_hash = struct.unpack('>I', raw[0:4])[0]\n");
         out.push_str("        match name_hash:\n");
         for s in package.structs.values() {
-            out.push_str(format!("            case Iris.Packages.{}.{}.NAME_HASH if len(raw) == Iris.Packages.{}.{}.BYTES_LENGTH:\n", package.name.clone().unwrap(), s.name, package.name.clone().unwrap(), s.name).as_str());
+            // A struct with a variable size can be shorter than the largest one
+            let length = match s.is_variable(package) {
+                true => format!("<= Iris.Packages.{}.{}.MAX_BYTES_LENGTH", package.name.clone().unwrap(), s.name),
+                false => format!("== Iris.Packages.{}.{}.BYTES_LENGTH", package.name.clone().unwrap(), s.name)
+            };
+            out.push_str(format!("            case Iris.Packages.{}.{}.NAME_HASH if len(raw) {}:\n", package.name.clone().unwrap(), s.name, length).as_str());
             out.push_str(format!("                return Iris.Packages.{}.{}.decode(raw)\n", package.name.clone().unwrap(), s.name).as_str());
         }
         out.push_str("            case _:\n");
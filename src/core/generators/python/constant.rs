@@ -0,0 +1,14 @@
+use crate::core::ast::{Constant, FieldType, Package};
+
+use crate::core::generators::code_gen::{doc_lines, gen_comment};
+
+use super::field::gen_value;
+
+pub fn gen_code(constant: &Constant, package: &Package) -> String {
+    let mut out = String::new();
+
+    out.push_str(gen_comment(&doc_lines(&constant.doc, &None), "            #:").as_str());
+    out.push_str(format!("            {} = {}\n", constant.name, gen_value(&constant.value, &FieldType::PRIMITIVE(constant.t.clone()), package)).as_str());
+
+    out
+}
@@ -1,27 +1,45 @@
-use crate::core::ast::{Enum, Package};
+use crate::core::ast::{Enum, FieldType, Package};
+
+use super::types::gen_pack_format;
 
 pub fn gen_code(enmn: &Enum, _package: &Package) -> String {
     let mut out = String::new();
+    let format = gen_pack_format(&FieldType::PRIMITIVE(enmn.repr.clone()), _package);
 
-    out.push_str(format!("            class {}(enum.IntEnum):\n", enmn.name).as_str());
+    out.push_str(format!("            class {}(enum.{}):\n", enmn.name, if enmn.flags { "IntFlag" } else { "IntEnum" }).as_str());
     
     for variant in enmn.variants.values() {
         out.push_str(format!("                {} = {}\n", variant.name, variant.value).as_str());
     }
     
     out.push_str("                def to_be_bytes(self) -> bytes:\n");
-    out.push_str("                    return struct.pack('>I', self)\n");
+    out.push_str(format!("                    return struct.pack('>{}', self)\n", format).as_str());
+
+    if enmn.flags {
+        // IntFlag values are immutable, set and clear return the new value
+        out.push_str("                def contains(self, flags) -> bool:\n");
+        out.push_str("                    return self & flags == flags\n");
+        out.push_str("                def set(self, flags):\n");
+        out.push_str("                    return self | flags\n");
+        out.push_str("                def clear(self, flags):\n");
+        out.push_str("                    return self & ~flags\n");
+        out.push_str("                @staticmethod\n");
+        out.push_str("                def from_be_bytes(raw: bytes):\n");
+        out.push_str(format!("                    return Iris.Packages.{}.{}(struct.unpack('>{}', raw)[0])\n", _package.name.as_ref().unwrap(), enmn.name, format).as_str());
+
+        return out;
+    }
     
     out.push_str("                @staticmethod\n");
     out.push_str("                def from_be_bytes(raw: bytes):\n");
-    out.push_str("                    match struct.unpack('>I', raw)[0]:\n");
+    out.push_str(format!("                    match struct.unpack('>{}', raw)[0]:\n", format).as_str());
     
     for variant in enmn.variants.values() {
         out.push_str(format!("                        case {}:\n", variant.value).as_str());
         out.push_str(format!("                            return Iris.Packages.{}.{}.{}\n", _package.name.as_ref().unwrap(), enmn.name, variant.name).as_str());
     }
     out.push_str("                        case _:\n");
-    out.push_str("                            raise 'No variant found.'\n");
+    out.push_str("                            raise ValueError('No variant found.')\n");
 
     out
 }
\ No newline at end of file
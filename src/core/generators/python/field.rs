@@ -1,31 +1,415 @@
-use crate::core::ast::{ComplexTypes, FieldType, StructField};
+use crate::core::ast::{ComplexTypes, FieldType, Package, PrimitiveTypes, StructField, Value};
+use crate::core::generators::code_gen::{gen_limits, gen_variant_values, is_checked, is_value_checked, is_variant_checked};
 
-pub fn gen_pack_arg(field: &StructField) -> String {
+use super::types::{gen_pack_format, gen_type_path};
+
+/// Attribute holding the value of the field, a deprecated field is kept in `_name` behind a property that warns
+pub fn gen_attr(field: &StructField) -> String {
+    match field.deprecated {
+        Some(_) => format!("_{}", field.name),
+        None => field.name.clone()
+    }
+}
+
+/// Integer encoded for the value of a scaled field, rounded and saturated by `Iris.to_fixed`
+fn gen_to_raw(field: &StructField, value: String) -> String {
+    match (&field.scaling, &field.t) {
+        (Some(s), FieldType::PRIMITIVE(p)) => {
+            let (min, max) = p.limits().unwrap();
+            format!("Iris.to_fixed({}, {:?}, {:?}, {}, {})", value, s.scale, s.offset, min, max)
+        },
+        _ => value
+    }
+}
+
+/// Value of a scaled field from the encoded integer
+pub fn gen_from_raw(field: &StructField, raw: String) -> String {
+    match field.scaling {
+        Some(s) => format!("{} * {:?} + {:?}", raw, s.scale, s.offset),
+        None => raw
+    }
+}
+
+/// Class of a flags field, the decoded integers are wrapped in it to have `contains`, `set` and `clear`
+pub fn gen_flags_class(field: &StructField, package: &Package) -> Option<String> {
+    match &field.t {
+        FieldType::COMPLEX(c @ ComplexTypes::Enum(e)) if package.get_enum(e).flags => Some(gen_type_path(c, package)),
+        _ => None
+    }
+}
+
+/// Python expression of a default value or constant of type `t`
+pub fn gen_value(value: &Value, t: &FieldType, package: &Package) -> String {
+    match (value, t) {
+        (Value::Int(v), _) => v.to_string(),
+        (Value::Float(v), _) => format!("{:?}", v),
+        (Value::Bool(v), _) => String::from(if *v { "True" } else { "False" }),
+        (Value::String(v), _) => format!("{:?}", v),
+        (Value::Variant(v), FieldType::COMPLEX(c @ ComplexTypes::Enum(_))) => format!("{}.{}", gen_type_path(c, package), v),
+        (Value::Variant(_), _) => unreachable!("Default values are checked by the semantic analysis.")
+    }
+}
+
+/// Default value of one element of the field, the one declared in the schema or zero, an empty string, the first variant of an enum
+pub fn gen_default_value(field: &StructField, package: &Package) -> String {
+    match (&field.default, &field.t) {
+        (Some((v, _)), t) => gen_value(v, t, package),
+        (None, _) if field.scaling.is_some() => String::from("0.0"),
+        (None, FieldType::PRIMITIVE(PrimitiveTypes::Bool)) => String::from("False"),
+        (None, FieldType::PRIMITIVE(PrimitiveTypes::F32 | PrimitiveTypes::F64)) => String::from("0.0"),
+        (None, FieldType::PRIMITIVE(_)) => String::from("0"),
+        (None, FieldType::STRING(_)) => String::from("''"),
+        (None, FieldType::COMPLEX(c @ (ComplexTypes::Struct(_) | ComplexTypes::Union(_)))) => format!("{}()", gen_type_path(c, package)),
+        (None, FieldType::COMPLEX(c @ ComplexTypes::Enum(e))) => {
+            let enmn = package.get_enum(e);
+            match enmn.flags {
+                true => format!("{}(0)", gen_type_path(c, package)),
+                false => format!("{}.{}", gen_type_path(c, package), enmn.variants.keys().next().unwrap())
+            }
+        },
+        (None, FieldType::COMPLEX(ComplexTypes::Unknown(_))) => unreachable!("Types are resolved by the semantic analysis.")
+    }
+}
+
+/// Keyword default of the field in `__init__`, and the value given by the body when it's `None`.
+/// Lists, structs and enums are created by the body, because they can't be shared between the instances or aren't declared yet.
+pub fn gen_init_default(field: &StructField, package: &Package) -> (String, Option<String>) {
+    let value = gen_default_value(field, package);
+
+    if field.optional {
+        (String::from("None"), None)
+    }
+    else if field.bounded {
+        (String::from("None"), Some(String::from("[]")))
+    }
+    else if field.is_array() {
+        let list = field.array.iter().rev().fold(value, |out, n| format!("[{} for _ in range({})]", out, n));
+        (String::from("None"), Some(list))
+    }
+    else if matches!(field.t, FieldType::COMPLEX(_)) {
+        (String::from("None"), Some(value))
+    }
+    else {
+        (value, None)
+    }
+}
+
+/// Return the path of the field if its value is out of the limits.
+/// `value` is the expression of the struct containing the field, the fields of nested structs are checked with the path `outer.inner`.
+pub fn gen_validate(field: &StructField, package: &Package, value: &str, path: &str, indent: usize) -> String {
     let mut out = String::new();
 
-    match field.array {
-        Some(_) => {
-            out.push_str("*itertools.chain.from_iterable([i");
-            match field.t {
-                FieldType::COMPLEX(_) => out.push_str(".to_be_bytes()"),
-                FieldType::PRIMITIVE(_) => {}
+    if !is_checked(field, package, true) {
+        return out;
+    }
+
+    // Every element of an array is checked, the loop variables are unique inside nested structs
+    let depth = path.matches('.').count();
+    let mut expr = format!("{}.{}", value, gen_attr(field));
+    let mut indent = indent;
+    // A missing optional field is valid
+    if field.optional {
+        out.push_str(format!("{}if {} is not None:\n", " ".repeat(indent), expr).as_str());
+        indent += 4;
+    }
+    if field.bounded {
+        out.push_str(format!("{}if isinstance({}, Iris.Truncated):\n", " ".repeat(indent), expr).as_str());
+        out.push_str(format!("{}    return '{}{}'\n", " ".repeat(indent), path, field.name).as_str());
+    }
+    if !is_value_checked(field, package, true) {
+        return out;
+    }
+    for d in 0..field.array.len() {
+        let x = format!("x{}_{}", depth, d);
+        out.push_str(format!("{}for {} in {}:\n", " ".repeat(indent), x, expr).as_str());
+        expr = x;
+        indent += 4;
+    }
+
+    match &field.t {
+        FieldType::COMPLEX(ComplexTypes::Struct(s)) => {
+            let (owner, s) = package.get_struct(s);
+            for f in s.fields.values() {
+                out.push_str(gen_validate(f, owner, expr.as_str(), format!("{}{}.", path, field.name).as_str(), indent).as_str());
             }
-            out.push_str(format!(" for i in self.{}])", field.name).as_str());
-        },
-        None => {
-            match &field.t {
-                FieldType::COMPLEX(c) => {
-                    match c {
-                        ComplexTypes::Struct(_) => out.push_str(format!("*self.{}.to_be_bytes()", field.name).as_str()),
-                        ComplexTypes::Enum(_) => out.push_str(format!("self.{}", field.name).as_str()),
-                        ComplexTypes::Unknown(_) => panic!("Can't generate pack argument for unknown type.")
-                    }
-                },
-                FieldType::PRIMITIVE(_) => out.push_str(format!("self.{}", field.name).as_str())
+        },
+        FieldType::COMPLEX(c @ ComplexTypes::Union(u)) => {
+            // The tag is kept as received, then only the struct of the current variant is checked, the fields are named `union.variant.field`
+            let (owner, u) = package.get_union(u);
+            out.push_str(format!("{}if not ({}.tag in ({},)):\n", " ".repeat(indent), expr, u.variants.values().map(|v| v.value.to_string()).collect::<Vec<_>>().join(", ")).as_str());
+            out.push_str(format!("{}    return '{}{}'\n", " ".repeat(indent), path, field.name).as_str());
+            for v in u.variants.values().filter(|v| is_variant_checked(&v.t, owner, true)) {
+                out.push_str(format!("{}if {}.tag == {}.Tag.{}:\n", " ".repeat(indent), expr, gen_type_path(c, package), v.name).as_str());
+                let (owner, s) = owner.get_struct(v.t.str());
+                for f in s.fields.values() {
+                    out.push_str(gen_validate(f, owner, format!("{}.value", expr).as_str(), format!("{}{}.{}.", path, field.name, v.name).as_str(), indent + 4).as_str());
+                }
+            }
+        },
+        _ => {
+            let (min, max) = gen_limits(field, package);
+            let mut cond = Vec::new();
+            // Enum fields are decoded as the received integer
+            if let Some(values) = gen_variant_values(field, package) {
+                cond.push(format!("{} in ({},)", expr, values.join(", ")));
             }
+            if let Some(min) = min {
+                cond.push(format!("{} >= {}", expr, min));
+            }
+            if let Some(max) = max {
+                cond.push(format!("{} <= {}", expr, max));
+            }
+
+            out.push_str(format!("{}if not ({}):\n", " ".repeat(indent), cond.join(" and ")).as_str());
+            out.push_str(format!("{}    return '{}{}'\n", " ".repeat(indent), path, field.name).as_str());
         }
+    }
+
+    out
+}
+
+/// Flatten the nested lists, `x0 for x0 in self.a` for 1-D arrays, `x1 for x0 in self.a for x1 in x0` for 2-D, ...
+/// Return the `for` clauses and the element.
+fn gen_flat_iter(field: &StructField) -> (String, String) {
+    let mut iter = format!("for x0 in self.{}", gen_attr(field));
+    for d in 1..field.array.len() {
+        iter.push_str(format!(" for x{} in x{}", d, d - 1).as_str());
+    }
+
+    (iter, format!("x{}", field.array.len() - 1))
+}
+
+/// Format of the field for `struct.pack`.
+/// The number of used elements of a bounded array and the size of the structs with a variable size are written for an f-string.
+pub fn gen_format(field: &StructField, package: &Package) -> String {
+    // Pad bytes, they're zero and not unpacked
+    if field.reserved {
+        return format!("{}x", field.elements());
+    }
+
+    let attr = gen_attr(field);
+    let element = gen_pack_format(&field.t, package);
+    let prefix = match field.length_type() {
+        Some(l) => gen_pack_format(&FieldType::PRIMITIVE(l), package),
+        None => String::new()
     };
 
+    // Nothing is packed for a missing optional field
+    if field.optional {
+        return match &field.t {
+            FieldType::COMPLEX(ComplexTypes::Struct(_) | ComplexTypes::Union(_)) if field.t.is_variable(package) => format!("{{self.{}.bytes_length() - 4 if self.{} is not None else 0}}B", attr, attr),
+            FieldType::COMPLEX(ComplexTypes::Struct(_) | ComplexTypes::Union(_)) => format!("{{{} if self.{} is not None else 0}}B", field.t.size(package), attr),
+            _ => format!("{{\"{}\" if self.{} is not None else \"\"}}", element, attr)
+        };
+    }
+
+    // Structs with a variable size are packed as their bytes
+    if field.t.is_variable(package) {
+        return match field.is_array() {
+            true => {
+                let (iter, x) = gen_flat_iter(field);
+                format!("{}{{sum({}.bytes_length() - 4 {})}}B", prefix, x, iter)
+            },
+            false => format!("{{self.{}.bytes_length() - 4}}B", attr)
+        };
+    }
+
+    if field.bounded {
+        return match &field.t {
+            FieldType::STRING(_) => format!("{}{{\"{}\" * len(self.{})}}", prefix, element, attr),
+            FieldType::COMPLEX(ComplexTypes::Struct(_) | ComplexTypes::Union(_)) => format!("{}{{len(self.{}) * {}}}B", prefix, attr, field.t.size(package)),
+            _ => format!("{}{{len(self.{})}}{}", prefix, attr, element)
+        };
+    }
+
+    element.repeat(field.elements() as usize)
+}
+
+/// Bytes of the field added to the base size of the struct, `None` when the size of the field doesn't change
+pub fn gen_bytes_length(field: &StructField, package: &Package) -> Option<String> {
+    let attr = gen_attr(field);
+    if !field.is_variable(package) {
+        return None;
+    }
+
+    if field.optional {
+        return match field.t.is_variable(package) {
+            true => Some(format!("(self.{}.bytes_length() - 4 if self.{} is not None else 0)", attr, attr)),
+            false => Some(format!("({} if self.{} is not None else 0)", field.t.size(package), attr))
+        };
+    }
+
+    if !field.t.is_variable(package) {
+        return match field.t.size(package) {
+            1 => Some(format!("len(self.{})", attr)),
+            n => Some(format!("len(self.{}) * {}", attr, n))
+        };
+    }
+
+    match field.is_array() {
+        true => {
+            let (iter, x) = gen_flat_iter(field);
+            Some(format!("sum({}.bytes_length() - 4 {})", x, iter))
+        },
+        false => Some(format!("(self.{}.bytes_length() - 4)", attr))
+    }
+}
+
+/// Decode an optional field, present when its `bit` is set in the `presence` bitmap, `None` is added to `data` when it's missing
+fn gen_decode_optional(field: &StructField, bit: u32, package: &Package) -> String {
+    let mut out = String::new();
+    let indent = " ".repeat(24);
+
+    out.push_str(format!("                    if Iris.read_bits(presence, {}, 1):\n", bit).as_str());
+    match &field.t {
+        FieldType::COMPLEX(c @ (ComplexTypes::Struct(_) | ComplexTypes::Union(_))) if field.t.is_variable(package) => {
+            out.push_str(format!("{}data += ({}.from_be_bytes(raw[i:]),)\n", indent, gen_type_path(c, package)).as_str());
+            out.push_str(format!("{}i += data[-1].bytes_length() - 4\n", indent).as_str());
+        },
+        FieldType::COMPLEX(c @ (ComplexTypes::Struct(_) | ComplexTypes::Union(_))) => {
+            out.push_str(format!("{}data += ({}.from_be_bytes(raw[i:i + {}]),)\n", indent, gen_type_path(c, package), field.t.size(package)).as_str());
+            out.push_str(format!("{}i += {}\n", indent, field.t.size(package)).as_str());
+        },
+        FieldType::STRING(_) => {
+            out.push_str(format!("{}data += (Iris.string_from_bytes(struct.unpack_from('>{}', raw, i)[0]),)\n", indent, gen_pack_format(&field.t, package)).as_str());
+            out.push_str(format!("{}i += {}\n", indent, field.t.size(package)).as_str());
+        },
+        _ => {
+            let raw = format!("struct.unpack_from('>{}', raw, i)[0]", gen_pack_format(&field.t, package));
+            let value = match gen_flags_class(field, package) {
+                Some(class) => format!("{}({})", class, raw),
+                None => gen_from_raw(field, raw)
+            };
+            out.push_str(format!("{}data += ({},)\n", indent, value).as_str());
+            out.push_str(format!("{}i += {}\n", indent, field.t.size(package)).as_str());
+        }
+    }
+    out.push_str("                    else:\n");
+    out.push_str(format!("{}data += (None,)\n", indent).as_str());
+
+    out
+}
+
+/// Decode a field with a variable size from `raw` at the offset `i`, the value is added to the `data` tuple and `i` moves after it.
+/// `presence` is the bit of an optional field.
+pub fn gen_decode_variable(field: &StructField, presence: Option<u32>, package: &Package) -> String {
+    if let Some(bit) = presence {
+        return gen_decode_optional(field, bit, package);
+    }
+
+    let Some(l) = field.length_type() else {
+        return gen_decode_elements(field, field.elements().to_string(), package);
+    };
+
+    let mut out = String::new();
+    let indent = " ".repeat(20);
+
+    // Only the capacity is decoded from a larger number of elements, the list is marked for `validate`
+    out.push_str(format!("{}received = struct.unpack_from('>{}', raw, i)[0]\n", indent, gen_pack_format(&FieldType::PRIMITIVE(l.clone()), package)).as_str());
+    out.push_str(format!("{}n = min(received, {})\n", indent, field.elements()).as_str());
+    out.push_str(format!("{}i += {}\n", indent, l.size()).as_str());
+    out.push_str(gen_decode_elements(field, String::from("n"), package).as_str());
+    out.push_str(format!("{}if received > n:\n", indent).as_str());
+    out.push_str(format!("{}    data = data[:-1] + (Iris.Truncated(data[-1]),)\n", indent).as_str());
+
     out
 }
 
+/// Decode the `n` elements of an array, or the value of a field, from `raw` at the offset `i`, like `gen_decode_variable`
+fn gen_decode_elements(field: &StructField, n: String, package: &Package) -> String {
+    let mut out = String::new();
+    let indent = " ".repeat(20);
+
+    let FieldType::COMPLEX(c) = &field.t else {
+        // Bounded array of numbers or strings
+        let size = field.t.size(package);
+        let value = match &field.t {
+            FieldType::STRING(_) => format!("[Iris.string_from_bytes(x) for x in struct.unpack_from('>' + '{}' * n, raw, i)]", gen_pack_format(&field.t, package)),
+            _ if field.scaling.is_some() => format!("[{} for x in struct.unpack_from(f'>{{n}}{}', raw, i)]", gen_from_raw(field, String::from("x")), gen_pack_format(&field.t, package)),
+            _ => format!("list(struct.unpack_from(f'>{{n}}{}', raw, i))", gen_pack_format(&field.t, package))
+        };
+        out.push_str(format!("{}data += ({},)\n", indent, value).as_str());
+        out.push_str(format!("{}i += n * {}\n", indent, size).as_str());
+        return out;
+    };
+
+    if !field.t.is_variable(package) {
+        // Bounded array of structs, unions or enums with a fixed size
+        let size = field.t.size(package);
+        match c {
+            ComplexTypes::Enum(_) => {
+                let values = format!("struct.unpack_from(f'>{{n}}{}', raw, i)", gen_pack_format(&field.t, package));
+                match gen_flags_class(field, package) {
+                    Some(class) => out.push_str(format!("{}data += ([{}(x) for x in {}],)\n", indent, class, values).as_str()),
+                    None => out.push_str(format!("{}data += (list({}),)\n", indent, values).as_str())
+                }
+            },
+            _ => out.push_str(format!("{}data += ([{}.from_be_bytes(raw[i + k * {}:i + (k + 1) * {}]) for k in range(n)],)\n", indent, gen_type_path(c, package), size, size).as_str())
+        }
+        out.push_str(format!("{}i += n * {}\n", indent, size).as_str());
+        return out;
+    }
+
+    // Structs with a variable size, every one starts after the previous
+    if !field.is_array() {
+        out.push_str(format!("{}data += ({}.from_be_bytes(raw[i:]),)\n", indent, gen_type_path(c, package)).as_str());
+        out.push_str(format!("{}i += data[-1].bytes_length() - 4\n", indent).as_str());
+        return out;
+    }
+    out.push_str(format!("{}items = []\n", indent).as_str());
+    out.push_str(format!("{}for _ in range({}):\n", indent, n).as_str());
+    out.push_str(format!("{}    items.append({}.from_be_bytes(raw[i:]))\n", indent, gen_type_path(c, package)).as_str());
+    out.push_str(format!("{}    i += items[-1].bytes_length() - 4\n", indent).as_str());
+    match field.array.len() {
+        1 => out.push_str(format!("{}data += (items,)\n", indent).as_str()),
+        _ => out.push_str(format!("{}data += (Iris.reshape(items, {:?}),)\n", indent, field.array).as_str())
+    }
+
+    out
+}
+
+pub fn gen_pack_arg(field: &StructField) -> String {
+    let attr = gen_attr(field);
+    let mut out = String::new();
+
+    // Nothing is packed for a missing optional field
+    if field.optional {
+        let value = match &field.t {
+            FieldType::COMPLEX(ComplexTypes::Struct(_) | ComplexTypes::Union(_)) => return format!("*(self.{}.to_be_bytes() if self.{} is not None else b'')", attr, attr),
+            FieldType::STRING(n) => format!("Iris.string_to_bytes(self.{}, {})", attr, n),
+            _ => gen_to_raw(field, format!("self.{}", attr))
+        };
+        return format!("*([{}] if self.{} is not None else [])", value, attr);
+    }
+
+    // A bounded array starts with the number of elements
+    if field.bounded {
+        out.push_str(format!("Iris.check_length(self.{}, {}), ", attr, field.elements()).as_str());
+    }
+
+    if field.is_array() {
+        let (iter, x) = gen_flat_iter(field);
+
+        match &field.t {
+            FieldType::COMPLEX(ComplexTypes::Struct(_) | ComplexTypes::Union(_)) => out.push_str(format!("*itertools.chain.from_iterable({}.to_be_bytes() {})", x, iter).as_str()),
+            FieldType::STRING(n) => out.push_str(format!("*[Iris.string_to_bytes({}, {}) {}]", x, n, iter).as_str()),
+            _ => out.push_str(format!("*[{} {}]", gen_to_raw(field, x), iter).as_str())
+        }
+
+        return out;
+    }
+
+    match &field.t {
+        FieldType::COMPLEX(c) => {
+            match c {
+                ComplexTypes::Struct(_) | ComplexTypes::Union(_) => out.push_str(format!("*self.{}.to_be_bytes()", attr).as_str()),
+                ComplexTypes::Enum(_) => out.push_str(format!("self.{}", attr).as_str()),
+                ComplexTypes::Unknown(_) => unreachable!("Types are resolved by the semantic analysis.")
+            }
+        },
+        FieldType::PRIMITIVE(_) => out.push_str(gen_to_raw(field, format!("self.{}", attr)).as_str()),
+        FieldType::STRING(n) => out.push_str(format!("Iris.string_to_bytes(self.{}, {})", attr, n).as_str())
+    }
+
+    out
+}
@@ -1,97 +1,254 @@
-use crate::core::ast::{FieldType, Package, Struct};
+use crate::core::ast::{FieldType, Package, PrimitiveTypes, Struct};
 
-use super::field::gen_pack_arg;
-use super::types::gen_pack_format;
+use crate::core::generators::code_gen::{doc_lines, gen_comment};
+
+use super::field::{gen_attr, gen_bytes_length, gen_decode_variable, gen_flags_class, gen_format, gen_from_raw, gen_init_default, gen_pack_arg, gen_validate};
+use super::types::gen_type_path;
+
+/// Docstring of a class, from the doc comment of the declaration
+pub fn gen_docstring(doc: &Option<String>) -> String {
+    let doc = doc_lines(doc, &None);
+    if doc.is_empty() {
+        return String::new();
+    }
+
+    // The lines after the first are indented like the body of the class
+    let doc = doc.iter().enumerate()
+        .map(|(i, l)| if i == 0 || l.is_empty() { l.clone() } else { format!("                {}", l) })
+        .collect::<Vec<String>>()
+        .join("\n")
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+
+    format!("                \"\"\"{}\"\"\"\n", doc)
+}
 
 pub fn gen_code(strc: &Struct, package: &Package) -> String {
     let mut out = String::new();
+    let variable = strc.is_variable(package);
     let mut struct_format = String::new();
-    struct_format.push_str(format!(">").as_str());
-    for f in &strc.fields_order {
-        struct_format.push_str(gen_pack_format(&strc.fields.get(f).unwrap().t, &package).as_str());
-        for _ in 1..strc.fields.get(f).unwrap().array.unwrap_or_default() {
-            struct_format.push_str(gen_pack_format(&strc.fields.get(f).unwrap().t, &package).as_str());
+    struct_format.push('>');
+    if strc.presence_bytes() > 0 {
+        struct_format.push_str(format!("{}s", strc.presence_bytes()).as_str());
+    }
+    for (f, bits) in strc.fields.values().zip(strc.bit_ranges()) {
+        // The bitfields are packed together in a byte string, added after the last one
+        if let Some(bits) = bits {
+            if let Some(n) = bits.run_bytes {
+                struct_format.push_str(format!("{}s", n).as_str());
+            }
+            continue;
         }
+        struct_format.push_str(gen_format(f, package).as_str());
     }
 
     out.push_str(format!("            class {}:\n", strc.name).as_str());
-    out.push_str(format!("                NAME_HASH = {}\n", strc.fnv_1a()).as_str());
-    out.push_str(format!("                BYTES_LENGTH = {} + 4\n", strc.size(package)).as_str());
+    out.push_str(gen_docstring(&strc.doc).as_str());
+    out.push_str(format!("                NAME_HASH = {}\n", strc.id()).as_str());
+    match variable {
+        true => out.push_str(format!("                MAX_BYTES_LENGTH = {} + 4\n", strc.size(package)).as_str()),
+        false => out.push_str(format!("                BYTES_LENGTH = {} + 4\n", strc.size(package)).as_str())
+    }
 
+    // Reserved bytes are only in the encoded struct
+    let fields: Vec<_> = strc.fields.values().filter(|f| !f.reserved).collect();
     out.push_str("                def __init__(self");
-    for f in &strc.fields_order {
-        out.push_str(format!(", {}", f).as_str());
+    for f in fields.iter() {
+        out.push_str(format!(", {}={}", f.name, gen_init_default(f, package).0).as_str());
     }
     out.push_str("):\n");
-    for f in &strc.fields_order {
-        out.push_str(format!("                    self.{} = {}\n", f, f).as_str());
+    for f in fields.iter() {
+        out.push_str(gen_comment(&doc_lines(&f.doc, &f.unit), "                    #:").as_str());
+        match gen_init_default(f, package).1 {
+            Some(value) => out.push_str(format!("                    self.{} = {} if {} is not None else {}\n", gen_attr(f), f.name, f.name, value).as_str()),
+            None => out.push_str(format!("                    self.{} = {}\n", gen_attr(f), f.name).as_str())
+        }
+    }
+    if fields.is_empty() {
+        out.push_str("                    pass\n");
+    }
+
+    // The deprecated fields warn when they're used from outside
+    for f in fields.iter() {
+        let Some(note) = f.deprecated.as_deref() else {
+            continue;
+        };
+        let message = match note {
+            "" => format!("`{}.{}` is deprecated.", strc.name, f.name),
+            note => format!("`{}.{}` is deprecated: {}", strc.name, f.name, note)
+        };
+        out.push_str("                @property\n");
+        out.push_str(format!("                def {}(self):\n", f.name).as_str());
+        out.push_str(format!("                    warnings.warn({:?}, DeprecationWarning, stacklevel=2)\n", message).as_str());
+        out.push_str(format!("                    return self.{}\n", gen_attr(f)).as_str());
+        out.push_str(format!("                @{}.setter\n", f.name).as_str());
+        out.push_str(format!("                def {}(self, value):\n", f.name).as_str());
+        out.push_str(format!("                    warnings.warn({:?}, DeprecationWarning, stacklevel=2)\n", message).as_str());
+        out.push_str(format!("                    self.{} = value\n", gen_attr(f)).as_str());
+    }
+
+    if variable {
+        out.push_str("                def bytes_length(self) -> int:\n");
+        out.push_str("                    \"\"\"Encoded size in bytes, it depends on the elements of the bounded arrays and the optional fields that are set\"\"\"\n");
+        out.push_str(format!("                    return {} + 4", strc.base_size(package)).as_str());
+        for f in strc.fields.values() {
+            if let Some(l) = gen_bytes_length(f, package) {
+                out.push_str(format!(" + {}", l).as_str());
+            }
+        }
+        out.push('\n');
     }
 
     out.push_str("                def encode(self) -> bytes:\n");
-    out.push_str(format!("                    return struct.pack('>I{}B', self.NAME_HASH, *self.to_be_bytes())\n", strc.size(package)).as_str());
+    match variable {
+        true => out.push_str("                    return struct.pack('>I', self.NAME_HASH) + self.to_be_bytes()\n"),
+        false => out.push_str(format!("                    return struct.pack('>I{}B', self.NAME_HASH, *self.to_be_bytes())\n", strc.size(package)).as_str())
+    }
 
+    // With a variable size the format depends on the values
     out.push_str("                def to_be_bytes(self) -> bytes:\n");
-    out.push_str(format!("                    return struct.pack('{}'", struct_format).as_str());
-    for f in &strc.fields_order {
+    out.push_str(format!("                    return struct.pack({}'{}'", if variable { "f" } else { "" }, struct_format).as_str());
+    // Presence bitmap of the optional fields
+    if strc.presence_bytes() > 0 {
+        let bits: Vec<_> = strc.fields.values().filter(|f| f.optional).map(|f| format!("(self.{} is not None, 1)", gen_attr(f))).collect();
+        out.push_str(format!(", Iris.pack_bits({}, [{}])", strc.presence_bytes(), bits.join(", ")).as_str());
+    }
+    let mut run = Vec::new();
+    for (f, bits) in strc.fields.values().zip(strc.bit_ranges()) {
+        if let Some(bits) = bits {
+            run.push(format!("(self.{}, {})", gen_attr(f), bits.width));
+            if let Some(n) = bits.run_bytes {
+                out.push_str(format!(", Iris.pack_bits({}, [{}])", n, run.join(", ")).as_str());
+                run.clear();
+            }
+            continue;
+        }
+        // The pad bytes of the format have no argument
+        if f.reserved {
+            continue;
+        }
         out.push_str(", ");
-        out.push_str(gen_pack_arg(strc.fields.get(f).unwrap()).as_str());
+        out.push_str(gen_pack_arg(f).as_str());
     }
     out.push_str(")\n");
 
     out.push_str("                @staticmethod\n");
-    out.push_str("                def decode(raw: bytes):\n");
-    out.push_str(format!("                    data = struct.unpack('>I{}B', raw)\n", strc.size(package)).as_str());
-    out.push_str(format!("                    return Iris.Packages.{}.{}.from_be_bytes(bytes(data[1:]))\n", package.name.as_ref().unwrap(), strc.name).as_str());
+    out.push_str("                def decode(raw: bytes, validate: bool = False):\n");
+    if variable {
+        out.push_str(format!("                    out = Iris.Packages.{}.{}.from_be_bytes(raw[4:])\n", package.name.as_ref().unwrap(), strc.name).as_str());
+    }
+    else {
+        out.push_str(format!("                    data = struct.unpack('>I{}B', raw)\n", strc.size(package)).as_str());
+        out.push_str(format!("                    out = Iris.Packages.{}.{}.from_be_bytes(bytes(data[1:]))\n", package.name.as_ref().unwrap(), strc.name).as_str());
+    }
+    out.push_str("                    if validate and (field := out.validate()) is not None:\n");
+    out.push_str("                        raise ValueError(f'Field `{field}` is not valid.')\n");
+    out.push_str("                    return out\n");
+
+    out.push_str("                def validate(self):\n");
+    out.push_str("                    \"\"\"Check the limits set with `@range`, `@min` and `@max` and that the enum values are variants, return the first field failing it or `None`\"\"\"\n");
+    for f in strc.fields.values() {
+        out.push_str(gen_validate(f, package, "self", "", 20).as_str());
+    }
+    out.push_str("                    return None\n");
 
     out.push_str("                @staticmethod\n");
     out.push_str("                def from_be_bytes(raw: bytes):\n");
-    out.push_str(format!("                    data = struct.unpack('{}', raw)\n", struct_format).as_str());
+    match variable {
+        true => out.push_str(gen_decode_segments(strc, package).as_str()),
+        false => out.push_str(format!("                    data = struct.unpack('{}', raw)\n", struct_format).as_str())
+    }
     out.push_str(format!("                    return Iris.Packages.{}.{}(\n", package.name.as_ref().unwrap(), strc.name).as_str());
     let mut data_index = 0;
-    for f in &strc.fields_order {
-        let f = strc.fields.get(f).unwrap();
+    for (f, bits) in strc.fields.values().zip(strc.bit_ranges()) {
+        if f.reserved {
+            continue;
+        }
         out.push_str(format!("                        {}=", f.name).as_str());
-        match f.array {
-            Some(n) => {
-                match &f.t {
-                    FieldType::COMPLEX(c) => {
-                        match c {
-                            crate::core::ast::ComplexTypes::Struct(s) => {
-                                out.push_str(format!("[Iris.Packages.{}.{}.from_be_bytes(bytes(data[i:i+{}])) for i in range({}, {}, {})]", package.name.as_ref().unwrap(), s, f.t.size(package), data_index, f.size(package), f.t.size(package)).as_str());
-                                data_index += f.size(package);
-                            },
-                            crate::core::ast::ComplexTypes::Enum(_) => {
-                                out.push_str(format!("data[{}:{}]", data_index, data_index + n).as_str());
-                                data_index += f.size(package);
-                            },
-                            crate::core::ast::ComplexTypes::Unknown(_) => todo!(),
-                        }
-                    },
-                    FieldType::PRIMITIVE(_) => {
-                        out.push_str(format!("data[{}:{}]", data_index, data_index + n).as_str());
-                        data_index += n;
+        // Already decoded by `gen_decode_segments`
+        if f.is_variable(package) {
+            out.push_str(format!("data[{}]", data_index).as_str());
+            data_index += 1;
+        }
+        else if let Some(bits) = bits {
+            let value = format!("Iris.read_bits(data[{}], {}, {})", data_index, bits.offset, bits.width);
+            match f.t {
+                FieldType::PRIMITIVE(PrimitiveTypes::Bool) => out.push_str(format!("bool({})", value).as_str()),
+                _ => out.push_str(value.as_str())
+            }
+            if bits.run_bytes.is_some() {
+                data_index += 1;
+            }
+        }
+        else if f.is_array() {
+            let n = f.elements();
+            // The elements are decoded in a flat list, then split in the dimensions of the array
+            let elements = match &f.t {
+                FieldType::COMPLEX(c) => {
+                    match c {
+                        crate::core::ast::ComplexTypes::Struct(_) | crate::core::ast::ComplexTypes::Union(_) => {
+                            let out = format!("[{}.from_be_bytes(bytes(data[i:i+{}])) for i in range({}, {}, {})]", gen_type_path(c, package), f.t.size(package), data_index, data_index + f.size(package), f.t.size(package));
+                            data_index += f.size(package);
+                            out
+                        },
+                        crate::core::ast::ComplexTypes::Enum(_) => {
+                            let out = match gen_flags_class(f, package) {
+                                Some(class) => format!("[{}(x) for x in data[{}:{}]]", class, data_index, data_index + n),
+                                None => format!("data[{}:{}]", data_index, data_index + n)
+                            };
+                            data_index += n;
+                            out
+                        },
+                        crate::core::ast::ComplexTypes::Unknown(_) => unreachable!("Types are resolved by the semantic analysis."),
                     }
+                },
+                FieldType::PRIMITIVE(_) => {
+                    let out = match f.scaling {
+                        Some(_) => format!("[{} for x in data[{}:{}]]", gen_from_raw(f, String::from("x")), data_index, data_index + n),
+                        None => format!("data[{}:{}]", data_index, data_index + n)
+                    };
+                    data_index += n;
+                    out
+                },
+                FieldType::STRING(_) => {
+                    let out = format!("[Iris.string_from_bytes(i) for i in data[{}:{}]]", data_index, data_index + n);
+                    data_index += n;
+                    out
                 }
-            },
-            None => {
-                match &f.t {
-                    FieldType::COMPLEX(c) => {
-                        match c {
-                            crate::core::ast::ComplexTypes::Struct(s) => {
-                                out.push_str(format!("Iris.Packages.{}.{}.from_be_bytes(bytes(data[{}:{}]))", package.name.as_ref().unwrap(), s, data_index, data_index + strc.size(package)).as_str());
-                                data_index += f.size(package);
-                            },
-                            crate::core::ast::ComplexTypes::Enum(_) => {
-                                out.push_str(format!("data[{}]", data_index).as_str());
-                                data_index += 1;
-                            },
-                            crate::core::ast::ComplexTypes::Unknown(_) => todo!(),
-                        }
-                    },
-                    FieldType::PRIMITIVE(_) => {
-                        out.push_str(format!("data[{}]", data_index).as_str());
-                        data_index += 1;
+            };
+
+            if f.array.len() > 1 {
+                out.push_str(format!("Iris.reshape({}, {:?})", elements, f.array).as_str());
+            }
+            else {
+                out.push_str(elements.as_str());
+            }
+        }
+        else {
+            match &f.t {
+                FieldType::COMPLEX(c) => {
+                    match c {
+                        crate::core::ast::ComplexTypes::Struct(_) | crate::core::ast::ComplexTypes::Union(_) => {
+                            out.push_str(format!("{}.from_be_bytes(bytes(data[{}:{}]))", gen_type_path(c, package), data_index, data_index + f.size(package)).as_str());
+                            data_index += f.size(package);
+                        },
+                        crate::core::ast::ComplexTypes::Enum(_) => {
+                            match gen_flags_class(f, package) {
+                                Some(class) => out.push_str(format!("{}(data[{}])", class, data_index).as_str()),
+                                None => out.push_str(format!("data[{}]", data_index).as_str())
+                            }
+                            data_index += 1;
+                        },
+                        crate::core::ast::ComplexTypes::Unknown(_) => unreachable!("Types are resolved by the semantic analysis."),
                     }
+                },
+                FieldType::PRIMITIVE(_) => {
+                    out.push_str(gen_from_raw(f, format!("data[{}]", data_index)).as_str());
+                    data_index += 1;
+                },
+                FieldType::STRING(_) => {
+                    out.push_str(format!("Iris.string_from_bytes(data[{}])", data_index).as_str());
+                    data_index += 1;
                 }
             }
         }
@@ -100,4 +257,45 @@ pub fn gen_code(strc: &Struct, package: &Package) -> String {
     out.push_str("                    )\n");
 
     out
-}
\ No newline at end of file
+}
+/// Decode of a struct with a variable size, the fixed fields between the variable ones are unpacked together
+fn gen_decode_segments(strc: &Struct, package: &Package) -> String {
+    let mut out = String::new();
+    out.push_str("                    data = ()\n");
+    // The presence bitmap of the optional fields comes first
+    match strc.presence_bytes() {
+        0 => out.push_str("                    i = 0\n"),
+        n => {
+            out.push_str(format!("                    presence = raw[0:{}]\n", n).as_str());
+            out.push_str(format!("                    i = {}\n", n).as_str());
+        }
+    }
+    let mut format = String::new();
+    let mut size = 0;
+    let flush = |out: &mut String, format: &mut String, size: &mut u32| {
+        if !format.is_empty() {
+            out.push_str(format!("                    data += struct.unpack_from('>{}', raw, i)\n", format).as_str());
+            out.push_str(format!("                    i += {}\n", size).as_str());
+            format.clear();
+            *size = 0;
+        }
+    };
+    for ((f, bits), presence) in strc.fields.values().zip(strc.bit_ranges()).zip(strc.presence_bits()) {
+        if let Some(bits) = bits {
+            if let Some(n) = bits.run_bytes {
+                format.push_str(format!("{}s", n).as_str());
+                size += n;
+            }
+            continue;
+        }
+        if f.is_variable(package) {
+            flush(&mut out, &mut format, &mut size);
+            out.push_str(gen_decode_variable(f, presence, package).as_str());
+            continue;
+        }
+        format.push_str(gen_format(f, package).as_str());
+        size += f.size(package);
+    }
+    flush(&mut out, &mut format, &mut size);
+    out
+}
@@ -1,5 +1,10 @@
 use crate::core::ast::{ComplexTypes, FieldType, Package, PrimitiveTypes};
 
+/// Path of a struct or enum from the top of the file, the types of imported packages are in the class of their package
+pub fn gen_type_path(c: &ComplexTypes, package: &Package) -> String {
+    format!("Iris.Packages.{}.{}", c.package().unwrap_or(package.name.as_ref().unwrap()), c.name())
+}
+
 pub fn gen_pack_format(t: &FieldType, package: &Package) -> String {
     match t {
         FieldType::PRIMITIVE(p) => {
@@ -10,16 +15,20 @@ pub fn gen_pack_format(t: &FieldType, package: &Package) -> String {
                 PrimitiveTypes::I16 => String::from("h"),
                 PrimitiveTypes::U32 => String::from("I"),
                 PrimitiveTypes::I32 => String::from("i"),
+                PrimitiveTypes::U64 => String::from("Q"),
+                PrimitiveTypes::I64 => String::from("q"),
                 PrimitiveTypes::Bool => String::from("?"),
-                PrimitiveTypes::F32 => String::from("f")
+                PrimitiveTypes::F32 => String::from("f"),
+                PrimitiveTypes::F64 => String::from("d")
             }
         },
         FieldType::COMPLEX(c) => {
             match c {
-                ComplexTypes::Struct(_s) => format!("{}B", t.size(package)),
-                ComplexTypes::Enum(_e) => String::from("I"),
-                ComplexTypes::Unknown(_u) => panic!("Can't generate pack format value for unknown type.")
+                ComplexTypes::Struct(_) | ComplexTypes::Union(_) => format!("{}B", t.size(package)),
+                ComplexTypes::Enum(e) => gen_pack_format(&FieldType::PRIMITIVE(package.get_enum(e).repr.clone()), package),
+                ComplexTypes::Unknown(_u) => unreachable!("Types are resolved by the semantic analysis.")
             }
-        }
+        },
+        FieldType::STRING(n) => format!("{}s", n)
     }
 }
\ No newline at end of file
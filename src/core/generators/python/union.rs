@@ -0,0 +1,50 @@
+use crate::core::ast::{ComplexTypes, FieldType, Package, Union};
+
+use super::r#struct::gen_docstring;
+use super::types::{gen_pack_format, gen_type_path};
+
+pub fn gen_code(union: &Union, package: &Package) -> String {
+    let mut out = String::new();
+    let format = gen_pack_format(&FieldType::PRIMITIVE(union.repr.clone()), package);
+    let path = gen_type_path(&ComplexTypes::Union(union.name.clone()), package);
+    let size = union.size(package);
+    // The struct of the variant starts after its value
+    let start = union.repr.size();
+
+    out.push_str(format!("            class {}:\n", union.name).as_str());
+    out.push_str(gen_docstring(&union.doc).as_str());
+    out.push_str(format!("                BYTES_LENGTH = {}\n", size).as_str());
+    out.push_str("                class Tag(enum.IntEnum):\n");
+    for v in union.variants.values() {
+        out.push_str(format!("                    {} = {}\n", v.name, v.value).as_str());
+    }
+
+    // `value` is the struct of the variant `tag`, the default is the first variant
+    let (_, first) = union.variants.first().unwrap();
+    let FieldType::COMPLEX(first_type) = &first.t else {
+        unreachable!("Union variants are checked by the semantic analysis.");
+    };
+    out.push_str("                def __init__(self, tag=None, value=None):\n");
+    out.push_str(format!("                    self.tag = tag if tag is not None else {}.Tag.{}\n", path, first.name).as_str());
+    out.push_str(format!("                    self.value = value if value is not None else {}()\n", gen_type_path(first_type, package)).as_str());
+
+    // The bytes after a struct smaller than the largest one are zero
+    out.push_str("                def to_be_bytes(self) -> bytes:\n");
+    out.push_str(format!("                    return (struct.pack('>{}', self.tag) + self.value.to_be_bytes()).ljust({}, b'\\0')\n", format, size).as_str());
+
+    out.push_str("                @staticmethod\n");
+    out.push_str("                def from_be_bytes(raw: bytes):\n");
+    out.push_str(format!("                    match struct.unpack('>{}', raw[0:{}])[0]:\n", format, start).as_str());
+    for v in union.variants.values() {
+        let FieldType::COMPLEX(t) = &v.t else {
+            unreachable!("Union variants are checked by the semantic analysis.");
+        };
+        out.push_str(format!("                        case {}:\n", v.value).as_str());
+        out.push_str(format!("                            return {}({}.Tag.{}, {}.from_be_bytes(raw[{}:{}]))\n", path, path, v.name, gen_type_path(t, package), start, start + v.t.size(package)).as_str());
+    }
+    // An unknown tag is kept with the default variant, `validate` of the struct reports it
+    out.push_str("                        case tag:\n");
+    out.push_str(format!("                            return {}(tag)\n", path).as_str());
+
+    out
+}
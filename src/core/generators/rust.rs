@@ -1,7 +1,6 @@
 /**
  * Code generation for Rust (no std).
  */
-
 use package::gen_code;
 
 use super::code_gen::CodeGen;
@@ -10,6 +9,8 @@ pub mod types;
 pub mod field;
 pub mod r#struct;
 pub mod enumeration;
+pub mod union;
+pub mod constant;
 pub mod package;
 
 
@@ -20,7 +21,14 @@ impl CodeGen for Rust {
     fn gen_code(&self, package: &crate::core::ast::Package) -> String {
         let mut out = String::new();
 
-        out.push_str(gen_code(&package).as_str());
+        out.push_str(gen_fixed_string().as_str());
+        out.push_str(gen_bounded_array().as_str());
+        out.push_str(gen_bits().as_str());
+
+        // Imported packages come first, every package is a module
+        for p in package.all_packages() {
+            out.push_str(gen_code(p).as_str());
+        }
 
         out.push_str("pub enum Structs {\n");
         for s in package.structs.values() {
@@ -34,7 +42,12 @@ impl CodeGen for Rust {
         
         out.push_str("match struct_name_hash {\n");
         for s in package.structs.values() {
-            out.push_str(format!("{}::{}::NAME_HASH if data.len() == {}::{}::BYTES_LENGTH => Ok(Structs::{}({}::{}::decode(&data))),\n", package.name.clone().unwrap(), s.name, package.name.clone().unwrap(), s.name, s.name, package.name.clone().unwrap(), s.name).as_str());
+            // A struct with a variable size can be shorter than the largest one
+            let length = match s.is_variable(package) {
+                true => format!("<= {}::{}::MAX_BYTES_LENGTH", package.name.clone().unwrap(), s.name),
+                false => format!("== {}::{}::BYTES_LENGTH", package.name.clone().unwrap(), s.name)
+            };
+            out.push_str(format!("{}::{}::NAME_HASH if data.len() {} => Ok(Structs::{}({}::{}::decode(&data))),\n", package.name.clone().unwrap(), s.name, length, s.name, package.name.clone().unwrap(), s.name).as_str());
         }
         out.push_str("_ => Err(\"Unknown data.\")\n");
         out.push_str("}\n");
@@ -43,4 +56,188 @@ impl CodeGen for Rust {
 
         out
     }
-}
\ No newline at end of file
+}
+
+/// UTF-8 string with a fixed capacity, used for `string[N]` fields.
+/// The bytes after the string are zero, so the wire format is the buffer itself.
+fn gen_fixed_string() -> String {
+    let mut out = String::new();
+
+    // The packages are modules next to the helpers, a const argument in braces is resolved as a value even if a package has its name
+
+    out.push_str("#[derive(Copy, Clone, PartialEq, Eq)]\n");
+    out.push_str("pub struct FixedString<const CAP: usize> {\n");
+    out.push_str("data: [u8; CAP],\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<const CAP: usize> FixedString<{ CAP }> {\n");
+    out.push_str("pub const CAPACITY: usize = CAP;\n");
+    out.push_str("pub const fn new() -> FixedString<{ CAP }> {\n");
+    out.push_str("FixedString { data: [0; CAP] }\n");
+    out.push_str("}\n");
+    out.push_str("pub fn set(&mut self, s: &str) -> Result<(), &'static str> {\n");
+    out.push_str("if s.len() > CAP {\n");
+    out.push_str("return Err(\"String too long.\");\n");
+    out.push_str("}\n");
+    out.push_str("self.data = [0; CAP];\n");
+    out.push_str("self.data[..s.len()].copy_from_slice(s.as_bytes());\n");
+    out.push_str("Ok(())\n");
+    out.push_str("}\n");
+    out.push_str("pub fn len(&self) -> usize {\n");
+    out.push_str("self.data.iter().position(|b| *b == 0).unwrap_or(CAP)\n");
+    out.push_str("}\n");
+    out.push_str("pub fn is_empty(&self) -> bool {\n");
+    out.push_str("self.len() == 0\n");
+    out.push_str("}\n");
+    out.push_str("/// The string up to the first zero byte, invalid UTF-8 received on the wire is cut off\n");
+    out.push_str("pub fn as_str(&self) -> &str {\n");
+    out.push_str("let bytes = &self.data[..self.len()];\n");
+    out.push_str("match core::str::from_utf8(bytes) {\n");
+    out.push_str("Ok(s) => s,\n");
+    out.push_str("Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(),\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+    out.push_str("pub fn to_be_bytes(&self) -> [u8; CAP] {\n");
+    out.push_str("self.data\n");
+    out.push_str("}\n");
+    out.push_str("pub fn from_be_bytes(data: [u8; CAP]) -> FixedString<{ CAP }> {\n");
+    out.push_str("FixedString { data }\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<const CAP: usize> Default for FixedString<{ CAP }> {\n");
+    out.push_str("fn default() -> FixedString<{ CAP }> {\n");
+    out.push_str("FixedString::new()\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<const CAP: usize> TryFrom<&str> for FixedString<{ CAP }> {\n");
+    out.push_str("type Error = &'static str;\n");
+    out.push_str("fn try_from(s: &str) -> Result<FixedString<{ CAP }>, &'static str> {\n");
+    out.push_str("let mut out = FixedString::new();\n");
+    out.push_str("out.set(s)?;\n");
+    out.push_str("Ok(out)\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<const CAP: usize> core::fmt::Debug for FixedString<{ CAP }> {\n");
+    out.push_str("fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {\n");
+    out.push_str("core::fmt::Debug::fmt(self.as_str(), f)\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out
+}
+
+/// Array with a maximum number of elements, used for `T[<=N]` fields.
+/// It dereferences to the slice of the elements in use, only those are encoded.
+fn gen_bounded_array() -> String {
+    let mut out = String::new();
+
+    // The capacity is passed in braces like in `FixedString`
+
+    out.push_str("#[derive(Copy, Clone)]\n");
+    out.push_str("pub struct BoundedArray<Item, const CAP: usize> {\n");
+    out.push_str("data: [Item; CAP],\n");
+    out.push_str("len: usize,\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<Item: Copy + Default, const CAP: usize> BoundedArray<Item, { CAP }> {\n");
+    out.push_str("pub const CAPACITY: usize = CAP;\n");
+    out.push_str("pub fn new() -> BoundedArray<Item, { CAP }> {\n");
+    out.push_str("BoundedArray { data: [Item::default(); CAP], len: 0 }\n");
+    out.push_str("}\n");
+    out.push_str("pub fn push(&mut self, value: Item) -> Result<(), &'static str> {\n");
+    out.push_str("if self.len == CAP {\n");
+    out.push_str("return Err(\"Array full.\");\n");
+    out.push_str("}\n");
+    out.push_str("self.data[self.len] = value;\n");
+    out.push_str("self.len += 1;\n");
+    out.push_str("Ok(())\n");
+    out.push_str("}\n");
+    out.push_str("pub fn pop(&mut self) -> Option<Item> {\n");
+    out.push_str("if self.len == 0 {\n");
+    out.push_str("return None;\n");
+    out.push_str("}\n");
+    out.push_str("self.len -= 1;\n");
+    out.push_str("Some(self.data[self.len])\n");
+    out.push_str("}\n");
+    out.push_str("pub fn clear(&mut self) {\n");
+    out.push_str("self.len = 0;\n");
+    out.push_str("}\n");
+    out.push_str("pub fn as_slice(&self) -> &[Item] {\n");
+    out.push_str("&self.data[..self.len]\n");
+    out.push_str("}\n");
+    out.push_str("pub fn as_mut_slice(&mut self) -> &mut [Item] {\n");
+    out.push_str("&mut self.data[..self.len]\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<Item: Copy + Default, const CAP: usize> Default for BoundedArray<Item, { CAP }> {\n");
+    out.push_str("fn default() -> BoundedArray<Item, { CAP }> {\n");
+    out.push_str("BoundedArray::new()\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<Item: Copy + Default, const CAP: usize> core::ops::Deref for BoundedArray<Item, { CAP }> {\n");
+    out.push_str("type Target = [Item];\n");
+    out.push_str("fn deref(&self) -> &[Item] {\n");
+    out.push_str("self.as_slice()\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<Item: Copy + Default, const CAP: usize> core::ops::DerefMut for BoundedArray<Item, { CAP }> {\n");
+    out.push_str("fn deref_mut(&mut self) -> &mut [Item] {\n");
+    out.push_str("self.as_mut_slice()\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<Item: Copy + Default, const CAP: usize> TryFrom<&[Item]> for BoundedArray<Item, { CAP }> {\n");
+    out.push_str("type Error = &'static str;\n");
+    out.push_str("fn try_from(s: &[Item]) -> Result<BoundedArray<Item, { CAP }>, &'static str> {\n");
+    out.push_str("if s.len() > CAP {\n");
+    out.push_str("return Err(\"Too many elements.\");\n");
+    out.push_str("}\n");
+    out.push_str("let mut out = BoundedArray::new();\n");
+    out.push_str("out.data[..s.len()].copy_from_slice(s);\n");
+    out.push_str("out.len = s.len();\n");
+    out.push_str("Ok(out)\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("impl<Item: Copy + Default + core::fmt::Debug, const CAP: usize> core::fmt::Debug for BoundedArray<Item, { CAP }> {\n");
+    out.push_str("fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {\n");
+    out.push_str("core::fmt::Debug::fmt(self.as_slice(), f)\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out
+}
+
+/// Read and write the bitfields and the presence bitmap of the optional fields, packed MSB first
+fn gen_bits() -> String {
+    let mut out = String::new();
+
+    out.push_str("pub fn write_bits(data: &mut [u8], offset: usize, width: usize, value: u64) {\n");
+    out.push_str("for b in 0..width {\n");
+    out.push_str("let bit = offset + b;\n");
+    out.push_str("let mask = 0x80 >> (bit % 8);\n");
+    out.push_str("if (value >> (width - 1 - b)) & 1 == 1 {\n");
+    out.push_str("data[bit / 8] |= mask;\n");
+    out.push_str("} else {\n");
+    out.push_str("data[bit / 8] &= !mask;\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str("pub fn read_bits(data: &[u8], offset: usize, width: usize) -> u64 {\n");
+    out.push_str("let mut value = 0;\n");
+    out.push_str("for b in 0..width {\n");
+    out.push_str("let bit = offset + b;\n");
+    out.push_str("value = (value << 1) | ((data[bit / 8] >> (7 - bit % 8)) & 1) as u64;\n");
+    out.push_str("}\n");
+    out.push_str("value\n");
+    out.push_str("}\n");
+
+    out
+}
@@ -0,0 +1,14 @@
+use crate::core::ast::{Constant, FieldType};
+
+use crate::core::generators::code_gen::{doc_lines, gen_comment};
+
+use super::field::gen_value;
+
+pub fn gen_code(constant: &Constant) -> String {
+    let mut out = String::new();
+
+    out.push_str(gen_comment(&doc_lines(&constant.doc, &None), "///").as_str());
+    out.push_str(format!("pub const {}: {} = {};\n", constant.name, constant.t.str(), gen_value(&constant.value, &FieldType::PRIMITIVE(constant.t.clone()))).as_str());
+
+    out
+}
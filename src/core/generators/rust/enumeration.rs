@@ -1,9 +1,15 @@
 use crate::core::ast::{Enum, Package};
 
 pub fn gen_code(enmn: &Enum, _package: &Package) -> String {
+    if enmn.flags {
+        return gen_flags(enmn);
+    }
+
     let mut out = String::new();
 
+    // The discriminants have the type of the encoded value, without `repr` they'd be `isize`, too small for `u32` values on 32-bit targets
     out.push_str("#[derive(Copy, Clone)]\n");
+    out.push_str(format!("#[repr({})]\n", enmn.repr.str()).as_str());
     out.push_str(format!("pub enum {} {{\n", enmn.name).as_str());
     for variant in enmn.variants.values() {
         out.push_str(format!("{} = {},\n", variant.name, variant.value).as_str());
@@ -12,25 +18,84 @@ pub fn gen_code(enmn: &Enum, _package: &Package) -> String {
 
     out.push_str(format!("impl {} {{\n", enmn.name).as_str());
     
-    out.push_str("pub fn to_be_bytes(&self) -> [u8; 4] {\n");
-    out.push_str("(match self {\n");
-    for variant in enmn.variants.values() {
-        out.push_str(format!("{}::{} => {},\n", enmn.name, variant.name, variant.value).as_str());
-    }
-    out.push_str("} as u32).to_be_bytes()\n");
+    out.push_str(format!("pub fn to_be_bytes(&self) -> [u8; {}] {{\n", enmn.size()).as_str());
+    out.push_str(format!("(*self as {}).to_be_bytes()\n", enmn.repr.str()).as_str());
     out.push_str("}\n");
     
     
-    out.push_str(format!("pub fn from_be_bytes(data: [u8; 4]) -> {} {{\n", enmn.name).as_str());
-    out.push_str("match u32::from_be_bytes(data) {\n");
+    // A value that isn't a variant can only come from corrupted bytes, the structs report it with `decode_validated`
+    out.push_str("/// The variant with the value of `data`, `None` if there is none\n");
+    out.push_str(format!("pub fn try_from_be_bytes(data: [u8; {}]) -> Option<{}> {{\n", enmn.size(), enmn.name).as_str());
+    out.push_str(format!("match {}::from_be_bytes(data) {{\n", enmn.repr.str()).as_str());
     for variant in enmn.variants.values() {
-        out.push_str(format!("{} => {}::{},\n", variant.value, enmn.name, variant.name).as_str());
+        out.push_str(format!("{} => Some({}::{}),\n", variant.value, enmn.name, variant.name).as_str());
     }
-    out.push_str("_ => panic!(\"No variant found.\")");
+    out.push_str("_ => None,\n");
     out.push_str("}\n");
     out.push_str("}\n");
+
+    if !enmn.variants.is_empty() {
+        out.push_str("/// The variant with the value of `data`, the default one if there is none\n");
+        out.push_str(format!("pub fn from_be_bytes(data: [u8; {}]) -> {} {{\n", enmn.size(), enmn.name).as_str());
+        out.push_str(format!("{}::try_from_be_bytes(data).unwrap_or_default()\n", enmn.name).as_str());
+        out.push_str("}\n");
+    }
     
     out.push_str("}\n");
 
+    // The default is the first variant, like the default value of the fields
+    if let Some(first) = enmn.variants.values().next() {
+        out.push_str(format!("impl Default for {} {{\n", enmn.name).as_str());
+        out.push_str(format!("fn default() -> {} {{\n", enmn.name).as_str());
+        out.push_str(format!("{}::{}\n", enmn.name, first.name).as_str());
+        out.push_str("}\n");
+        out.push_str("}\n");
+    }
+
+    out
+}
+
+/// Flags are a wrapper of the integer, so that any combination of bits can be stored
+fn gen_flags(enmn: &Enum) -> String {
+    let mut out = String::new();
+    let repr = enmn.repr.str();
+
+    out.push_str("#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]\n");
+    out.push_str(format!("pub struct {}(pub {});\n", enmn.name, repr).as_str());
+
+    out.push_str(format!("impl {} {{\n", enmn.name).as_str());
+    for variant in enmn.variants.values() {
+        out.push_str(format!("pub const {}: {} = {}({});\n", variant.name, enmn.name, enmn.name, variant.value).as_str());
+    }
+    out.push_str(format!("pub const fn empty() -> {} {{\n", enmn.name).as_str());
+    out.push_str(format!("{}(0)\n", enmn.name).as_str());
+    out.push_str("}\n");
+    out.push_str(format!("pub const fn bits(&self) -> {} {{\n", repr).as_str());
+    out.push_str("self.0\n");
+    out.push_str("}\n");
+    out.push_str(format!("pub fn contains(&self, flags: {}) -> bool {{\n", enmn.name).as_str());
+    out.push_str("self.0 & flags.0 == flags.0\n");
+    out.push_str("}\n");
+    out.push_str(format!("pub fn set(&mut self, flags: {}) {{\n", enmn.name).as_str());
+    out.push_str("self.0 |= flags.0;\n");
+    out.push_str("}\n");
+    out.push_str(format!("pub fn clear(&mut self, flags: {}) {{\n", enmn.name).as_str());
+    out.push_str("self.0 &= !flags.0;\n");
+    out.push_str("}\n");
+    out.push_str(format!("pub fn to_be_bytes(&self) -> [u8; {}] {{\n", enmn.size()).as_str());
+    out.push_str("self.0.to_be_bytes()\n");
+    out.push_str("}\n");
+    out.push_str(format!("pub fn from_be_bytes(data: [u8; {}]) -> {} {{\n", enmn.size(), enmn.name).as_str());
+    out.push_str(format!("{}({}::from_be_bytes(data))\n", enmn.name, repr).as_str());
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out.push_str(format!("impl core::ops::BitOr for {} {{\n", enmn.name).as_str());
+    out.push_str(format!("type Output = {};\n", enmn.name).as_str());
+    out.push_str(format!("fn bitor(self, rhs: {}) -> {} {{\n", enmn.name, enmn.name).as_str());
+    out.push_str(format!("{}(self.0 | rhs.0)\n", enmn.name).as_str());
+    out.push_str("}\n");
+    out.push_str("}\n");
+
     out
-}
\ No newline at end of file
+}
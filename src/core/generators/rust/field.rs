@@ -1,92 +1,380 @@
-use crate::core::ast::{StructField, Package};
+use crate::core::ast::{BitRange, ComplexTypes, FieldType, StructField, Package, Value};
 
-use super::types::gen_default_value;
+use crate::core::generators::code_gen::{doc_lines, gen_comment, gen_limits, is_checked, is_variant_checked, join_path, type_invalid_paths};
+
+use super::types::{gen_default_value, gen_type, gen_type_path};
+use super::union::gen_variant_name;
+
+/// Wrap `value` in a Rust array for every dimension of the field, the last dimension is the innermost
+fn gen_array(field: &StructField, value: String) -> String {
+    field.array.iter().rev().fold(value, |out, n| format!("[{}; {}]", out, n))
+}
+
+/// Type of a field, bounded arrays are stored in the `BoundedArray` defined at the top of the file
+fn gen_declaration_type(field: &StructField) -> String {
+    match (field.bounded, field.optional) {
+        (true, _) => format!("super::BoundedArray<{}, {}>", gen_field_type(field), field.elements()),
+        (false, true) => format!("Option<{}>", gen_field_type(field)),
+        (false, false) => gen_array(field, gen_field_type(field))
+    }
+}
+
+/// Scaled fields are exposed as `f64`
+fn gen_field_type(field: &StructField) -> String {
+    match field.scaling {
+        Some(_) => String::from("f64"),
+        None => gen_type(&field.t)
+    }
+}
+
+/// Integer encoded for the value of a scaled field, `as` rounds NaN to 0 and saturates.
+/// `f64::round` needs `std`, the generated code rounds halfway away from zero with `core` only.
+fn gen_to_raw(field: &StructField, value: String) -> String {
+    match field.scaling {
+        Some(s) => format!(
+            "{{ let raw = ({} - {:?}) / {:?}; (if raw >= 0.0 {{ raw + 0.5 }} else {{ raw - 0.5 }}) as {} }}",
+            value, s.offset, s.scale, field.t.str()
+        ),
+        None => value
+    }
+}
+
+/// Value of a scaled field from the encoded integer
+fn gen_from_raw(field: &StructField, raw: String) -> String {
+    match field.scaling {
+        Some(s) => format!("{} as f64 * {:?} + {:?}", raw, s.scale, s.offset),
+        None => raw
+    }
+}
+
+/// `#[deprecated]` attribute of a field declared with `@deprecated`
+fn gen_deprecated(field: &StructField) -> String {
+    match field.deprecated.as_deref() {
+        Some("") => String::from("#[deprecated]\n"),
+        Some(note) => format!("#[deprecated(note = {:?})]\n", note),
+        None => String::new()
+    }
+}
 
 pub fn gen_declaration(field: &StructField) -> String {
     let mut out = String::new();
 
+    // Reserved bytes are only in the encoded struct
+    if field.reserved {
+        return out;
+    }
+
+    out.push_str(gen_comment(&doc_lines(&field.doc, &field.unit), "///").as_str());
+    out.push_str(gen_deprecated(field).as_str());
     out.push_str(format!("pub {}: ", field.name).as_str());
-    out.push_str(match field.array {
-        Some(n) => {
-            format!("[{}; {}],\n", field.t.str(), n)
-        },
-        None => {
-            format!("{},\n", field.t.str())
-        }
-    }.as_str());
+    out.push_str(format!("{},\n", gen_declaration_type(field)).as_str());
+
+    out
+}
+
+/// Move to the byte after the packed bitfields, once the last one is done
+fn gen_bits_end(bits: BitRange) -> String {
+    match bits.run_bytes {
+        Some(n) => format!("index += {};\n", n),
+        None => String::new()
+    }
+}
+
+/// Copy the bytes of `value`, one element of the field, structs with a variable size copy only the used bytes
+fn gen_encode_value(field: &StructField, value: String, package: &Package) -> String {
+    let mut out = String::new();
+
+    out.push_str(format!("for x in {} {{\n", match field.t.str() {
+        "bool" => format!("({} as u8).to_be_bytes()", value),
+        _ if field.t.is_variable(package) => format!("{}.to_be_bytes().into_iter().take({}.bytes_length() - 4)", value, value),
+        _ => format!("{}.to_be_bytes()", gen_to_raw(field, value))
+    }).as_str());
+    out.push_str("data[index] = x;\n");
+    out.push_str("index += 1;\n");
+    out.push_str("}\n");
 
     out
 }
 
-pub fn gen_encode(field: &StructField) -> String {
+pub fn gen_encode(field: &StructField, bits: Option<BitRange>, package: &Package) -> String {
     let mut out = String::new();
 
-    match field.array {
-        Some(_n) => {
-            out.push_str(format!("for i in self.{} {{\n", field.name).as_str());
-            out.push_str(format!("for x in {}.to_be_bytes() {{\n", match field.t.str() {
-                "bool" => "(i as u8)",
-                _ => "i"
-            }).as_str());
-            out.push_str("data[index] = x;\n");
-            out.push_str("index += 1;\n");
-            out.push_str("}\n");
+    if field.reserved {
+        // The buffer is already zero
+        out.push_str(format!("index += {};\n", field.elements()).as_str());
+    }
+    else if field.optional {
+        // The presence bit is written by the struct
+        out.push_str(format!("if let Some(x) = self.{} {{\n", field.name).as_str());
+        out.push_str(gen_encode_value(field, String::from("x"), package).as_str());
+        out.push_str("}\n");
+    }
+    else if let Some(bits) = bits {
+        out.push_str(format!("super::write_bits(&mut data[index..], {}, {}, self.{} as u64);\n", bits.offset, bits.width, field.name).as_str());
+        out.push_str(gen_bits_end(bits).as_str());
+    }
+    else if let Some(l) = field.length_type() {
+        // The number of elements, then only the used ones
+        out.push_str(format!("for x in (self.{}.len() as {}).to_be_bytes() {{\n", field.name, l.str()).as_str());
+        out.push_str("data[index] = x;\n");
+        out.push_str("index += 1;\n");
+        out.push_str("}\n");
+        out.push_str(format!("for &i in self.{}.iter() {{\n", field.name).as_str());
+        out.push_str(gen_encode_value(field, String::from("i"), package).as_str());
+        out.push_str("}\n");
+    }
+    else if field.is_array() {
+        out.push_str(format!("for i in self.{} {{\n", field.name).as_str());
+        for _ in 1..field.array.len() {
+            out.push_str("for i in i {\n");
+        }
+        out.push_str(gen_encode_value(field, String::from("i"), package).as_str());
+        for _ in 0..field.array.len() {
             out.push_str("}\n");
+        }
+    }
+    else {
+        out.push_str(gen_encode_value(field, format!("self.{}", field.name), package).as_str());
+    }
+
+    out
+}
+
+/// Bytes of the field added to the base size of the struct, `None` when the size of the field doesn't change
+pub fn gen_bytes_length(field: &StructField, package: &Package) -> Option<String> {
+    if !field.is_variable(package) {
+        return None;
+    }
+
+    if field.optional {
+        return match field.t.is_variable(package) {
+            true => Some(format!("self.{}.map_or(0, |x| x.bytes_length() - 4)", field.name)),
+            false => Some(format!("self.{}.map_or(0, |_| {})", field.name, field.t.size(package)))
+        };
+    }
+
+    if !field.t.is_variable(package) {
+        return match field.t.size(package) {
+            1 => Some(format!("self.{}.len()", field.name)),
+            n => Some(format!("self.{}.len() * {}", field.name, n))
+        };
+    }
+
+    match field.is_array() {
+        true => Some(format!("self.{}.iter(){}.map(|x| x.bytes_length() - 4).sum::<usize>()", field.name, ".flatten()".repeat(field.array.len() - 1))),
+        false => Some(format!("(self.{}.bytes_length() - 4)", field.name))
+    }
+}
+
+/// Return the path of the field if its value is out of the limits.
+/// `value` is the expression of the struct containing the field, the fields of nested structs are checked with the path `outer.inner`.
+pub fn gen_validate(field: &StructField, package: &Package, value: &str, path: &str) -> String {
+    let mut out = String::new();
+
+    if !is_checked(field, package, false) {
+        return out;
+    }
+
+    // Every element of an array is checked, the loop variables are unique inside nested structs
+    let depth = path.matches('.').count();
+    let mut expr = format!("{}.{}", value, field.name);
+    for d in 0..field.array.len() {
+        let x = format!("x{}_{}", depth, d);
+        out.push_str(format!("for {} in {}.iter() {{\n", x, expr).as_str());
+        expr = x;
+    }
+    // A missing optional field is valid
+    if field.optional {
+        let x = format!("o{}", depth);
+        out.push_str(format!("if let Some({}) = &{} {{\n", x, expr).as_str());
+        expr = x;
+    }
+    if field.is_array() || field.optional {
+        expr = format!("(*{})", expr);
+    }
+
+    match &field.t {
+        FieldType::COMPLEX(ComplexTypes::Struct(s)) => {
+            let (owner, s) = package.get_struct(s);
+            for f in s.fields.values() {
+                out.push_str(gen_validate(f, owner, expr.as_str(), format!("{}{}.", path, field.name).as_str()).as_str());
+            }
         },
-        None => {
-            out.push_str(format!("for x in {}.to_be_bytes() {{\n", match field.t.str() {
-                "bool" => String::from("(self.") + field.name.as_str() + " as u8)",
-                _ => String::from("self.") + field.name.as_str()
-            }).as_str());
-            out.push_str("data[index] = x;\n");
-            out.push_str("index += 1;\n");
+        FieldType::COMPLEX(c @ ComplexTypes::Union(u)) => {
+            // Only the struct of the current variant is checked, the fields are named `union.variant.field`
+            let x = format!("u{}", depth);
+            let (owner, u) = package.get_union(u);
+            for v in u.variants.values().filter(|v| is_variant_checked(&v.t, owner, false)) {
+                out.push_str(format!("if let {}::{}({}) = &{} {{\n", gen_type_path(c, package), gen_variant_name(&v.name), x, expr).as_str());
+                let (owner, s) = owner.get_struct(v.t.str());
+                for f in s.fields.values() {
+                    out.push_str(gen_validate(f, owner, x.as_str(), format!("{}{}.{}.", path, field.name, v.name).as_str()).as_str());
+                }
+                out.push_str("}\n");
+            }
+        },
+        _ => {
+            if let FieldType::COMPLEX(crate::core::ast::ComplexTypes::Enum(e)) = &field.t {
+                expr = format!("({} as {})", expr, package.get_enum(e).repr.str());
+            }
+
+            let (min, max) = gen_limits(field, package);
+            let mut cond = Vec::new();
+            if let Some(min) = min {
+                cond.push(format!("{} >= {}", expr, min));
+            }
+            if let Some(max) = max {
+                cond.push(format!("{} <= {}", expr, max));
+            }
+
+            out.push_str(format!("if !({}) {{\n", cond.join(" && ")).as_str());
+            out.push_str(format!("return Err(\"{}{}\");\n", path, field.name).as_str());
             out.push_str("}\n");
         }
     }
 
+    for _ in 0..field.array.len() + field.optional as usize {
+        out.push_str("}\n");
+    }
+
     out
 }
 
+/// Rust expression of a default value or constant of type `t`
+pub fn gen_value(value: &Value, t: &FieldType) -> String {
+    match (value, t) {
+        (Value::Int(v), _) => v.to_string(),
+        (Value::Float(v), _) => format!("{:?}", v),
+        (Value::Bool(v), _) => v.to_string(),
+        (Value::String(v), _) => format!("super::FixedString::try_from({:?}).unwrap()", v),
+        (Value::Variant(v), FieldType::COMPLEX(ComplexTypes::Enum(_))) => format!("{}::{}", gen_type(t), v),
+        (Value::Variant(_), _) => unreachable!("Default values are checked by the semantic analysis.")
+    }
+}
+
 pub fn gen_default(field: &StructField, package: &Package) -> String {
     let mut out = String::new();
 
+    if field.reserved {
+        return out;
+    }
+
+    let value = match (&field.default, field.scaling) {
+        (Some((v, _)), _) => gen_value(v, &field.t),
+        (None, Some(_)) => String::from("0.0"),
+        (None, None) => gen_default_value(&field.t, package)
+    };
+
     out.push_str(format!("{}: ", field.name).as_str());
-    out.push_str(match field.array {
-        Some(n) => format!("[{}; {}]", gen_default_value(&field.t, package), n),
-        None => format!("{}", gen_default_value(&field.t, package))
-    }.as_str());
+    match (field.bounded, field.optional) {
+        (true, _) => out.push_str("super::BoundedArray::new()"),
+        (false, true) => out.push_str("None"),
+        (false, false) => out.push_str(gen_array(field, value).as_str())
+    }
     out.push_str(",\n");
 
     out
 }
 
-pub fn gen_from_bytes(field: &StructField, package: &Package) -> String {
+/// Decode one element of the field of type `t` from `bytes`.
+/// A value that can't be stored, like an enum value that isn't a variant, is replaced with the default one and its path is set in `invalid`,
+/// the paths of nested structs are relative to them and become `field.path`.
+fn gen_decode_element(field: &StructField, t: &str, bytes: String, package: &Package) -> String {
+    let paths = type_invalid_paths(&field.t, package);
+
+    match &field.t {
+        _ if paths.is_empty() => format!("{}::from_be_bytes({})", t, bytes),
+        FieldType::COMPLEX(ComplexTypes::Enum(_)) => format!(
+            "{}::try_from_be_bytes({}).unwrap_or_else(|| {{ invalid.get_or_insert({:?}); {}::default() }})",
+            t, bytes, field.name, t
+        ),
+        _ => gen_decode_checked(t, bytes, &field.name, &paths)
+    }
+}
+
+/// Decode a struct or union of type `t` with `from_be_bytes_checked`, its `paths` reported in `invalid` become `name.path`
+pub fn gen_decode_checked(t: &str, bytes: String, name: &str, paths: &[String]) -> String {
+    let mut arms: Vec<String> = paths.iter().map(|p| format!("{:?} => {:?}", p, join_path(name, p))).collect();
+    arms.push(format!("_ => {:?}", name));
+    format!(
+        "{{ let mut e = None; let x = {}::from_be_bytes_checked({}, &mut e); if let Some(e) = e {{ invalid.get_or_insert(match e {{ {} }}); }} x }}",
+        t, bytes, arms.join(", ")
+    )
+}
+
+pub fn gen_from_bytes(field: &StructField, bits: Option<BitRange>, presence: Option<u32>, package: &Package) -> String {
     let mut out = String::new();
 
-    match field.array {
-        Some(n) => {
-            out.push_str(format!("for i in 0..{} {{\n", n).as_str());
-            out.push_str(format!("out.{}[i] = {}::from_be_bytes(data[index..index+{}].try_into().unwrap()){};\n", field.name, match field.t.str() {
-                "bool" => "u8",
-                _ => field.t.str()
-            }, field.size(package) / n, match field.t.str() {
-                "bool" => " != 0",
-                _ => ""
-            }).as_str());
-            out.push_str(format!("index += {};\n", field.size(package) / n).as_str());
+    if let Some(bits) = bits {
+        out.push_str(format!("out.{} = super::read_bits(&data[index..], {}, {}){};\n", field.name, bits.offset, bits.width, match field.t.str() {
+            "bool" => String::from(" != 0"),
+            t => format!(" as {}", t)
+        }).as_str());
+        out.push_str(gen_bits_end(bits).as_str());
+
+        return out;
+    }
+
+    if field.reserved {
+        out.push_str(format!("index += {};\n", field.elements()).as_str());
+        return out;
+    }
+
+    // The capacity of the string is inferred from the type of the field
+    let t = match field.t {
+        FieldType::STRING(_) => String::from("super::FixedString"),
+        _ => gen_type(&field.t)
+    };
+
+    // One element, structs with a variable size are read from a window of their largest size
+    let bytes = format!("data[index..index+{}].try_into().unwrap()", field.t.size(package));
+    let raw = match field.t.str() {
+        "bool" => format!("u8::from_be_bytes({}) != 0", bytes),
+        _ => gen_decode_element(field, t.as_str(), bytes, package)
+    };
+    let value = gen_from_raw(field, raw);
+    // Move to the next element once `element` is decoded
+    let advance = |element: String| match field.t.is_variable(package) {
+        true => format!("index += {}.bytes_length() - 4;\n", element),
+        false => format!("index += {};\n", field.t.size(package))
+    };
+
+    if let Some(bit) = presence {
+        // Only the fields with their bit set in the presence bitmap, at the start of the struct, are encoded
+        out.push_str(format!("if super::read_bits(&data, {}, 1) != 0 {{\n", bit).as_str());
+        out.push_str(format!("let x = {};\n", value).as_str());
+        out.push_str(advance(String::from("x")).as_str());
+        out.push_str(format!("out.{} = Some(x);\n", field.name).as_str());
+        out.push_str("}\n");
+    }
+    else if let Some(l) = field.length_type() {
+        // Only the capacity is decoded from a larger number of elements, the field is reported
+        out.push_str(format!("let n = {}::from_be_bytes(data[index..index+{}].try_into().unwrap()) as usize;\n", l.str(), l.size()).as_str());
+        out.push_str(format!("if n > {} {{\n", field.elements()).as_str());
+        out.push_str(format!("invalid.get_or_insert({:?});\n", field.name).as_str());
+        out.push_str("}\n");
+        out.push_str(format!("index += {};\n", l.size()).as_str());
+        out.push_str(format!("for _ in 0..n.min({}) {{\n", field.elements()).as_str());
+        out.push_str(format!("out.{}.push({}).unwrap();\n", field.name, value).as_str());
+        out.push_str(advance(format!("out.{}[out.{}.len() - 1]", field.name, field.name)).as_str());
+        out.push_str("}\n");
+    }
+    else if field.is_array() {
+        let mut indexes = String::new();
+        for (d, n) in field.array.iter().enumerate() {
+            out.push_str(format!("for i{} in 0..{} {{\n", d, n).as_str());
+            indexes.push_str(format!("[i{}]", d).as_str());
+        }
+        out.push_str(format!("out.{}{} = {};\n", field.name, indexes, value).as_str());
+        out.push_str(advance(format!("out.{}{}", field.name, indexes)).as_str());
+        for _ in 0..field.array.len() {
             out.push_str("}\n");
-        },
-        None => {
-            out.push_str(format!("out.{} = {}::from_be_bytes(data[index..index+{}].try_into().unwrap()){};\n", field.name, match field.t.str() {
-                "bool" => "u8",
-                _ => field.t.str()
-            }, field.size(package), match field.t.str() {
-                "bool" => " != 0",
-                _ => ""
-            }).as_str());
-            out.push_str(format!("index += {};\n", field.size(package)).as_str());
         }
     }
+    else {
+        out.push_str(format!("out.{} = {};\n", field.name, value).as_str());
+        out.push_str(advance(format!("out.{}", field.name)).as_str());
+    }
 
     out
 }
\ No newline at end of file
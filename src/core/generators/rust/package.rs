@@ -1,16 +1,28 @@
 use crate::core::ast::Package;
 
-use super::{enumeration, r#struct};
+use super::{constant, enumeration, r#struct, union};
 
 pub fn gen_code(package: &Package) -> String {
     let mut out = String::new();
 
     out.push_str(format!("pub mod {} {{\n", package.name.as_ref().unwrap()).as_str());
+    // The generated code uses the deprecated fields, only the uses outside of the module warn
+    if package.has_deprecated() {
+        out.push_str("#![allow(deprecated)]\n");
+    }
+
+    for c in package.constants.values() {
+        out.push_str(constant::gen_code(c).as_str());
+    }
 
     for e in package.enums.values() {
         out.push_str(enumeration::gen_code(e, package).as_str());
     }
 
+    for u in package.unions.values() {
+        out.push_str(union::gen_code(u, package).as_str());
+    }
+
     for s in package.structs.values() {
         out.push_str(r#struct::gen_code(s, package).as_str());
     }
@@ -1,27 +1,47 @@
 use crate::core::ast::{Package, Struct};
 
-use super::field::{gen_declaration, gen_default, gen_encode, gen_from_bytes};
+use crate::core::generators::code_gen::{doc_lines, gen_comment, invalid_paths};
+
+use super::field::{gen_bytes_length, gen_declaration, gen_default, gen_encode, gen_from_bytes, gen_validate};
 
 pub fn gen_code(strc: &Struct, package: &Package) -> String {
     let mut out = String::new();
 
+    out.push_str(gen_comment(&doc_lines(&strc.doc, &None), "///").as_str());
     out.push_str("#[derive(Copy, Clone)]\n");
 
     out.push_str(format!("pub struct {} {{\n", strc.name).as_str());
-    for f in &strc.fields_order {
-        out.push_str(gen_declaration(strc.fields.get(f).unwrap()).as_str());
+    for f in strc.fields.values() {
+        out.push_str(gen_declaration(f).as_str());
     }
     out.push_str("}\n");
 
     out.push_str(format!("impl {} {{\n", strc.name).as_str());
 
-    out.push_str(format!("pub const NAME_HASH: u32 = {};\n", strc.fnv_1a()).as_str());
-    out.push_str(format!("pub const BYTES_LENGTH: usize = {} + 4;\n", strc.size(package)).as_str());
+    // With a variable size the buffers take the largest struct, only the first `bytes_length()` bytes are used
+    let length = match strc.is_variable(package) {
+        true => "MAX_BYTES_LENGTH",
+        false => "BYTES_LENGTH"
+    };
+
+    out.push_str(format!("pub const NAME_HASH: u32 = {};\n", strc.id()).as_str());
+    out.push_str(format!("pub const {}: usize = {} + 4;\n", length, strc.size(package)).as_str());
+    if strc.is_variable(package) {
+        out.push_str("/// Encoded size in bytes, only this many bytes of `encode` are sent\n");
+        out.push_str("pub fn bytes_length(&self) -> usize {\n");
+        out.push_str(format!("{} + 4", strc.base_size(package)).as_str());
+        for f in strc.fields.values() {
+            if let Some(l) = gen_bytes_length(f, package) {
+                out.push_str(format!(" + {}", l).as_str());
+            }
+        }
+        out.push_str("\n}\n");
+    }
     {    
-        out.push_str(format!("pub fn encode(&self) -> [u8; {}::BYTES_LENGTH] {{\n", strc.name).as_str());
+        out.push_str(format!("pub fn encode(&self) -> [u8; {}::{}] {{\n", strc.name, length).as_str());
 
-        out.push_str(format!("let mut data: [u8; {}::BYTES_LENGTH] = [0; {}::BYTES_LENGTH];", strc.name, strc.name).as_str());
-        out.push_str(format!("let mut index = 0;").as_str());
+        out.push_str(format!("let mut data: [u8; {}::{}] = [0; {}::{}];", strc.name, length, strc.name, length).as_str());
+        out.push_str("let mut index = 0;");
 
         out.push_str(format!("for x in u32::to_be_bytes({}::NAME_HASH) {{\n", strc.name).as_str());
         out.push_str("data[index] = x;\n");
@@ -35,39 +55,113 @@ pub fn gen_code(strc: &Struct, package: &Package) -> String {
         out.push_str("}\n");
     }
     {
-        out.push_str(format!("pub fn to_be_bytes(&self) -> [u8; {}::BYTES_LENGTH - 4] {{\n", strc.name).as_str());
-        out.push_str(format!("let mut data: [u8; {}::BYTES_LENGTH - 4] = [0; {}::BYTES_LENGTH - 4];", strc.name, strc.name).as_str());
-        out.push_str(format!("let mut index = 0;").as_str());
-        for f in &strc.fields_order {
-            out.push_str(gen_encode(strc.fields.get(f).unwrap()).as_str());
+        out.push_str(format!("pub fn to_be_bytes(&self) -> [u8; {}::{} - 4] {{\n", strc.name, length).as_str());
+        out.push_str(format!("let mut data: [u8; {}::{} - 4] = [0; {}::{} - 4];", strc.name, length, strc.name, length).as_str());
+        out.push_str("let mut index = 0;");
+        // Presence bitmap of the optional fields
+        for (f, bit) in strc.fields.values().zip(strc.presence_bits()) {
+            if let Some(bit) = bit {
+                out.push_str(format!("super::write_bits(&mut data, {}, 1, self.{}.is_some() as u64);\n", bit, f.name).as_str());
+            }
+        }
+        if strc.presence_bytes() > 0 {
+            out.push_str(format!("index += {};\n", strc.presence_bytes()).as_str());
+        }
+        for (f, bits) in strc.fields.values().zip(strc.bit_ranges()) {
+            out.push_str(gen_encode(f, bits, package).as_str());
         }
         out.push_str("data\n");
         out.push_str("}\n");
     }
+    // Values that can't be stored, like enum values that aren't variants, are decoded as the default ones and reported by `decode_validated`
+    let checked = !invalid_paths(strc, package).is_empty();
     {
         out.push_str(format!("pub fn decode(data: &[u8]) -> {} {{\n", strc.name).as_str());
-        out.push_str(format!("{}::from_be_bytes(data[4..data.len()].try_into().unwrap())\n", strc.name).as_str());
+        let from_be_bytes = match checked {
+            true => {
+                out.push_str(format!("{}::decode_checked(data, &mut None)\n", strc.name).as_str());
+                out.push_str("}\n");
+                out.push_str(format!("fn decode_checked(data: &[u8], invalid: &mut Option<&'static str>) -> {} {{\n", strc.name).as_str());
+                "from_be_bytes_checked"
+            },
+            false => "from_be_bytes"
+        };
+        let invalid = if checked { ", invalid" } else { "" };
+        if strc.is_variable(package) {
+            // The bytes after the received ones are zero
+            out.push_str(format!("let mut buffer = [0; {}::MAX_BYTES_LENGTH - 4];\n", strc.name).as_str());
+            out.push_str("let n = (data.len() - 4).min(buffer.len());\n");
+            out.push_str("buffer[..n].copy_from_slice(&data[4..4 + n]);\n");
+            out.push_str(format!("{}::{}(buffer{})\n", strc.name, from_be_bytes, invalid).as_str());
+        }
+        else {
+            out.push_str(format!("{}::{}(data[4..data.len()].try_into().unwrap(){})\n", strc.name, from_be_bytes, invalid).as_str());
+        }
         out.push_str("}\n");
     }
     {
-        out.push_str(format!("pub fn from_be_bytes(data: [u8; {}::BYTES_LENGTH - 4]) -> {} {{\n", strc.name, strc.name).as_str());
+        if checked {
+            out.push_str(format!("pub fn from_be_bytes(data: [u8; {}::{} - 4]) -> {} {{\n", strc.name, length, strc.name).as_str());
+            out.push_str(format!("{}::from_be_bytes_checked(data, &mut None)\n", strc.name).as_str());
+            out.push_str("}\n");
+            out.push_str("/// Decode the struct, the first value that can't be stored is set to the default and its path is set in `invalid`\n");
+            out.push_str(format!("pub fn from_be_bytes_checked(data: [u8; {}::{} - 4], invalid: &mut Option<&'static str>) -> {} {{\n", strc.name, length, strc.name).as_str());
+        }
+        else {
+            out.push_str(format!("pub fn from_be_bytes(data: [u8; {}::{} - 4]) -> {} {{\n", strc.name, length, strc.name).as_str());
+        }
         out.push_str(format!("let mut out = {} {{\n", strc.name).as_str());
-        for f in &strc.fields_order {
-            out.push_str(gen_default(strc.fields.get(f).unwrap(), package).as_str());
+        for f in strc.fields.values() {
+            out.push_str(gen_default(f, package).as_str());
         }
         out.push_str("};\n");
 
-        out.push_str("let mut index = 0;\n");
+        out.push_str(format!("let mut index = {};\n", strc.presence_bytes()).as_str());
 
-        for f in &strc.fields_order {
-            out.push_str(gen_from_bytes(strc.fields.get(f).unwrap(), package).as_str());
+        for ((f, bits), presence) in strc.fields.values().zip(strc.bit_ranges()).zip(strc.presence_bits()) {
+            out.push_str(gen_from_bytes(f, bits, presence, package).as_str());
         }
 
         out.push_str("out\n");
         out.push_str("}\n");
     }
+    {
+        out.push_str("/// Check the limits set with `@range`, `@min` and `@max`, return the first field out of them\n");
+        out.push_str("pub fn validate(&self) -> Result<(), &'static str> {\n");
+        for f in strc.fields.values() {
+            out.push_str(gen_validate(f, package, "self", "").as_str());
+        }
+        out.push_str("Ok(())\n");
+        out.push_str("}\n");
+
+        out.push_str("/// Decode the struct and check its limits, the received values that can't be stored, like an enum value that isn't a variant, are reported like a value out of them\n");
+        out.push_str(format!("pub fn decode_validated(data: &[u8]) -> Result<{}, &'static str> {{\n", strc.name).as_str());
+        if checked {
+            out.push_str("let mut invalid = None;\n");
+            out.push_str(format!("let out = {}::decode_checked(data, &mut invalid);\n", strc.name).as_str());
+            out.push_str("if let Some(field) = invalid {\n");
+            out.push_str("return Err(field);\n");
+            out.push_str("}\n");
+        }
+        else {
+            out.push_str(format!("let out = {}::decode(data);\n", strc.name).as_str());
+        }
+        out.push_str("out.validate()?;\n");
+        out.push_str("Ok(out)\n");
+        out.push_str("}\n");
+    }
 
     out.push_str("}\n");
 
+    out.push_str(format!("impl Default for {} {{\n", strc.name).as_str());
+    out.push_str(format!("fn default() -> {} {{\n", strc.name).as_str());
+    out.push_str(format!("{} {{\n", strc.name).as_str());
+    for f in strc.fields.values() {
+        out.push_str(gen_default(f, package).as_str());
+    }
+    out.push_str("}\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
     out
 }
\ No newline at end of file
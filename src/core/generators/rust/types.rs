@@ -1,32 +1,47 @@
 use crate::core::ast::{ComplexTypes, FieldType, Package, PrimitiveTypes};
 
-use super::field::gen_default;
+/// Rust type of a field, strings are stored in the `FixedString` defined at the top of the file
+/// and the types of imported packages in the module of their package
+pub fn gen_type(t: &FieldType) -> String {
+    match t {
+        FieldType::STRING(n) => format!("super::FixedString<{}>", n),
+        FieldType::COMPLEX(c) => match c.package() {
+            Some(p) => format!("super::{}::{}", p, c.name()),
+            None => String::from(c.name())
+        },
+        _ => String::from(t.str())
+    }
+}
+
+/// Path of a struct, enum or union from any module of the file, used when the code is not in the module of `package`
+pub fn gen_type_path(c: &ComplexTypes, package: &Package) -> String {
+    format!("super::{}::{}", c.package().unwrap_or(package.name.as_ref().unwrap()), c.name())
+}
 
 pub fn gen_default_value(t: &FieldType, package: &Package) -> String {
     match t {
         FieldType::PRIMITIVE(p) => {
             match p {
-                PrimitiveTypes::U8 | PrimitiveTypes::U16 | PrimitiveTypes::U32 | PrimitiveTypes::I8 | PrimitiveTypes::I16 | PrimitiveTypes::I32 => String::from("0"),
+                PrimitiveTypes::U8 | PrimitiveTypes::U16 | PrimitiveTypes::U32 | PrimitiveTypes::U64 | PrimitiveTypes::I8 | PrimitiveTypes::I16 | PrimitiveTypes::I32 | PrimitiveTypes::I64 => String::from("0"),
                 PrimitiveTypes::Bool => String::from("false"),
-                PrimitiveTypes::F32 => String::from("0.0")
+                PrimitiveTypes::F32 | PrimitiveTypes::F64 => String::from("0.0")
             }
         },
         FieldType::COMPLEX(c) => {
             match c {
-                ComplexTypes::Struct(s) => {
-                    let mut out = String::new();
-
-                    out.push_str(format!("{} {{\n", s).as_str());
-                    for f in package.structs.get(s).unwrap().fields.values() {
-                        out.push_str(gen_default(f, package).as_str());
+                ComplexTypes::Struct(_) | ComplexTypes::Union(_) => format!("{}::default()", gen_type(t)),
+                ComplexTypes::Enum(e) => {
+                    let enmn = package.get_enum(e);
+                    if enmn.flags {
+                        format!("{}::empty()", gen_type(t))
+                    }
+                    else {
+                        format!("{}::{}", gen_type(t), enmn.variants.keys().next().unwrap())
                     }
-                    out.push_str("}\n");
-
-                    out
                 },
-                ComplexTypes::Enum(e) => format!("{}::{}", e, package.enums.get(e).unwrap().variants.keys().next().unwrap()),
-                ComplexTypes::Unknown(_u) => panic!("Can't generate default value for unknow type.")
+                ComplexTypes::Unknown(_u) => unreachable!("Types are resolved by the semantic analysis.")
             }
-        }
+        },
+        FieldType::STRING(_) => String::from("super::FixedString::new()")
     }
 }
\ No newline at end of file
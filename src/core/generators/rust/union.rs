@@ -0,0 +1,88 @@
+use crate::core::ast::{Package, Union, UnionVariant};
+
+use crate::core::generators::code_gen::{doc_lines, gen_comment, invalid_paths};
+
+use super::field::gen_decode_checked;
+use super::types::gen_type;
+
+/// Name of the Rust variant, in UpperCamelCase
+pub fn gen_variant_name(name: &str) -> String {
+    UnionVariant::camel_case(name)
+}
+
+pub fn gen_code(union: &Union, package: &Package) -> String {
+    let mut out = String::new();
+    let size = union.size(package);
+    // The struct of the variant starts after its value
+    let start = union.repr.size();
+
+    out.push_str(gen_comment(&doc_lines(&union.doc, &None), "///").as_str());
+    out.push_str("#[derive(Copy, Clone)]\n");
+    out.push_str(format!("pub enum {} {{\n", union.name).as_str());
+    for v in union.variants.values() {
+        out.push_str(gen_comment(&doc_lines(&v.doc, &None), "///").as_str());
+        out.push_str(format!("{}({}),\n", gen_variant_name(&v.name), gen_type(&v.t)).as_str());
+    }
+    out.push_str("}\n");
+
+    out.push_str(format!("impl {} {{\n", union.name).as_str());
+    out.push_str(format!("pub const BYTES_LENGTH: usize = {};\n", size).as_str());
+
+    out.push_str("/// Value of the variant on the wire\n");
+    out.push_str(format!("pub fn tag(&self) -> {} {{\n", union.repr.str()).as_str());
+    out.push_str("match self {\n");
+    for v in union.variants.values() {
+        out.push_str(format!("{}::{}(_) => {},\n", union.name, gen_variant_name(&v.name), v.value).as_str());
+    }
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    // The bytes after a struct smaller than the largest one stay zero
+    out.push_str(format!("pub fn to_be_bytes(&self) -> [u8; {}] {{\n", size).as_str());
+    out.push_str(format!("let mut data = [0; {}];\n", size).as_str());
+    out.push_str(format!("data[0..{}].copy_from_slice(&self.tag().to_be_bytes());\n", start).as_str());
+    out.push_str("match self {\n");
+    for v in union.variants.values() {
+        out.push_str(format!("{}::{}(x) => data[{}..{}].copy_from_slice(&x.to_be_bytes()),\n", union.name, gen_variant_name(&v.name), start, start + v.t.size(package)).as_str());
+    }
+    out.push_str("}\n");
+    out.push_str("data\n");
+    out.push_str("}\n");
+
+    out.push_str(format!("pub fn from_be_bytes(data: [u8; {}]) -> {} {{\n", size, union.name).as_str());
+    out.push_str(format!("{}::from_be_bytes_checked(data, &mut None)\n", union.name).as_str());
+    out.push_str("}\n");
+
+    // An unknown tag is reported with the empty path, the field itself
+    out.push_str("/// Decode the union, an unknown tag is decoded as the default variant, it or the first value of the variant that can't be stored is set in `invalid`\n");
+    out.push_str(format!("pub fn from_be_bytes_checked(data: [u8; {}], invalid: &mut Option<&'static str>) -> {} {{\n", size, union.name).as_str());
+    out.push_str(format!("match {}::from_be_bytes(data[0..{}].try_into().unwrap()) {{\n", union.repr.str(), start).as_str());
+    for v in union.variants.values() {
+        let t = gen_type(&v.t);
+        let bytes = format!("data[{}..{}].try_into().unwrap()", start, start + v.t.size(package));
+        let (owner, s) = package.get_struct(v.t.str());
+        let paths = invalid_paths(s, owner);
+        let value = match paths.is_empty() {
+            true => format!("{}::from_be_bytes({})", t, bytes),
+            false => gen_decode_checked(&t, bytes, &v.name, &paths)
+        };
+        out.push_str(format!("{} => {}::{}({}),\n", v.value, union.name, gen_variant_name(&v.name), value).as_str());
+    }
+    out.push_str("_ => {\n");
+    out.push_str("invalid.get_or_insert(\"\");\n");
+    out.push_str(format!("{}::default()\n", union.name).as_str());
+    out.push_str("}\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    // The default is the first variant
+    let (_, first) = union.variants.first().unwrap();
+    out.push_str(format!("impl Default for {} {{\n", union.name).as_str());
+    out.push_str(format!("fn default() -> {} {{\n", union.name).as_str());
+    out.push_str(format!("{}::{}({}::default())\n", union.name, gen_variant_name(&first.name), gen_type(&first.t)).as_str());
+    out.push_str("}\n");
+    out.push_str("}\n");
+
+    out
+}
@@ -1,26 +1,170 @@
-use std::{collections::HashMap, fs, io::Write, path::Path};
+use std::{fs, io::{self, Write}, path::Path};
 
-use super::ast::{Struct, AST};
+use indexmap::IndexMap;
+
+use super::ast::{Package, Struct, Value, AST};
 
 use serde::{Deserialize, Serialize};
 use serde_json::ser::to_string_pretty;
 
 
+/// Indexes of the i-th element of an array with the given dimensions, `[1][2]` is the element 6 of a 3x4 array
+fn array_indexes(dims: &[u32], mut i: u32) -> String {
+    let mut out = String::new();
+
+    for d in (0..dims.len()).rev() {
+        out.insert_str(0, &format!("[{}]", i % dims[d]));
+        i /= dims[d];
+    }
+
+    out
+}
+
+/// Conversion of a scaled field, `value = raw * scale + offset`.
+/// `min` and `max` are the values encoded by the smallest and largest integer, the others are saturated.
+#[derive(Deserialize, Serialize)]
+pub struct KronosScaling {
+    pub scale: f64,
+    pub offset: f64,
+    pub min: f64,
+    pub max: f64
+}
+
+/// Enum or flags used by a field, encoded as `repr`
+#[derive(Deserialize, Serialize)]
+pub struct KronosEnum {
+    pub repr: String,
+    /// Value of every variant, a power of two for the flags
+    pub variants: IndexMap<String, u32>
+}
+
+/// Variant of a union, encoded with `value` and followed by the fields of `struct`
+#[derive(Deserialize, Serialize)]
+pub struct KronosUnionVariant {
+    pub value: u32,
+    #[serde(rename = "struct")]
+    pub strct: String
+}
+
+/// Union used by a field, the value of the variant is encoded as `repr` and padded to `size` bytes
+#[derive(Deserialize, Serialize)]
+pub struct KronosUnion {
+    pub repr: String,
+    pub size: u32,
+    pub variants: IndexMap<String, KronosUnionVariant>
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct KronosCodeGen {
     pub package: String,
-    pub data: HashMap<String, HashMap<String, String>>,
-    pub size: HashMap<String, u32>
+    pub data: IndexMap<String, IndexMap<String, String>>,
+    /// Size of the structs, the largest one for the structs with bounded arrays
+    pub size: IndexMap<String, u32>,
+    /// Type and value of every variant of the enums
+    pub enums: IndexMap<String, KronosEnum>,
+    /// Type and value of every flag
+    pub flags: IndexMap<String, KronosEnum>,
+    /// Variants of the unions
+    pub unions: IndexMap<String, KronosUnion>,
+    /// Scale and offset of the fields declared with `@scale` or `@offset`
+    pub scaling: IndexMap<String, IndexMap<String, KronosScaling>>,
+    /// Doc comment of the structs
+    pub docs: IndexMap<String, String>,
+    /// Doc comment of the fields
+    pub field_docs: IndexMap<String, IndexMap<String, String>>,
+    /// Unit of the fields declared with `@unit`
+    pub units: IndexMap<String, IndexMap<String, String>>,
+    /// Bit of the optional fields in the presence bitmap, encoded before the fields of their struct
+    pub optional: IndexMap<String, IndexMap<String, u32>>,
+    /// Note of the fields declared with `@deprecated`, empty without one
+    pub deprecated: IndexMap<String, IndexMap<String, String>>,
+    /// Value of the constants, a number or a boolean
+    pub constants: IndexMap<String, serde_json::Value>
+}
+
+impl Default for KronosCodeGen {
+    fn default() -> KronosCodeGen {
+        KronosCodeGen::new()
+    }
 }
 
 impl KronosCodeGen {
-    fn expand_property(&mut self, s: &Struct, name: String, ast: &AST, class: String) {
-        for f in s.fields.values() {
-            for i in 0..f.array.unwrap_or(1) {
+    pub fn new() -> KronosCodeGen {
+        KronosCodeGen {
+            package: String::new(),
+            data: IndexMap::new(),
+            size: IndexMap::new(),
+            enums: IndexMap::new(),
+            flags: IndexMap::new(),
+            unions: IndexMap::new(),
+            scaling: IndexMap::new(),
+            docs: IndexMap::new(),
+            field_docs: IndexMap::new(),
+            units: IndexMap::new(),
+            optional: IndexMap::new(),
+            deprecated: IndexMap::new(),
+            constants: IndexMap::new()
+        }
+    }
+
+    /// Name of a type used by a field of `package`, the types of imported packages are qualified by their package
+    fn type_name(&self, package: &Package, name: &str) -> String {
+        match package.name.as_ref() {
+            Some(p) if *p != self.package && !name.contains('.') => format!("{}.{}", p, name),
+            _ => name.to_string()
+        }
+    }
+
+    /// Add the fields of `s`, declared by `package`, the fields of nested structs are named `outer.inner`
+    fn expand_property(&mut self, s: &Struct, name: String, package: &Package, class: String) {
+        for (f, bit) in s.fields.values().zip(s.presence_bits()) {
+            if let Some(bit) = bit {
+                self.optional.entry(class.clone()).or_default().insert(format!("{}{}", name, f.name), bit);
+            }
+            if let Some(note) = &f.deprecated {
+                self.deprecated.entry(class.clone()).or_default().insert(format!("{}{}", name, f.name), note.clone());
+            }
+
+            // Padding bytes, a single entry like `reserved[4]`
+            if f.reserved {
+                self.data.get_mut(&class).unwrap().insert(format!("{}{}", name, f.name), format!("reserved[{}]", f.elements()));
+                continue;
+            }
+
+            // The number of elements is only known when decoding, a bounded array is a single entry like `u16[<=64]`
+            if f.bounded {
+                let new_name = format!("{}{}", name, f.name);
+                if let Some(doc) = &f.doc {
+                    self.field_docs.entry(class.clone()).or_default().insert(new_name.clone(), doc.clone());
+                }
+                if let Some(unit) = &f.unit {
+                    self.units.entry(class.clone()).or_default().insert(new_name.clone(), unit.clone());
+                }
+                let t = match &f.t {
+                    super::ast::FieldType::COMPLEX(c) => self.type_name(package, c.str()),
+                    super::ast::FieldType::PRIMITIVE(p) => {
+                        if let Some(s) = f.scaling {
+                            let (min, max) = s.limits(p);
+                            self.scaling.entry(class.clone()).or_default().insert(new_name.clone(), KronosScaling { scale: s.scale, offset: s.offset, min, max });
+                        }
+                        p.str().to_string()
+                    },
+                    super::ast::FieldType::STRING(n) => format!("string[{}]", n)
+                };
+                self.data.get_mut(&class).unwrap().insert(new_name, format!("{}[<={}]", t, f.elements()));
+                continue;
+            }
+
+            for i in 0..f.elements() {
                 let mut new_name = name.clone();
                 new_name.push_str(&f.name);
-                if f.array.is_some() {
-                    new_name.push_str(&format!("[{}]", i));
+                new_name.push_str(&array_indexes(&f.array, i));
+
+                if let Some(doc) = &f.doc {
+                    self.field_docs.entry(class.clone()).or_default().insert(new_name.clone(), doc.clone());
+                }
+                if let Some(unit) = &f.unit {
+                    self.units.entry(class.clone()).or_default().insert(new_name.clone(), unit.clone());
                 }
 
                 match &f.t {
@@ -28,16 +172,30 @@ impl KronosCodeGen {
                         match complex_types {
                             super::ast::ComplexTypes::Struct(strct) => {
                                 new_name.push('.');
-                                self.expand_property(ast.package.structs.get(strct).unwrap(), new_name, ast, class.clone());
+                                let (owner, strct) = package.get_struct(strct);
+                                self.expand_property(strct, new_name, owner, class.clone());
                             },
-                            super::ast::ComplexTypes::Enum(_) => {
-                                self.data.get_mut(&class).unwrap().insert(new_name, complex_types.str().to_string());
+                            super::ast::ComplexTypes::Enum(e) | super::ast::ComplexTypes::Union(e) => {
+                                let t = self.type_name(package, e);
+                                self.data.get_mut(&class).unwrap().insert(new_name, t);
                             },
                             super::ast::ComplexTypes::Unknown(_) => {},
                         }
                     },
                     super::ast::FieldType::PRIMITIVE(primitive_types) => {
-                        self.data.get_mut(&class).unwrap().insert(new_name, primitive_types.str().to_string());
+                        if let Some(s) = f.scaling {
+                            let (min, max) = s.limits(primitive_types);
+                            self.scaling.entry(class.clone()).or_default().insert(new_name.clone(), KronosScaling { scale: s.scale, offset: s.offset, min, max });
+                        }
+
+                        let t = match f.bits {
+                            Some(bits) => format!("{}:{}", primitive_types.str(), bits),
+                            None => primitive_types.str().to_string()
+                        };
+                        self.data.get_mut(&class).unwrap().insert(new_name, t);
+                    },
+                    super::ast::FieldType::STRING(n) => {
+                        self.data.get_mut(&class).unwrap().insert(new_name, format!("string[{}]", n));
                     },
                 };
             }
@@ -45,20 +203,57 @@ impl KronosCodeGen {
     }
     
     // Create file kronos-code-gen.json
-    pub fn generate(&mut self, path: String, ast: &AST) {
+    pub fn generate(&mut self, path: String, ast: &AST) -> io::Result<()> {
         self.package = ast.package.name.clone().unwrap();
 
         for s in ast.package.structs.values() {
-            self.data.insert(s.name.clone(), HashMap::new());
-            self.expand_property(s, String::new(), ast, s.name.clone());
+            self.data.insert(s.name.clone(), IndexMap::new());
+            self.expand_property(s, String::new(), &ast.package, s.name.clone());
 
             self.size.insert(s.name.clone(), s.size(&ast.package));
+
+            if let Some(doc) = &s.doc {
+                self.docs.insert(s.name.clone(), doc.clone());
+            }
+        }
+
+        // The enums of the imported packages can be used by the fields of nested structs
+        for p in ast.package.all_packages() {
+            for e in p.enums.values() {
+                let variants = e.variants.values().map(|v| (v.name.clone(), v.value)).collect();
+                let kronos = KronosEnum { repr: e.repr.str().to_string(), variants };
+                if e.flags {
+                    self.flags.insert(self.type_name(p, &e.name), kronos);
+                }
+                else {
+                    self.enums.insert(self.type_name(p, &e.name), kronos);
+                }
+            }
         }
     
+        for p in ast.package.all_packages() {
+            for u in p.unions.values() {
+                let variants = u.variants.values()
+                    .map(|v| (v.name.clone(), KronosUnionVariant { value: v.value, strct: self.type_name(p, v.t.str()) }))
+                    .collect();
+                self.unions.insert(self.type_name(p, &u.name), KronosUnion { repr: u.repr.str().to_string(), size: u.size(p), variants });
+            }
+        }
+
+        for c in ast.package.constants.values() {
+            let value = match &c.value {
+                Value::Int(v) => serde_json::json!(v),
+                Value::Float(v) => serde_json::json!(v),
+                Value::Bool(v) => serde_json::json!(v),
+                Value::String(_) | Value::Variant(_) => unreachable!("Constants are numbers or booleans.")
+            };
+            self.constants.insert(c.name.clone(), value);
+        }
+
         let mut out = fs::File::create(
             Path::new(&path).join("kronos-code-gen.json")
-        ).unwrap();
+        )?;
     
-        out.write_all(to_string_pretty(&self).unwrap().as_bytes()).unwrap();
+        out.write_all(to_string_pretty(&self)?.as_bytes())
     }
 }
\ No newline at end of file
@@ -0,0 +1,119 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+use super::{ast::Package, error::FileDiagnostics, parser::Parser};
+
+/// Why a file imported with `import "path";` can't be used
+pub enum ImportError {
+    /// The file is not in the directory of the importing file or in the include directories
+    NotFound,
+    /// The file was found but can't be read, with the error of the file system
+    Unreadable(String),
+    /// The file imports itself, directly or through other files, with the chain of imports
+    Cycle(Vec<String>),
+    /// Another file declares the same package
+    Conflict(String, String),
+    /// The file has errors, they are reported with its own diagnostics
+    Invalid
+}
+
+/// Parse the files imported by a schema, every file is parsed once even when it's imported by more files
+pub struct Loader {
+    include_dirs: Vec<PathBuf>,
+    max_errors: usize,
+    /// Files being parsed, the last one imports the next file
+    stack: Vec<PathBuf>,
+    /// Package of every file already parsed, `None` when it has errors
+    done: HashMap<PathBuf, Option<Package>>,
+    /// File declaring every package, with the path shown in the errors
+    packages: HashMap<String, (PathBuf, String)>,
+    /// Imported files in the order they were parsed
+    pub files: Vec<PathBuf>,
+    /// Errors found in the imported files
+    pub diagnostics: Vec<FileDiagnostics>
+}
+
+impl Loader {
+    /// `root` is the file of the schema, its imports are searched from its directory
+    pub fn new(root: &Path, include_dirs: &[PathBuf], max_errors: usize) -> Loader {
+        Loader {
+            include_dirs: include_dirs.to_vec(),
+            max_errors,
+            stack: vec![root.to_path_buf()],
+            done: HashMap::new(),
+            packages: HashMap::new(),
+            files: Vec::new(),
+            diagnostics: Vec::new()
+        }
+    }
+
+    /// The same file can be reached from different paths, like `a/../b.iris` and `b.iris`
+    fn canonical(path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// Search the file relative to the directory of the importing file, then to every include directory
+    fn find(&self, path: &str) -> Option<PathBuf> {
+        let importer = self.stack.last().unwrap();
+        let dir = importer.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        std::iter::once(dir)
+            .chain(self.include_dirs.iter().cloned())
+            .map(|d| d.join(path))
+            .find(|p| p.is_file())
+    }
+
+    /// Return the package of the file imported with `import "path";` by the file being parsed
+    pub fn import(&mut self, path: &str) -> Result<Package, ImportError> {
+        let file = self.find(path).ok_or(ImportError::NotFound)?;
+        let id = Loader::canonical(&file);
+
+        if let Some(start) = self.stack.iter().position(|f| Loader::canonical(f) == id) {
+            let mut cycle: Vec<String> = self.stack[start..].iter().map(|f| f.display().to_string()).collect();
+            cycle.push(file.display().to_string());
+            return Err(ImportError::Cycle(cycle));
+        }
+
+        let package = match self.done.get(&id) {
+            Some(done) => done.clone(),
+            None => self.parse(&file, &id).map_err(|e| ImportError::Unreadable(e.to_string()))?
+        };
+        let package = package.ok_or(ImportError::Invalid)?;
+
+        let name = package.name.clone().unwrap();
+        match self.packages.get(&name) {
+            Some((other, declared_by)) if *other != id => Err(ImportError::Conflict(name, declared_by.clone())),
+            _ => {
+                self.packages.insert(name, (id, file.display().to_string()));
+                Ok(package)
+            }
+        }
+    }
+
+    /// Parse an imported file with the files it imports, its errors are saved in `diagnostics`
+    fn parse(&mut self, file: &Path, id: &Path) -> std::io::Result<Option<Package>> {
+        let src = fs::read_to_string(file)?;
+
+        self.stack.push(file.to_path_buf());
+        let max_errors = self.max_errors;
+        let (res, package) = {
+            let mut parser = Parser::new(src.clone());
+            parser.max_errors = max_errors;
+            parser.loader = Some(&mut *self);
+            let res = parser.generate_ast();
+            (res, parser.ast.package)
+        };
+        self.stack.pop();
+        self.files.push(file.to_path_buf());
+
+        let package = match res {
+            Ok(()) => Some(package),
+            Err(diagnostics) => {
+                self.diagnostics.push(FileDiagnostics { path: file.display().to_string(), src, diagnostics });
+                None
+            }
+        };
+        self.done.insert(id.to_path_buf(), package.clone());
+
+        Ok(package)
+    }
+}
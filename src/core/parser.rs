@@ -1,48 +1,183 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
-use super::{ast::{self, ComplexTypes, FieldType, Package, PrimitiveTypes, StructField}, error::{error, ErrorType}, token_types::TokenTypes, tokenizer::{Token, Tokenizer}};
+use super::{resolver, ast::{self, ComplexTypes, FieldType, Package, PrimitiveTypes, StructField}, error::{error, error_at, Diagnostic, Diagnostics, ErrorType, Span}, loader::{ImportError, Loader}, token_types::TokenTypes, tokenizer::{Token, Tokenizer}};
 
-pub struct Parser {
+/// Number of errors after which the parser gives up, 0 means no limit
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+/// Names of all the attributes
+const ATTRIBUTES: [&str; 8] = ["id", "scale", "offset", "unit", "range", "min", "max", "deprecated"];
+
+/// Keywords of the generated languages, Rust, C++ and Python, they include the values `true` and `false`: they can't be the names of constants
+const TARGET_KEYWORDS: &[&str] = &[
+    "False", "None", "Self", "True", "abstract", "alignas", "alignof", "and", "and_eq", "as", "asm", "assert", "async",
+    "auto", "await", "become", "bitand", "bitor", "bool", "box", "break", "case", "catch", "char", "char16_t",
+    "char32_t", "char8_t", "class", "co_await", "co_return", "co_yield", "compl", "concept", "const", "const_cast",
+    "consteval", "constexpr", "constinit", "continue", "crate", "decltype", "def", "default", "del", "delete", "do",
+    "double", "dyn", "dynamic_cast", "elif", "else", "enum", "except", "explicit", "export", "extern", "false",
+    "final", "finally", "float", "fn", "for", "friend", "from", "gen", "global", "goto", "if", "impl", "import", "in",
+    "inline", "int", "is", "lambda", "let", "long", "loop", "macro", "match", "mod", "move", "mut", "mutable",
+    "namespace", "new", "noexcept", "nonlocal", "not", "not_eq", "nullptr", "operator", "or", "or_eq", "override",
+    "pass", "priv", "private", "protected", "pub", "public", "raise", "ref", "register", "reinterpret_cast",
+    "requires", "return", "self", "short", "signed", "sizeof", "static", "static_assert", "static_cast", "struct",
+    "super", "switch", "template", "this", "thread_local", "throw", "trait", "true", "try", "type", "typedef",
+    "typeid", "typename", "typeof", "union", "unsafe", "unsigned", "unsized", "use", "using", "virtual", "void",
+    "volatile", "wchar_t", "where", "while", "with", "xor", "xor_eq", "yield"
+];
+
+/// Attribute written as `@name` or `@name(arg, ...)` before a declaration, or after the name of a field
+struct Attribute {
+    name: String,
+    span: Span,
+    args: Vec<Token>
+}
+
+/// Parse a decimal or hexadecimal (`0x` prefix) unsigned integer
+fn parse_uint(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok()
+    }
+}
+
+/// Parse a number with an optional `-` sign, an integer or a decimal like `0.25`
+fn parse_number(s: &str) -> Option<f64> {
+    let (sign, digits) = match s.strip_prefix('-') {
+        Some(digits) => (-1.0, digits),
+        None => (1.0, s)
+    };
+
+    let value = match parse_uint(digits) {
+        Some(v) => v as f64,
+        None => digits.parse::<f64>().ok()?
+    };
+
+    Some(sign * value)
+}
+
+/// Position of a value, the span of a string includes the quotes
+fn value_span(token: &Token) -> Span {
+    match token.t {
+        TokenTypes::String => Span { row: token.row, col: token.col, len: token.span().len + 2 },
+        _ => token.span()
+    }
+}
+
+/// Check that the value written after `=` fits the type, return the help of the error otherwise.
+/// Variants of enums are checked by the semantic analysis, when the variants of the type are known.
+fn parse_value(token: &Token, field_type: &FieldType, bits: Option<u32>, scaling: Option<ast::Scaling>) -> Result<ast::Value, String> {
+    let text = token.value.as_ref().unwrap();
+    let number = matches!(token.t, TokenTypes::UInt | TokenTypes::SemanticVersion);
+
+    match field_type {
+        FieldType::PRIMITIVE(PrimitiveTypes::Bool) => match (&token.t, text.as_str()) {
+            (TokenTypes::Identifier, "true") => Ok(ast::Value::Bool(true)),
+            (TokenTypes::Identifier, "false") => Ok(ast::Value::Bool(false)),
+            _ => Err(String::from("a `bool` is `true` or `false`."))
+        },
+        FieldType::PRIMITIVE(p) => match (p.limits(), scaling) {
+            (Some((low, high)), None) => {
+                let (low, high, t) = match bits {
+                    Some(b) => (0, (1i128 << b) - 1, format!("`{}:{}` bitfield", p.str(), b)),
+                    None => (low, high, format!("`{}`", p.str()))
+                };
+                let (sign, digits) = match text.strip_prefix('-') {
+                    Some(digits) => (-1, digits),
+                    None => (1, text.as_str())
+                };
+
+                match parse_uint(digits).filter(|_| token.t == TokenTypes::UInt).map(|v| sign * v as i128) {
+                    Some(v) if (low..=high).contains(&v) => Ok(ast::Value::Int(v)),
+                    _ => Err(format!("a {} must be an integer from {} to {}.", t, low, high))
+                }
+            },
+            (Some(_), Some(s)) => {
+                let (low, high) = s.limits(p);
+                match parse_number(text).filter(|_| number) {
+                    Some(v) if v >= low && v <= high => Ok(ast::Value::Float(v)),
+                    _ => Err(format!("this scaled field must be a number from {} to {}.", low, high))
+                }
+            },
+            (None, _) => match parse_number(text).filter(|_| number) {
+                Some(v) => Ok(ast::Value::Float(v)),
+                None => Err(format!("a `{}` must be a number.", p.str()))
+            }
+        },
+        FieldType::STRING(n) => match token.t {
+            TokenTypes::String if text.len() <= *n as usize => Ok(ast::Value::String(text.clone())),
+            TokenTypes::String => Err(format!("the string is {} bytes long but the capacity is {}.", text.len(), n)),
+            _ => Err(String::from("a string must be written between double quotes."))
+        },
+        FieldType::COMPLEX(_) => match token.t {
+            TokenTypes::Identifier => Ok(ast::Value::Variant(text.clone())),
+            _ => Err(String::from("the default of an enum is one of its variants."))
+        }
+    }
+}
+
+pub struct Parser<'a> {
     tokenizer: Tokenizer,
     pub ast: ast::AST,
+    pub max_errors: usize,
+    diagnostics: Diagnostics,
     index: usize,
     curly_brackets: u32,
     in_struct: Option<String>,
     in_enum: Option<String>,
+    in_union: Option<String>,
+    /// Variants written in the body of the current enum or union, including the invalid ones
+    entries: usize,
+    /// The body of the current struct or enum is parsed but not saved, because the name was already used
+    discard: bool,
+    /// Attributes waiting for the declaration they belong to
+    attributes: Vec<Attribute>,
+    /// Lines of the doc comment waiting for the declaration it belongs to
+    doc: Vec<String>,
+    /// Reads the imported files, without it imports are not allowed
+    pub loader: Option<&'a mut Loader>,
+    /// Name of every imported package with the position of its import
+    imports: Vec<(String, Span)>,
+    /// An import failed, the types of its package can't be checked
+    failed_import: bool,
     row: u32,
     col: u32
 }
 
-impl Parser {
-    pub fn new(src: String) -> Parser {
-        let mut parser = Parser {
+impl<'a> Parser<'a> {
+    pub fn new(src: String) -> Parser<'a> {
+        Parser {
             tokenizer: Tokenizer::new(src),
             ast: ast::AST {
-                package: Package { name: None, version: None, structs: HashMap::new(), enums: HashMap::new() }
+                package: Package { name: None, version: None, structs: IndexMap::new(), enums: IndexMap::new(), unions: IndexMap::new(), constants: IndexMap::new(), imports: IndexMap::new() },
+                imports: Vec::new()
             },
+            max_errors: DEFAULT_MAX_ERRORS,
+            diagnostics: Diagnostics::new(),
             index: 0,
             curly_brackets: 0,
             in_struct: None,
             in_enum: None,
+            in_union: None,
+            entries: 0,
+            discard: false,
+            attributes: Vec::new(),
+            doc: Vec::new(),
+            loader: None,
+            imports: Vec::new(),
+            failed_import: false,
             row: 0,
             col: 0
-        };
-
-        parser.tokenizer.tokenize();
-
-        parser
+        }
     }
 
+    #[allow(dead_code)]
     pub fn print(&self) {
-        println!("{:?}", self.tokenizer.structs);
-        println!("{:?}", self.tokenizer.enums);
-
         println!("{:?} {:?}", self.ast.package.name, self.ast.package.version);
             
         for s in self.ast.package.structs.values() {
             println!("{:?}:", s.name);
             for f in s.fields.values() {
-                println!("{:?} {:?} [{:?}]", f.name, f.t, f.array);
+                println!("{:?} {:?} {:?}", f.name, f.t, f.array);
             }
         }
     
@@ -55,7 +190,7 @@ impl Parser {
     }
 
     pub fn next(&mut self) -> &Token {
-        self.index += 1;
+        self.index = (self.index + 1).min(self.tokenizer.tokens.len() - 1);
         let token = self.tokenizer.tokens.get(self.index).unwrap();
 
         self.row = token.row;
@@ -68,262 +203,1452 @@ impl Parser {
         self.tokenizer.tokens.get(self.index + pos).unwrap()
     }
 
-    /// Create the AST used for code generation
-    pub fn generate_ast(&mut self) {
+    /// Save a diagnostic and keep parsing
+    fn report(&mut self, d: Diagnostic) {
+        self.diagnostics.push(d);
+    }
+
+    fn too_many_errors(&self) -> bool {
+        self.max_errors > 0 && self.diagnostics.errors() >= self.max_errors
+    }
+
+    /// Skip tokens until the end of the current statement (`;`) or block (`}`), so that parsing can go on after an error.
+    /// `start` is the index of the first token of the statement.
+    fn synchronize(&mut self, start: usize) {
+        loop {
+            match self.peek(0).t {
+                TokenTypes::SemiColon | TokenTypes::EndOfStream => return,
+                // The main loop has to see the bracket to close the block and the keyword to start the next declaration
+                TokenTypes::CloseCurlyBracket | TokenTypes::Version | TokenTypes::Package | TokenTypes::Struct | TokenTypes::Enum if self.index > start => {
+                    self.index -= 1;
+                    return;
+                },
+                TokenTypes::Identifier if self.index > start && self.is_declaration_start() => {
+                    self.index -= 1;
+                    return;
+                },
+                _ => self.index += 1
+            }
+        }
+    }
+
+    /// A declaration starting with an identifier instead of a keyword
+    fn is_declaration_start(&self) -> bool {
+        self.is_declaration("flags") || self.is_declaration("union") || self.is_const_declaration() || self.is_import()
+    }
+
+    /// `flags` and `union` are not keywords, so that they can still be used as field names.
+    /// They start a declaration only when followed by a name and `:` or `{`.
+    fn is_declaration(&self, keyword: &str) -> bool {
+        self.peek(0).t == TokenTypes::Identifier
+            && self.peek(0).value.as_deref() == Some(keyword)
+            && self.peek(1).t == TokenTypes::Identifier
+            && matches!(self.peek(2).t, TokenTypes::Colon | TokenTypes::OpenCurlyBracket)
+    }
+
+    /// `const` is not a keyword either, a constant is `const` followed by the type and the name
+    fn is_const_declaration(&self) -> bool {
+        self.peek(0).t == TokenTypes::Identifier
+            && self.peek(0).value.as_deref() == Some("const")
+            && self.peek(1).t == TokenTypes::Identifier
+            && self.peek(2).t == TokenTypes::Identifier
+    }
+
+    /// `import` is followed by the path of the file
+    fn is_import(&self) -> bool {
+        self.peek(0).value.as_deref() == Some("import") && self.peek(1).t == TokenTypes::String
+    }
+
+    /// `optional` before the type of a field, a type can still be named `optional`
+    fn is_optional(&self) -> bool {
+        self.peek(0).value.as_deref() == Some("optional")
+            && self.peek(1).t == TokenTypes::Identifier
+            && matches!(self.peek(2).t, TokenTypes::Identifier | TokenTypes::Dot | TokenTypes::Colon | TokenTypes::OpenSquareBracket)
+    }
+
+    /// `reserved` followed by a number of bytes or a constant, a type can still be named `reserved`
+    fn is_reserved(&self) -> bool {
+        self.peek(0).value.as_deref() == Some("reserved") && match self.peek(1).t {
+            TokenTypes::UInt => true,
+            TokenTypes::Identifier => self.peek(2).t == TokenTypes::SemiColon && self.ast.package.constants.contains_key(self.peek(1).value.as_ref().unwrap()),
+            _ => false
+        }
+    }
+
+    /// Leave the current struct or enum
+    fn close_block(&mut self) {
+        self.curly_brackets = 0;
+        self.in_struct = None;
+        // An enum or a union needs a variant, it's the default value of the fields using it
+        if let Some(name) = self.in_enum.take() {
+            let enmn = self.ast.package.enums.get(&name);
+            if self.entries == 0 && !self.discard && enmn.is_some_and(|e| !e.flags) {
+                self.report(error_at(ErrorType::Parser, format!("Enum `{}` has no variants.", name).as_str(), 22, enmn.unwrap().span)
+                    .with_help("add at least one variant, like `IDLE;`."));
+            }
+        }
+        if let Some(name) = self.in_union.take() {
+            if self.entries == 0 && !self.discard {
+                let span = self.ast.package.unions.get(&name).unwrap().span;
+                self.report(error_at(ErrorType::Parser, format!("Union `{}` has no variants.", name).as_str(), 21, span)
+                    .with_help("add at least one variant, like `Ignite ignite;`."));
+            }
+        }
+        self.entries = 0;
+        self.discard = false;
+    }
+
+    /// Create the AST used for code generation, then run the semantic analysis on it.
+    /// Every error found in the file is returned, up to `max_errors`.
+    pub fn generate_ast(&mut self) -> Result<(), Diagnostics> {
+        if let Err(d) = self.tokenizer.tokenize() {
+            self.diagnostics.extend(d);
+        }
+
         self.ast.package = Package {
             name: None,
             version: None,
-            structs: HashMap::new(),
-            enums: HashMap::new()
+            structs: IndexMap::new(),
+            enums: IndexMap::new(),
+            unions: IndexMap::new(),
+            constants: IndexMap::new(),
+            imports: IndexMap::new()
         };
         
         while self.index < self.tokenizer.tokens.len() {
+            if self.too_many_errors() {
+                self.diagnostics.truncated = true;
+                break;
+            }
+
+            let start = self.index;
             let token = self.tokenizer.tokens.get(self.index).unwrap();
             self.row = token.row;
             self.col = token.col;
-            match token.t {
+            let token_t = token.t.clone();
+            let flags = self.is_declaration("flags");
+            let union = self.is_declaration("union");
+            let constant = self.is_const_declaration();
+            if !matches!(token_t, TokenTypes::At | TokenTypes::DocComment | TokenTypes::Struct | TokenTypes::Enum) && !flags && !union && !constant {
+                for a in std::mem::take(&mut self.attributes) {
+                    self.report(error_at(ErrorType::Parser, format!("Attribute `@{}` can't be used here.", a.name).as_str(), 13, a.span));
+                }
+            }
+
+            let res = match token_t {
+                TokenTypes::At => self.attribute(),
+                TokenTypes::DocComment => {
+                    let line = self.peek(0).value.clone().unwrap();
+                    self.doc.push(line);
+                    Ok(())
+                },
                 TokenTypes::CloseCurlyBracket => {
                     if self.curly_brackets == 0 {
-                        error(ErrorType::Parser, "Unexpected closed curly bracket `}`.", 1, self.row, self.col);
+                        self.report(error(ErrorType::Parser, "Unexpected closed curly bracket `}`.", 5, self.row, self.col));
+                    }
+                    else {
+                        self.close_block();
                     }
-                    
-                    self.curly_brackets -= 1;
-                    self.in_struct = None;
-                    self.in_enum = None;
+                    Ok(())
                 },
                 TokenTypes::Version => self.version(),
                 TokenTypes::Package => self.package(),
                 TokenTypes::Struct => self.structure(),
-                TokenTypes::Enum => self.enumeration(),
+                TokenTypes::Enum => self.enumeration(false),
                 TokenTypes::Identifier => {
-                    if self.in_struct != None {
-                        self.struct_field();
+                    if flags {
+                        self.enumeration(true)
+                    }
+                    else if union {
+                        self.union()
+                    }
+                    else if constant {
+                        self.constant()
+                    }
+                    else if self.is_import() {
+                        self.import()
                     }
-                    else if self.in_enum != None {
-                        self.enum_variant();
+                    else if self.in_struct.is_some() {
+                        self.struct_field()
+                    }
+                    else if self.in_enum.is_some() {
+                        self.enum_variant()
+                    }
+                    else if self.in_union.is_some() {
+                        self.union_variant()
                     }
                     else {
-                        error(ErrorType::Parser, "Unexpected token.", 1, self.row, self.col);
+                        Err(error(ErrorType::Parser, "Unexpected token.", 2, self.row, self.col))
                     }
                 },
                 TokenTypes::EndOfStream => break,
-                _ => error(ErrorType::Parser, "Unexpected token.", 1, self.row, self.col)
+                _ => Err(error(ErrorType::Parser, "Unexpected token.", 2, self.row, self.col))
+            };
+
+            if let Err(d) = res {
+                self.report(d);
+                self.synchronize(start);
+            }
+
+            // Doc comments are kept only by structs, fields and constants, elsewhere they are normal comments
+            if !matches!(token_t, TokenTypes::At | TokenTypes::DocComment) {
+                self.doc.clear();
             }
 
             self.index += 1;
         }
 
-        if self.curly_brackets > 0 {
-            error(ErrorType::Parser, "Opened curly brackets not closed.", 1, 0, 0);
+        if !self.diagnostics.truncated {
+            if self.curly_brackets > 0 {
+                self.report(error(ErrorType::Parser, "Opened curly brackets not closed.", 5, self.row, self.col)
+                    .with_help("add the missing `}`."));
+            }
+
+            if self.ast.package.name.is_none() {
+                self.report(error(ErrorType::Parser, "Package name not declared.", 8, 1, 1)
+                    .with_help("declare the package with `package Name;` after the version."));
+            }
+
+            // The generated code has a module for every package, so the imported files can't declare the package of this one
+            let imports = std::mem::take(&mut self.imports);
+            for (name, span) in &imports {
+                let imported = self.ast.package.imports.get(name).unwrap();
+                if imported.all_packages().iter().any(|p| p.name.is_some() && p.name == self.ast.package.name) {
+                    self.report(error_at(ErrorType::Parser, format!("Package `{}` is already declared by this file.", self.ast.package.name.as_ref().unwrap()).as_str(), 20, *span)
+                        .with_help(format!("`{}` or one of the files it imports declares the same package.", name).as_str()));
+                }
+            }
+
+            if let Err(d) = resolver::resolve(&mut self.ast.package, &imports, self.failed_import) {
+                for d in d.list {
+                    if self.too_many_errors() {
+                        self.diagnostics.truncated = true;
+                        break;
+                    }
+                    self.report(d);
+                }
+            }
+        }
+
+        if self.diagnostics.has_errors() {
+            self.diagnostics.sort();
+            Err(std::mem::take(&mut self.diagnostics))
+        }
+        else {
+            Ok(())
         }
     }
 
     /// Read the version declaration
-    fn version(&mut self) {
-        if self.ast.package.version != None {
-            error(ErrorType::Parser, "Version already declared.", 1, self.row, self.col);
+    fn version(&mut self) -> Result<(), Diagnostic> {
+        if self.ast.package.version.is_some() {
+            self.report(error(ErrorType::Parser, "Version already declared.", 7, self.row, self.col));
         }
 
         let token = self.next();
         if token.t == TokenTypes::SemanticVersion {
-            self.ast.package.version = Some(token.value.clone().unwrap());
+            let (version, row, col) = (token.value.clone().unwrap(), token.row, token.col);
+            self.ast.package.version = Some(version.clone());
 
             let token = self.next();
             if token.t != TokenTypes::SemiColon {
-                error(ErrorType::Parser, "Expected semicolon.", 1, token.row, token.col);
+                return Err(error(ErrorType::Parser, "Expected semicolon.", 3, token.row, token.col));
+            }
+
+            match self.ast.package.check_version() {
+                Ok(true) => {},
+                Ok(false) => self.report(error(ErrorType::Parser, "Version not supported by this compiler.", 7, row, col)
+                    .with_len(version.len() as u32)
+                    .with_help(format!("this is iris {}.", env!("CARGO_PKG_VERSION")).as_str())),
+                Err(_) => self.report(error(ErrorType::Parser, "Invalid semantic version.", 7, row, col)
+                    .with_len(version.len() as u32))
             }
         }
         else {
-            error(ErrorType::Parser, "Expected semantic version after keyword `version`.", 1, token.row, token.col);
+            return Err(error(ErrorType::Parser, "Expected semantic version after keyword `version`.", 7, token.row, token.col));
         }
 
-        if !self.ast.package.check_version() {
-            error(ErrorType::Parser, "Invalid version not valid.", 1, self.row, self.col);
-        }
+        Ok(())
     }
 
     /// Read the declaration of the package name
-    fn package(&mut self) {
-        if self.ast.package.name != None {
-            error(ErrorType::Parser, "Package name already declared.", 1, self.row, self.col);
+    fn package(&mut self) -> Result<(), Diagnostic> {
+        let declared = self.ast.package.name.is_some();
+        if declared {
+            self.report(error(ErrorType::Parser, "Package name already declared.", 8, self.row, self.col));
         }
 
         let token = self.next();
         if token.t == TokenTypes::Identifier {
-            self.ast.package.name = Some(token.value.clone().unwrap());
+            if !declared {
+                self.ast.package.name = Some(token.value.clone().unwrap());
+            }
         
             let token = self.next();
             if token.t != TokenTypes::SemiColon {
-                error(ErrorType::Parser, "Expected semicolon.", 1, token.row, token.col);
+                return Err(error(ErrorType::Parser, "Expected semicolon.", 3, token.row, token.col));
+            }
+        }
+        else {
+            return Err(error(ErrorType::Parser, "Expected identifier after keyword `package`.", 4, token.row, token.col));
+        }
+
+        Ok(())
+    }
+
+    /// Read an attribute and keep it for the next declaration
+    fn attribute(&mut self) -> Result<(), Diagnostic> {
+        let at = self.peek(0).span();
+
+        let token = self.next();
+        if token.t != TokenTypes::Identifier {
+            return Err(error(ErrorType::Parser, "Expected the name of the attribute after `@`.", 13, token.row, token.col));
+        }
+        let name = token.value.clone().unwrap();
+        let span = Span { row: at.row, col: at.col, len: token.span().len + 1 };
+
+        let mut args = Vec::new();
+        if self.peek(1).t == TokenTypes::OpenParenthesis {
+            self.next();
+
+            loop {
+                let token = self.next().clone();
+                match token.t {
+                    TokenTypes::CloseParenthesis if args.is_empty() => break,
+                    TokenTypes::UInt | TokenTypes::SemanticVersion | TokenTypes::Identifier | TokenTypes::String => args.push(token),
+                    // Negative numbers are kept as a single argument
+                    TokenTypes::Minus if matches!(self.peek(1).t, TokenTypes::UInt | TokenTypes::SemanticVersion) => {
+                        let number = self.next();
+                        args.push(Token {
+                            t: number.t.clone(),
+                            value: Some(format!("-{}", number.value.as_ref().unwrap())),
+                            row: token.row,
+                            col: token.col
+                        });
+                    },
+                    _ => return Err(error(ErrorType::Parser, "Expected a value.", 13, token.row, token.col))
+                }
+
+                let token = self.next();
+                match token.t {
+                    TokenTypes::Comma => {},
+                    TokenTypes::CloseParenthesis => break,
+                    _ => return Err(error(ErrorType::Parser, "Expected `,` or `)`.", 13, token.row, token.col))
+                }
             }
         }
+
+        self.attributes.push(Attribute { name, span, args });
+
+        Ok(())
+    }
+
+    /// Report the attributes that can't be used on a declaration, return the others
+    fn check_attributes(&mut self, allowed: &[&str], declaration: &str) -> Vec<Attribute> {
+        let mut out = Vec::new();
+
+        for a in std::mem::take(&mut self.attributes) {
+            if allowed.contains(&a.name.as_str()) {
+                out.push(a);
+            }
+            else if ATTRIBUTES.contains(&a.name.as_str()) {
+                self.report(error_at(ErrorType::Parser, format!("Attribute `@{}` can't be used on {}.", a.name, declaration).as_str(), 13, a.span));
+            }
+            else {
+                self.report(error_at(ErrorType::Parser, format!("Unknown attribute `@{}`.", a.name).as_str(), 13, a.span));
+            }
+        }
+
+        out
+    }
+
+    /// Return the doc comment of the declaration, the lines are joined with `\n`
+    fn take_doc(&mut self) -> Option<String> {
+        if self.doc.is_empty() {
+            None
+        }
         else {
-            error(ErrorType::Parser, "Expected identifier after keyword `package`.", 1, token.row, token.col);
+            Some(std::mem::take(&mut self.doc).join("\n"))
+        }
+    }
+
+    /// Read the unit given with `@unit("...")`
+    fn unit_attribute(&mut self, a: &Attribute) -> Option<String> {
+        match a.args.as_slice() {
+            [arg] if arg.t == TokenTypes::String => arg.value.clone(),
+            _ => {
+                self.report(error_at(ErrorType::Parser, "Invalid unit.", 13, a.span)
+                    .with_help("the unit must be a string, e.g. `@unit(\"V\")`."));
+                None
+            }
+        }
+    }
+
+    /// Read the note given with `@deprecated("...")`, it's empty for `@deprecated`
+    fn deprecated_attribute(&mut self, a: &Attribute) -> Option<String> {
+        match a.args.as_slice() {
+            [] => Some(String::new()),
+            [arg] if arg.t == TokenTypes::String => arg.value.clone(),
+            _ => {
+                self.report(error_at(ErrorType::Parser, "Invalid deprecation note.", 13, a.span)
+                    .with_help("the note must be a string, e.g. `@deprecated(\"use `rate`\")`."));
+                None
+            }
+        }
+    }
+
+    /// Read the id given with `@id(...)`
+    fn id_attribute(&mut self, a: &Attribute) -> Option<u32> {
+        let id = match a.args.as_slice() {
+            [arg] if arg.t == TokenTypes::UInt => parse_uint(arg.value.as_ref().unwrap()).and_then(|v| u32::try_from(v).ok()),
+            _ => None
+        };
+
+        if id.is_none() {
+            self.report(error_at(ErrorType::Parser, "Invalid struct id.", 13, a.span)
+                .with_help("the id must be a 32-bit unsigned integer, e.g. `@id(0x1A2B3C4D)`."));
         }
+
+        id
     }
 
     /// Create a node representing a struct
-    fn structure(&mut self) {
+    fn structure(&mut self) -> Result<(), Diagnostic> {
+        let doc = self.take_doc();
+        let mut explicit_id = None;
+        for a in self.check_attributes(&["id"], "structs") {
+            if a.name == "id" {
+                explicit_id = self.id_attribute(&a);
+            }
+        }
+
         if self.curly_brackets > 0 {
-            error(ErrorType::Parser, "Curly bracket not closed.", 1, self.row, self.col);
+            self.report(error(ErrorType::Parser, "Curly bracket not closed.", 5, self.row, self.col)
+                .with_help("add `}` before this declaration."));
+            self.close_block();
         }
 
         let token_t = self.next().t.clone();
         if token_t == TokenTypes::Identifier {
             let name = self.peek(0).value.clone().unwrap();
+            let name_span = self.peek(0).span();
 
             let token_t = self.next().t.clone();
             if token_t == TokenTypes::OpenCurlyBracket {
-                if self.ast.package.structs.contains_key(&name) || self.ast.package.enums.contains_key(&name) {
-                    error(ErrorType::Parser, "Name already used.", 1, self.row, self.col);
+                if self.ast.package.is_declared(&name) {
+                    self.report(error_at(ErrorType::Parser, "Name already used.", 6, name_span));
+                    self.discard = true;
                 }
                 else {
                     self.ast.package.structs.insert(name.clone(), ast::Struct {
                         name: name.clone(),
-                        fields: HashMap::new(),
-                        fields_order: Vec::new()
+                        span: name_span,
+                        explicit_id,
+                        doc,
+                        fields: IndexMap::new()
                     });
                 }
                 self.curly_brackets += 1;
                 self.in_struct = Some(name);
             }
             else {
-                error(ErrorType::Parser, "Expected `{` after the identifier of struct.", 1, self.row, self.col);
+                return Err(error(ErrorType::Parser, "Expected `{` after the identifier of struct.", 5, self.row, self.col));
             }
         }
+        else if token_t == TokenTypes::OpenCurlyBracket {
+            // The fields are still read, so that the closing bracket and the following declarations are not reported
+            self.report(error(ErrorType::Parser, "Expected identifier after keyword `struct`.", 4, self.row, self.col));
+            self.discard = true;
+            self.curly_brackets += 1;
+            self.in_struct = Some(String::new());
+        }
         else {
-            error(ErrorType::Parser, "Expected identifier after keyword `struct`.", 1, self.row, self.col);
+            return Err(error(ErrorType::Parser, "Expected identifier after keyword `struct`.", 4, self.row, self.col));
         }
+
+        Ok(())
     }
 
-    /// Create a node representing an enum 
-    fn enumeration(&mut self) {
+    /// Create a node representing an enum, or a set of flags when declared with `flags`
+    fn enumeration(&mut self, flags: bool) -> Result<(), Diagnostic> {
+        let keyword = if flags { "flags" } else { "enum" };
+        self.check_attributes(&[], if flags { "flags" } else { "enums" });
+
         if self.curly_brackets > 0 {
-            error(ErrorType::Parser, "Curly bracket not closed.", 1, self.row, self.col);
+            self.report(error(ErrorType::Parser, "Curly bracket not closed.", 5, self.row, self.col)
+                .with_help("add `}` before this declaration."));
+            self.close_block();
         }
 
         let token = self.next();
         if token.t == TokenTypes::Identifier {
             let name = token.value.as_ref().unwrap().clone();
+            let name_span = token.span();
+            let mut repr = PrimitiveTypes::U32;
+
+            let mut token_t = self.next().t.clone();
+            if token_t == TokenTypes::Colon {
+                // An invalid type is reported and replaced with the default one, so that the variants are still checked
+                let token = self.peek(1);
+                match token.value.as_deref().and_then(PrimitiveTypes::new) {
+                    Some(p @ (PrimitiveTypes::U8 | PrimitiveTypes::U16 | PrimitiveTypes::U32)) => repr = p,
+                    _ => self.report(error_at(ErrorType::Parser, "Invalid enum type.", 16, token.span())
+                        .with_help("enums can be encoded as `u8`, `u16` or `u32`."))
+                }
+
+                if self.peek(1).t == TokenTypes::Identifier {
+                    self.next();
+                }
+                token_t = self.next().t.clone();
+            }
 
-            let token_t = self.next().t.clone();
             if token_t == TokenTypes::OpenCurlyBracket {
-                if self.ast.package.structs.contains_key(&name) || self.ast.package.enums.contains_key(&name) {
-                    error(ErrorType::Parser, "Name already used.", 1, self.row, self.col);
+                if self.ast.package.is_declared(&name) {
+                    self.report(error_at(ErrorType::Parser, "Name already used.", 6, name_span));
+                    self.discard = true;
                 }
                 else {
                     self.ast.package.enums.insert(name.clone(), ast::Enum {
                         name: name.clone(),
-                        variants: HashMap::new()
+                        span: name_span,
+                        repr,
+                        flags,
+                        variants: IndexMap::new()
                     });
                 }
                 self.curly_brackets += 1;
                 self.in_enum = Some(name);
             }
             else {
-                error(ErrorType::Parser, "Expected `{` after the identifier of an enum.", 1, self.row, self.col);
+                return Err(error(ErrorType::Parser, format!("Expected `{{` after the identifier of {}.", if flags { "flags" } else { "an enum" }).as_str(), 5, self.row, self.col));
             }
         }
+        else if token.t == TokenTypes::OpenCurlyBracket {
+            // The variants are still read, like the fields of a struct without a name
+            let d = error(ErrorType::Parser, format!("Expected identifier after keyword `{}`.", keyword).as_str(), 4, token.row, token.col);
+            self.report(d);
+            self.discard = true;
+            self.curly_brackets += 1;
+            self.in_enum = Some(String::new());
+        }
         else {
-            error(ErrorType::Parser, "Expected identifier after keyword `enum`.", 1, token.row, token.col);
+            return Err(error(ErrorType::Parser, format!("Expected identifier after keyword `{}`.", keyword).as_str(), 4, token.row, token.col));
+        }
+
+        Ok(())
+    }
+
+    /// Create a tagged union, `union Payload : u8 { Ignite ignite; Abort abort; }`
+    fn union(&mut self) -> Result<(), Diagnostic> {
+        let doc = self.take_doc();
+        self.check_attributes(&[], "unions");
+
+        if self.curly_brackets > 0 {
+            self.report(error(ErrorType::Parser, "Curly bracket not closed.", 5, self.row, self.col)
+                .with_help("add `}` before this declaration."));
+            self.close_block();
+        }
+
+        let token = self.next();
+        if token.t != TokenTypes::Identifier {
+            return Err(error(ErrorType::Parser, "Expected identifier after keyword `union`.", 4, token.row, token.col));
+        }
+        let name = token.value.as_ref().unwrap().clone();
+        let name_span = token.span();
+        let mut repr = PrimitiveTypes::U32;
+
+        let mut token_t = self.next().t.clone();
+        if token_t == TokenTypes::Colon {
+            // An invalid type is reported and replaced with the default one, so that the variants are still checked
+            let token = self.peek(1);
+            match token.value.as_deref().and_then(PrimitiveTypes::new) {
+                Some(p @ (PrimitiveTypes::U8 | PrimitiveTypes::U16 | PrimitiveTypes::U32)) => repr = p,
+                _ => self.report(error_at(ErrorType::Parser, "Invalid union type.", 21, token.span())
+                    .with_help("the variant of a union can be encoded as `u8`, `u16` or `u32`."))
+            }
+
+            if self.peek(1).t == TokenTypes::Identifier {
+                self.next();
+            }
+            token_t = self.next().t.clone();
+        }
+
+        if token_t != TokenTypes::OpenCurlyBracket {
+            return Err(error(ErrorType::Parser, "Expected `{` after the identifier of a union.", 5, self.row, self.col));
+        }
+
+        if self.ast.package.is_declared(&name) {
+            self.report(error_at(ErrorType::Parser, "Name already used.", 6, name_span));
+            self.discard = true;
+        }
+        else {
+            self.ast.package.unions.insert(name.clone(), ast::Union {
+                name: name.clone(),
+                span: name_span,
+                repr,
+                doc,
+                variants: IndexMap::new()
+            });
+        }
+        self.curly_brackets += 1;
+        self.in_union = Some(name);
+
+        Ok(())
+    }
+
+    /// Import the package declared by another file, `import "common/gnss.iris";`
+    fn import(&mut self) -> Result<(), Diagnostic> {
+        if self.curly_brackets > 0 {
+            return Err(error(ErrorType::Parser, "Imports can't be declared inside structs or enums.", 20, self.row, self.col)
+                .with_help("move the import to the top of the file."));
+        }
+
+        let token = self.next().clone();
+        let span = value_span(&token);
+        let path = token.value.unwrap();
+
+        let token = self.next();
+        if token.t != TokenTypes::SemiColon {
+            return Err(error(ErrorType::Parser, "Expected a semicolon `;`.", 3, token.row, token.col));
+        }
+
+        let Some(loader) = self.loader.as_deref_mut() else {
+            self.report(error_at(ErrorType::Parser, "Imports need the path of the file.", 20, span)
+                .with_help("parse the schema with `iris::parse_with_imports`."));
+            return Ok(());
+        };
+
+        let package = match loader.import(&path) {
+            Ok(package) => package,
+            Err(e) => {
+                let d = match e {
+                    ImportError::NotFound => error_at(ErrorType::Parser, "File not found.", 20, span)
+                        .with_help(format!("`{}` is searched from the directory of this file, then from the include directories.", path).as_str()),
+                    ImportError::Unreadable(cause) => error_at(ErrorType::Parser, "Can't read the imported file.", 20, span)
+                        .with_help(format!("{}.", cause).as_str()),
+                    ImportError::Cycle(files) => error_at(ErrorType::Parser, "Circular import.", 20, span)
+                        .with_help(format!("the files import each other: {}.", files.join(" -> ")).as_str()),
+                    ImportError::Conflict(name, file) => error_at(ErrorType::Parser, format!("Package `{}` is already declared by `{}`.", name, file).as_str(), 20, span)
+                        .with_help("every imported package must have a different name."),
+                    ImportError::Invalid => error_at(ErrorType::Parser, "The imported file has errors.", 20, span)
+                };
+                self.report(d);
+                self.failed_import = true;
+                return Ok(());
+            }
+        };
+
+        let name = package.name.clone().unwrap();
+        if self.ast.package.imports.contains_key(&name) {
+            self.report(error_at(ErrorType::Parser, format!("Package `{}` already imported.", name).as_str(), 20, span));
+            return Ok(());
+        }
+
+        self.imports.push((name.clone(), span));
+        self.ast.package.imports.insert(name, package);
+
+        Ok(())
+    }
+
+    /// Create a constant, `const u8 MAX_ENGINES = 4;`
+    fn constant(&mut self) -> Result<(), Diagnostic> {
+        let doc = self.take_doc();
+        self.check_attributes(&[], "constants");
+
+        if self.curly_brackets > 0 {
+            return Err(error(ErrorType::Parser, "Constants can't be declared inside structs or enums.", 19, self.row, self.col)
+                .with_help("move the declaration before the struct or enum."));
+        }
+
+        let type_token = self.next().clone();
+        let t = PrimitiveTypes::new(type_token.value.as_ref().unwrap());
+        if t.is_none() {
+            self.report(error_at(ErrorType::Parser, "Invalid constant type.", 19, type_token.span())
+                .with_help("constants can be integers, floats or `bool`."));
+        }
+
+        // The name is an identifier, `is_const_declaration` checked it
+        let name_token = self.next().clone();
+        let name_span = name_token.span();
+        let name = name_token.value.unwrap();
+        let valid_name = !TARGET_KEYWORDS.contains(&name.as_str());
+        if !valid_name {
+            self.report(error_at(ErrorType::Parser, format!("`{}` can't be the name of a constant.", name).as_str(), 19, name_span)
+                .with_help("it's a value or a keyword of a generated language, use another name."));
+        }
+
+        let token = self.next();
+        if token.t != TokenTypes::Equal {
+            return Err(error(ErrorType::Parser, "Expected `=` after the name of the constant.", 19, token.row, token.col));
+        }
+        let value = self.value_token(19)?;
+
+        let token = self.next();
+        if token.t != TokenTypes::SemiColon {
+            return Err(error(ErrorType::Parser, "Expected a semicolon `;`.", 3, token.row, token.col));
+        }
+
+        let (Some(t), true) = (t, valid_name) else {
+            return Ok(());
+        };
+        let value = match parse_value(&value, &FieldType::PRIMITIVE(t.clone()), None, None) {
+            Ok(v) => v,
+            Err(help) => {
+                self.report(error_at(ErrorType::Parser, "Invalid constant value.", 19, value_span(&value)).with_help(help.as_str()));
+                return Ok(());
+            }
+        };
+
+        if self.ast.package.is_declared(&name) {
+            self.report(error_at(ErrorType::Parser, "Name already used.", 6, name_span));
+            return Ok(());
         }
+
+        self.ast.package.constants.insert(name.clone(), ast::Constant { name, span: name_span, t, value, doc });
+
+        Ok(())
     }
 
     /// Add the field to the struct
-    fn struct_field(&mut self) {
-        let mut array: Option<u32> = None;
-        let mut name: String = String::new();
-
-        //let var_type = self.tokenizer.tokens.get(self.index).unwrap();
-        let var_type = self.peek(0);
-        let field_type = match var_type.value.clone().unwrap().as_str() {
-            "u8" | "u16" | "u32" | "i8" | "i16" | "i32" | "f32" | "bool" => FieldType::PRIMITIVE(PrimitiveTypes::new(var_type.value.clone().unwrap())),
-            _ => {
-                if self.tokenizer.structs.contains(&var_type.value.clone().unwrap()) {
-                    FieldType::COMPLEX(ComplexTypes::Struct(var_type.value.clone().unwrap()))
-                }
-                else if self.tokenizer.enums.contains(&var_type.value.clone().unwrap()) {
-                    FieldType::COMPLEX(ComplexTypes::Enum(var_type.value.clone().unwrap()))
-                }
-                else {
-                    FieldType::COMPLEX(ComplexTypes::Unknown(var_type.value.clone().unwrap()))
+    fn struct_field(&mut self) -> Result<(), Diagnostic> {
+        if self.is_reserved() {
+            return self.reserved();
+        }
+
+        let doc = self.take_doc();
+        let mut array: Vec<u32> = Vec::new();
+        let mut bounded = false;
+        let mut bits: Option<u32> = None;
+        let name: String;
+
+        let optional = self.is_optional();
+        if optional {
+            self.next();
+        }
+
+        let (var_type, mut type_span) = self.type_name();
+        // Structs and enums are resolved by the semantic analysis, after the whole file is parsed
+        let field_type = match var_type.as_str() {
+            "string" => {
+                if self.next().t != TokenTypes::OpenSquareBracket {
+                    return Err(error(ErrorType::Parser, "Expected the capacity of the string.", 9, self.row, self.col)
+                        .with_help("declare strings as `string[N]`, where N is the maximum length in bytes."));
                 }
+
+                let capacity = self.array_size()?;
+                type_span.len = self.col + 1 - type_span.col;
+
+                FieldType::STRING(capacity)
+            },
+            t => match PrimitiveTypes::new(t) {
+                Some(p) => FieldType::PRIMITIVE(p),
+                None => FieldType::COMPLEX(ComplexTypes::Unknown(t.to_string()))
             }
         };
 
+        if self.peek(1).t == TokenTypes::Colon {
+            self.next();
+            if optional {
+                return Err(error(ErrorType::Parser, "Optional fields can't be bitfields.", 17, self.row, self.col)
+                    .with_help("the bits are always encoded, use a value that means missing."));
+            }
+            bits = Some(self.bit_width(&field_type, type_span)?);
+        }
+
         let token = self.next();
         if token.t == TokenTypes::OpenSquareBracket {
-            let array_size = self.next();
+            if bits.is_some() {
+                return Err(error(ErrorType::Parser, "Bitfields can't be arrays.", 17, token.row, token.col));
+            }
+            if optional {
+                return Err(error(ErrorType::Parser, "Optional fields can't be arrays.", 9, token.row, token.col)
+                    .with_help("use a bounded array, it can be empty."));
+            }
 
-            if array_size.t == TokenTypes::UInt {
-                array = match array_size.value.as_ref().unwrap().parse() {
-                    Ok(v) => Some(v),
-                    Err(_) => None
-                };
-                if array == None {
-                    error(ErrorType::Parser, "Invalid index.", 1, array_size.row, array_size.col);
-                }
+            let start = token.span();
+
+            // `[<=N]` is a bounded array, with up to N elements
+            if self.peek(1).t == TokenTypes::LessEqual {
+                self.next();
+                bounded = true;
             }
-            else {
-                error(ErrorType::Parser, "Expected unsigned integer.", 1, array_size.row, array_size.col);
+            array.push(self.array_size()?);
+            while self.next().t == TokenTypes::OpenSquareBracket {
+                if bounded || self.peek(1).t == TokenTypes::LessEqual {
+                    return Err(error(ErrorType::Parser, "Bounded arrays can't have more dimensions.", 9, start.row, start.col)
+                        .with_len(self.col - start.col)
+                        .with_help("use a struct with the inner array as the type of the elements."));
+                }
+                array.push(self.array_size()?);
             }
 
-            let token = self.next();
-            if token.t != TokenTypes::CloseSquareBracket {
-                error(ErrorType::Parser, "Expected `]` but found something else.", 1, token.row, token.col);
+            if array.iter().try_fold(1u32, |acc, n| acc.checked_mul(*n)).is_none() {
+                return Err(error(ErrorType::Parser, "Array too big.", 9, start.row, start.col)
+                    .with_len(self.col - start.col));
             }
 
-            let token = self.next();
+            let token = self.peek(0);
             if token.t == TokenTypes::Identifier {
                 name = token.value.clone().unwrap();
             }
             else {
-                error(ErrorType::Parser, "Expected an identifier.", 1, token.row, token.col);
+                return Err(error(ErrorType::Parser, "Expected an identifier.", 4, token.row, token.col));
             }
         }
         else if token.t == TokenTypes::Identifier {
             name = token.value.clone().unwrap();
         }
         else {
-            error(ErrorType::Parser, "Unexpected token after identifier.", 1, token.row, token.col);
+            return Err(error(ErrorType::Parser, "Unexpected token after identifier.", 2, token.row, token.col));
+        }
+        let name_span = self.peek(0).span();
+
+        // Attributes and the default value of the field are written after the name, `i16 temp = 20 @scale(0.1);`
+        let mut res = Ok(());
+        let mut default = None;
+        while res.is_ok() {
+            match self.peek(1).t {
+                TokenTypes::At => {
+                    self.next();
+                    res = self.attribute();
+                },
+                TokenTypes::Equal if default.is_none() => {
+                    self.next();
+                    res = self.value_token(18).map(|t| default = Some(t));
+                },
+                _ => break
+            }
         }
+        let attributes = self.check_attributes(&["scale", "offset", "unit", "range", "min", "max", "deprecated"], "fields");
+        res?;
 
         let token_t = self.next().t.clone();
         if token_t != TokenTypes::SemiColon {
-            error(ErrorType::Parser, "Expected a semicolon `;`.", 1, self.row, self.col);
+            return Err(error(ErrorType::Parser, "Expected a semicolon `;`.", 3, self.row, self.col));
+        }
+
+        let scaling = self.scaling(&attributes, &field_type, bits);
+        let mut unit = None;
+        for a in attributes.iter().filter(|a| a.name == "unit") {
+            unit = self.unit_attribute(a);
+        }
+        let mut deprecated = None;
+        for a in attributes.iter().filter(|a| a.name == "deprecated") {
+            deprecated = self.deprecated_attribute(a);
+        }
+        let limits = self.limits(&attributes, &field_type, scaling.is_some());
+        // A bounded array starts empty
+        let default = match default {
+            Some(t) if bounded => {
+                self.report(error_at(ErrorType::Parser, "Bounded arrays can't have a default value.", 18, value_span(&t))
+                    .with_help("a bounded array is empty until its elements are added."));
+                None
+            },
+            Some(t) if optional => {
+                self.report(error_at(ErrorType::Parser, "Optional fields can't have a default value.", 18, value_span(&t))
+                    .with_help("an optional field is missing until it's set."));
+                None
+            },
+            _ => default.and_then(|t| self.default_value(&t, &field_type, bits, scaling, &limits))
+        };
+
+        if self.discard {
+            return Ok(());
         }
 
         if self.ast.package.structs.get(self.in_struct.as_ref().unwrap()).unwrap().fields.contains_key(&name) {
-            error(ErrorType::Parser, "Field name already used.", 1, self.row, self.col);
+            self.report(error_at(ErrorType::Parser, "Field name already used.", 6, name_span));
+        }
+        else {
+            self.ast.package.add_struct_field(
+                self.in_struct.as_ref().unwrap(), 
+                StructField { name, span: name_span, t: field_type, type_span, array, bounded, optional, reserved: false, deprecated, bits, scaling, doc, unit, limits, default }
+            );
         }
 
+        Ok(())
+    }
+
+    /// Add the padding declared with `reserved 4;` to the struct, as a field that isn't exposed by the generated code
+    fn reserved(&mut self) -> Result<(), Diagnostic> {
+        let span = self.peek(0).span();
+        self.take_doc();
+        self.check_attributes(&[], "reserved bytes");
+
+        let size = self.size()?;
+        if self.next().t != TokenTypes::SemiColon {
+            return Err(error(ErrorType::Parser, "Expected a semicolon `;`.", 3, self.row, self.col));
+        }
+
+        if self.discard {
+            return Ok(());
+        }
+
+        let strc = self.ast.package.structs.get(self.in_struct.as_ref().unwrap()).unwrap();
+        let name = format!("reserved#{}", strc.fields.values().filter(|f| f.reserved).count());
         self.ast.package.add_struct_field(
-            self.in_struct.as_ref().unwrap(), 
-            StructField { name, t: field_type, array }
+            self.in_struct.as_ref().unwrap(),
+            StructField {
+                name, span, t: FieldType::PRIMITIVE(PrimitiveTypes::U8), type_span: span, array: vec![size], bounded: false, optional: false, reserved: true,
+                deprecated: None, bits: None, scaling: None, doc: None, unit: None, limits: None, default: None
+            }
         );
+
+        Ok(())
+    }
+
+    /// Read the name of the type of a field, the types of imported packages are qualified by the package, `Gnss.Fix`
+    fn type_name(&mut self) -> (String, Span) {
+        let mut name = self.peek(0).value.clone().unwrap();
+        let mut span = self.peek(0).span();
+
+        if self.peek(1).t == TokenTypes::Dot && self.peek(2).t == TokenTypes::Identifier {
+            self.next();
+            name = format!("{}.{}", name, self.next().value.as_ref().unwrap());
+            span.len = self.peek(0).span().len + self.col - span.col;
+        }
+
+        (name, span)
+    }
+
+    /// Return the value after `=`, a number with an optional `-`, a string or an identifier.
+    /// `code` is the code of the error when there is no value.
+    fn value_token(&mut self, code: u32) -> Result<Token, Diagnostic> {
+        let token = self.next().clone();
+
+        match token.t {
+            TokenTypes::UInt | TokenTypes::SemanticVersion | TokenTypes::String | TokenTypes::Identifier => Ok(token),
+            TokenTypes::Minus if matches!(self.peek(1).t, TokenTypes::UInt | TokenTypes::SemanticVersion) => {
+                let number = self.next();
+                Ok(Token {
+                    t: number.t.clone(),
+                    value: Some(format!("-{}", number.value.as_ref().unwrap())),
+                    row: token.row,
+                    col: token.col
+                })
+            },
+            _ => Err(error(ErrorType::Parser, "Expected a value after `=`.", code, token.row, token.col))
+        }
+    }
+
+    /// Check that the default value can be given to the field.
+    /// Enums are checked by the semantic analysis, when the variants of the type are known.
+    fn default_value(&mut self, token: &Token, field_type: &FieldType, bits: Option<u32>, scaling: Option<ast::Scaling>, limits: &Option<ast::Limits>) -> Option<(ast::Value, Span)> {
+        let span = value_span(token);
+        let value = match parse_value(token, field_type, bits, scaling) {
+            Ok(v) => v,
+            Err(help) => {
+                self.report(error_at(ErrorType::Parser, "Invalid default value.", 18, span).with_help(help.as_str()));
+                return None;
+            }
+        };
+
+        // A default that doesn't pass `validate` is a mistake
+        if let Some(ast::Limits::Range(min, max)) = limits {
+            let v = match value {
+                ast::Value::Int(v) => v as f64,
+                ast::Value::Float(v) => v,
+                _ => return Some((value, span))
+            };
+            if !(v >= *min && v <= *max) {
+                self.report(error_at(ErrorType::Parser, "Default value out of the range.", 18, span)
+                    .with_help(format!("the range of the field is from {} to {}.", min, max).as_str()));
+                return None;
+            }
+        }
+
+        Some((value, span))
+    }
+
+    /// Read `@scale` and `@offset`, they can be used only on integer fields
+    fn scaling(&mut self, attributes: &[Attribute], field_type: &FieldType, bits: Option<u32>) -> Option<ast::Scaling> {
+        let mut scaling = ast::Scaling { scale: 1.0, offset: 0.0 };
+        let attributes: Vec<&Attribute> = attributes.iter().filter(|a| a.name == "scale" || a.name == "offset").collect();
+
+        for a in attributes.iter() {
+            let value = match a.args.as_slice() {
+                [arg] if matches!(arg.t, TokenTypes::UInt | TokenTypes::SemanticVersion) => parse_number(arg.value.as_ref().unwrap()),
+                _ => None
+            };
+
+            match (a.name.as_str(), value) {
+                ("scale", Some(v)) if v != 0.0 && v.is_finite() => scaling.scale = v,
+                ("scale", _) => self.report(error_at(ErrorType::Parser, "Invalid scale.", 13, a.span)
+                    .with_help("the scale must be a number different from 0, e.g. `@scale(0.1)`.")),
+                ("offset", Some(v)) if v.is_finite() => scaling.offset = v,
+                _ => self.report(error_at(ErrorType::Parser, "Invalid offset.", 13, a.span)
+                    .with_help("the offset must be a number, e.g. `@offset(-40)`."))
+            }
+        }
+
+        let a = attributes.first()?;
+        let integer = matches!(field_type, FieldType::PRIMITIVE(p) if p.limits().is_some());
+        if !integer || bits.is_some() {
+            self.report(error_at(ErrorType::Parser, format!("Attribute `@{}` can only be used on integer fields.", a.name).as_str(), 13, a.span)
+                .with_help(if integer { "bitfields can't be scaled." } else { "the value is encoded as an integer, use a type like `i16` or `u32`." }));
+            return None;
+        }
+
+        Some(scaling)
+    }
+
+    /// Read `@range(min, max)` of a number, or `@min` and `@max` of an enum.
+    /// The variants of the enum are checked by the semantic analysis, when the type of the field is known.
+    fn limits(&mut self, attributes: &[Attribute], field_type: &FieldType, scaled: bool) -> Option<ast::Limits> {
+        let mut limits = None;
+
+        for a in attributes.iter().filter(|a| a.name == "range") {
+            let number = match field_type {
+                FieldType::PRIMITIVE(p) if !matches!(p, PrimitiveTypes::Bool) => Some(p),
+                _ => None
+            };
+            let Some(p) = number else {
+                let d = error_at(ErrorType::Parser, "Attribute `@range` can only be used on numeric fields.", 13, a.span);
+                self.report(match field_type {
+                    FieldType::COMPLEX(_) => d.with_help("use `@min` and `@max` with the variants of an enum."),
+                    _ => d
+                });
+                continue;
+            };
+
+            let range = match a.args.as_slice() {
+                [min, max] if [min, max].iter().all(|t| matches!(t.t, TokenTypes::UInt | TokenTypes::SemanticVersion)) => {
+                    parse_number(min.value.as_ref().unwrap()).zip(parse_number(max.value.as_ref().unwrap()))
+                },
+                _ => None
+            };
+            let Some((min, max)) = range else {
+                self.report(error_at(ErrorType::Parser, "Invalid range.", 13, a.span)
+                    .with_help("give the minimum and the maximum value, e.g. `@range(0, 100)`."));
+                continue;
+            };
+
+            // The limits of integers are compared with the encoded value, so they must be integers of the same type
+            match p.limits() {
+                Some((low, high)) if !scaled && [min, max].iter().any(|v| v.fract() != 0.0 || *v < low as f64 || *v > high as f64) => {
+                    self.report(error_at(ErrorType::Parser, "Invalid range.", 13, a.span)
+                        .with_help(format!("the limits of a `{}` field must be integers from {} to {}.", p.str(), low, high).as_str()));
+                },
+                _ if min > max => self.report(error_at(ErrorType::Parser, "Invalid range.", 13, a.span)
+                    .with_help("the minimum can't be greater than the maximum.")),
+                _ => limits = Some(ast::Limits::Range(min, max))
+            }
+        }
+
+        let mut variants = (None, None);
+        for a in attributes.iter().filter(|a| a.name == "min" || a.name == "max") {
+            if !matches!(field_type, FieldType::COMPLEX(_)) {
+                let d = error_at(ErrorType::Parser, format!("Attribute `@{}` can only be used on enum fields.", a.name).as_str(), 13, a.span);
+                self.report(match field_type {
+                    FieldType::PRIMITIVE(p) if !matches!(p, PrimitiveTypes::Bool) => d.with_help("use `@range(min, max)` for numbers."),
+                    _ => d
+                });
+                continue;
+            }
+
+            match a.args.as_slice() {
+                [arg] if arg.t == TokenTypes::Identifier => {
+                    let variant = Some((arg.value.clone().unwrap(), a.span));
+                    if a.name == "min" { variants.0 = variant } else { variants.1 = variant }
+                },
+                _ => self.report(error_at(ErrorType::Parser, format!("Invalid `@{}`.", a.name).as_str(), 13, a.span)
+                    .with_help(format!("give the name of a variant, e.g. `@{}(IDLE)`.", a.name).as_str()))
+            }
+        }
+        if variants.0.is_some() || variants.1.is_some() {
+            limits = Some(ast::Limits::Variants(variants.0, variants.1));
+        }
+
+        limits
+    }
+
+    /// Parse the width of a bitfield after the colon, `u8:3`
+    fn bit_width(&mut self, field_type: &FieldType, type_span: Span) -> Result<u32, Diagnostic> {
+        let max = match field_type {
+            FieldType::PRIMITIVE(PrimitiveTypes::Bool) => 1,
+            FieldType::PRIMITIVE(p @ (PrimitiveTypes::U8 | PrimitiveTypes::U16 | PrimitiveTypes::U32 | PrimitiveTypes::U64)) => p.size() * 8,
+            _ => return Err(error_at(ErrorType::Parser, format!("`{}` can't be a bitfield.", field_type.str()).as_str(), 17, type_span)
+                .with_help("bitfields can be unsigned integers or `bool`."))
+        };
+
+        let token = self.next();
+        if token.t != TokenTypes::UInt {
+            return Err(error(ErrorType::Parser, "Expected the number of bits.", 17, token.row, token.col));
+        }
+
+        let span = token.span();
+        match parse_uint(token.value.as_ref().unwrap()) {
+            Some(width) if (1..=max as u64).contains(&width) => Ok(width as u32),
+            _ if max == 1 => Err(error_at(ErrorType::Parser, "Invalid number of bits.", 17, span)
+                .with_help(format!("a `{}` bitfield has 1 bit.", field_type.str()).as_str())),
+            _ => Err(error_at(ErrorType::Parser, "Invalid number of bits.", 17, span)
+                .with_help(format!("a `{}` bitfield can have from 1 to {} bits.", field_type.str(), max).as_str()))
+        }
+    }
+
+    /// Parse `N]` after an open square bracket, N is an integer or a constant declared before and must be greater than 0
+    fn array_size(&mut self) -> Result<u32, Diagnostic> {
+        let size = self.size()?;
+
+        let token = self.next();
+        if token.t != TokenTypes::CloseSquareBracket {
+            return Err(error(ErrorType::Parser, "Expected `]` but found something else.", 9, token.row, token.col));
+        }
+
+        Ok(size)
+    }
+
+    /// Read a size greater than 0, an integer or a constant
+    fn size(&mut self) -> Result<u32, Diagnostic> {
+        let array_size = self.next().clone();
+        let span = array_size.span();
+
+        let size = match array_size.t {
+            TokenTypes::UInt => parse_uint(array_size.value.as_ref().unwrap()).and_then(|v| u32::try_from(v).ok()),
+            TokenTypes::Identifier => {
+                let name = array_size.value.as_ref().unwrap();
+                let constant = self.ast.package.constants.get(name).ok_or_else(|| error_at(ErrorType::Parser, format!("Unknown constant `{}`.", name).as_str(), 19, span)
+                    .with_help("constants must be declared before they are used."))?;
+
+                match constant.value {
+                    ast::Value::Int(0) => return Err(error_at(ErrorType::Parser, "The size must be greater than 0.", 9, span)
+                        .with_help(format!("`{}` is 0.", name).as_str())),
+                    ast::Value::Int(v) if u32::try_from(v).is_err() => return Err(error_at(ErrorType::Parser, "Invalid array size.", 9, span)
+                        .with_help(format!("`{}` is {}, the size must be from 1 to {}.", name, v, u32::MAX).as_str())),
+                    ast::Value::Int(v) => Some(v as u32),
+                    _ => return Err(error_at(ErrorType::Parser, "Invalid array size.", 9, span)
+                        .with_help(format!("`{}` is a `{}`, the size must be an integer.", name, constant.t.str()).as_str()))
+                }
+            },
+            _ => return Err(error(ErrorType::Parser, "Expected unsigned integer or constant.", 9, array_size.row, array_size.col))
+        };
+
+        let size = match size {
+            Some(0) => return Err(error_at(ErrorType::Parser, "The size must be greater than 0.", 9, span)),
+            None => return Err(error_at(ErrorType::Parser, "Invalid index.", 9, span)),
+            Some(size) => size
+        };
+
+        Ok(size)
     }
 
     /// Add the variant to the enum
-    fn enum_variant(&mut self) {
-        let variant_value = self.ast.package.enums.get(self.in_enum.as_ref().unwrap()).unwrap().variants.len();
-        
+    fn enum_variant(&mut self) -> Result<(), Diagnostic> {
+        self.entries += 1;
         let name = self.peek(0).value.clone().unwrap();
-        if self.ast.package.enums.get(self.in_enum.as_ref().unwrap()).unwrap().variants.contains_key(&name) {
-            error(ErrorType::Parser, "Variant name already used.", 1, self.row, self.col);
+        let name_span = self.peek(0).span();
+        let mut explicit_value: Option<(u64, Span)> = None;
+
+        let mut token = self.next();
+        if token.t == TokenTypes::Equal {
+            let value = self.next();
+            let value_span = value.span();
+
+            if value.t != TokenTypes::UInt {
+                return Err(error_at(ErrorType::Parser, "Expected the value of the variant.", 14, value_span)
+                    .with_help("the value must be an unsigned integer, like `FLIGHT = 7;`."));
+            }
+
+            explicit_value = Some((parse_uint(value.value.as_ref().unwrap()).unwrap_or(u64::MAX), value_span));
+
+            token = self.next();
+        }
+
+        if token.t != TokenTypes::SemiColon {
+            return Err(error(ErrorType::Parser, "Expected a semicolon `;`.", 3, token.row, token.col));
+        }
+
+        if self.discard {
+            return Ok(());
+        }
+
+        let enmn = self.ast.package.enums.get(self.in_enum.as_ref().unwrap()).unwrap();
+        if enmn.variants.contains_key(&name) {
+            self.report(error_at(ErrorType::Parser, "Variant name already used.", 6, name_span));
+            return Ok(());
+        }
+
+        // Without an explicit value the variant takes the value of the previous one plus 1, or the next bit for flags
+        let (value, value_span) = match explicit_value {
+            Some(v) => v,
+            None if enmn.flags => (enmn.variants.last().map(|(_, last)| last.value as u64 * 2).unwrap_or(1), name_span),
+            None => (enmn.variants.last().map(|(_, last)| last.value as u64 + 1).unwrap_or(0), name_span)
+        };
+
+        if enmn.flags && !value.is_power_of_two() {
+            self.report(error_at(ErrorType::Parser, "Flag value must be a power of two.", 14, value_span)
+                .with_help("every flag is a single bit, like `ARMED = 0x04;`."));
+            return Ok(());
+        }
+
+        if value > enmn.max_value() as u64 {
+            let help = match explicit_value {
+                Some(_) => format!("the maximum value for `{}` is {}.", enmn.repr.str(), enmn.max_value()),
+                None => format!("`{}` would have value {}, but the maximum for `{}` is {}.", name, value, enmn.repr.str(), enmn.max_value())
+            };
+            self.report(error_at(ErrorType::Parser, "Variant value out of range.", 14, value_span).with_help(help.as_str()));
+            return Ok(());
+        }
+        let value = value as u32;
+
+        if let Some(used_by) = enmn.variants.values().find(|v| v.value == value) {
+            let d = error_at(ErrorType::Parser, format!("Value {} already used by `{}`.", value, used_by.name).as_str(), 15, value_span)
+                .with_help("every variant must have a different value, otherwise they can't be told apart when decoded.");
+            self.report(d);
+            return Ok(());
+        }
+
+        self.ast.package.add_enum_variant(
+            self.in_enum.as_ref().unwrap(), 
+            ast::EnumVariant {
+                name,
+                span: name_span,
+                value
+            }
+        );
+
+        Ok(())
+    }
+
+    /// Add a variant to the current union, `Ignite ignite;` or `Ignite ignite = 4;`.
+    /// The type of the variant is resolved by the semantic analysis.
+    fn union_variant(&mut self) -> Result<(), Diagnostic> {
+        self.entries += 1;
+        let doc = self.take_doc();
+        let (var_type, type_span) = self.type_name();
+
+        if var_type == "string" || PrimitiveTypes::new(&var_type).is_some() {
+            return Err(error_at(ErrorType::Parser, "Union variants must be structs.", 21, type_span)
+                .with_help(format!("declare a struct with a `{}` field and use it as the variant.", var_type).as_str()));
         }
 
         let token = self.next();
-        if token.t == TokenTypes::SemiColon {
-            self.ast.package.add_enum_variant(
-                self.in_enum.as_ref().unwrap(), 
-                ast::EnumVariant {
-                    name,
-                    value: variant_value as u32
-                }
-            );
+        match token.t {
+            TokenTypes::Identifier => {},
+            TokenTypes::OpenSquareBracket | TokenTypes::Colon => return Err(error(ErrorType::Parser, "Union variants can't be arrays or bitfields.", 21, token.row, token.col)
+                .with_help("declare a struct with the array and use it as the variant.")),
+            _ => return Err(error(ErrorType::Parser, "Expected an identifier.", 4, token.row, token.col))
         }
-        else {
-            error(ErrorType::Parser, "Expected a semicolon `;`.", 1, token.row, token.col);
+        let name = token.value.clone().unwrap();
+        let name_span = token.span();
+        let mut explicit_value: Option<(u64, Span)> = None;
+
+        let mut token = self.next();
+        if token.t == TokenTypes::Equal {
+            let value = self.next();
+            let value_span = value.span();
+
+            if value.t != TokenTypes::UInt {
+                return Err(error_at(ErrorType::Parser, "Expected the value of the variant.", 21, value_span)
+                    .with_help("the value must be an unsigned integer, like `Ignite ignite = 7;`."));
+            }
+
+            explicit_value = Some((parse_uint(value.value.as_ref().unwrap()).unwrap_or(u64::MAX), value_span));
+
+            token = self.next();
+        }
+
+        if token.t != TokenTypes::SemiColon {
+            return Err(error(ErrorType::Parser, "Expected a semicolon `;`.", 3, token.row, token.col));
+        }
+
+        if self.discard {
+            return Ok(());
+        }
+
+        let union = self.ast.package.unions.get(self.in_union.as_ref().unwrap()).unwrap();
+        if union.variants.contains_key(&name) {
+            self.report(error_at(ErrorType::Parser, "Variant name already used.", 6, name_span));
+            return Ok(());
+        }
+
+        // The Rust variants are in UpperCamelCase, `a_b` and `aB` would both be `AB`
+        let rust_name = ast::UnionVariant::camel_case(&name);
+        if let Some(other) = union.variants.values().find(|v| ast::UnionVariant::camel_case(&v.name) == rust_name) {
+            self.report(error_at(ErrorType::Parser, format!("Variant `{}` has the same Rust name `{}` as `{}`.", name, rust_name, other.name).as_str(), 6, name_span)
+                .with_help("rename one of them, the names must still differ after removing `_` and capitalizing every word."));
+            return Ok(());
+        }
+
+        // Without an explicit value the variant takes the value of the previous one plus 1
+        let (value, value_span) = match explicit_value {
+            Some(v) => v,
+            None => (union.variants.last().map(|(_, last)| last.value as u64 + 1).unwrap_or(0), name_span)
+        };
+
+        if value > union.max_value() as u64 {
+            let help = match explicit_value {
+                Some(_) => format!("the maximum value for `{}` is {}.", union.repr.str(), union.max_value()),
+                None => format!("`{}` would have value {}, but the maximum for `{}` is {}.", name, value, union.repr.str(), union.max_value())
+            };
+            self.report(error_at(ErrorType::Parser, "Variant value out of range.", 21, value_span).with_help(help.as_str()));
+            return Ok(());
+        }
+        let value = value as u32;
+
+        if let Some(used_by) = union.variants.values().find(|v| v.value == value) {
+            self.report(error_at(ErrorType::Parser, format!("Value {} already used by `{}`.", value, used_by.name).as_str(), 21, value_span)
+                .with_help("every variant must have a different value, otherwise they can't be told apart when decoded."));
+            return Ok(());
+        }
+
+        self.ast.package.add_union_variant(
+            self.in_union.as_ref().unwrap(),
+            ast::UnionVariant {
+                name,
+                span: name_span,
+                t: FieldType::COMPLEX(ComplexTypes::Unknown(var_type)),
+                type_span,
+                value,
+                doc
+            }
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `src` after a version and a package, the parser is returned to look at the AST also when there are errors
+    fn parse(src: &str, max_errors: usize) -> (Parser<'static>, Diagnostics) {
+        let mut parser = Parser::new(format!("version 4.0.0;\npackage Test;\n{}", src));
+        parser.max_errors = max_errors;
+        let diagnostics = parser.generate_ast().err().unwrap_or_default();
+
+        (parser, diagnostics)
+    }
+
+    fn positions(diagnostics: &Diagnostics) -> Vec<(u32, u32, u32)> {
+        diagnostics.iter().map(|d| (d.code, d.span.row, d.span.col)).collect()
+    }
+
+    /// After every kind of error the parser goes on with the next declaration, which is still added to the AST and checked
+    #[test]
+    fn recovers_after_each_error() {
+        let cases = [
+            ("$", 1, 3, 1),
+            ("42;", 2, 3, 1),
+            ("struct S { u8 a }", 3, 3, 17),
+            ("struct { u8 a; }", 4, 3, 8),
+            ("enum { A; }", 4, 3, 6),
+            ("}", 5, 3, 1),
+            ("struct S { u8 a; }\nstruct S { u8 b; }", 6, 4, 8),
+            ("version 4.0.0;", 7, 3, 1),
+            ("package Other;", 8, 3, 1),
+            ("struct S { u8[0] a; }", 9, 3, 15),
+            ("@foo\nstruct S { u8 a; }", 13, 3, 1),
+            ("struct S { u8 a @range(1); }", 13, 3, 17),
+            ("enum E { A = -1; }", 14, 3, 14),
+            ("enum E { A = 1; B = 1; }", 15, 3, 21),
+            ("enum E : f32 { A; }", 16, 3, 10),
+            ("struct S { u8:9 a; }", 17, 3, 15),
+            ("struct S { u8 a = 300; }", 18, 3, 19),
+            ("struct S { u8 a = ; }", 18, 3, 19),
+            ("const u8 A = 300;", 19, 3, 14),
+            ("const f32 true = 9.81;", 19, 3, 11),
+            ("import \"x.iris\";", 20, 3, 8),
+            ("union U : u8 { }", 21, 3, 7),
+            ("enum E : u8 { }", 22, 3, 6)
+        ];
+
+        for (bad, code, row, col) in cases {
+            let (parser, diagnostics) = parse(format!("{}\nstruct After {{ u8 a; u8 a; }}\n", bad).as_str(), 0);
+            let after = 3 + bad.lines().count() as u32;
+
+            assert_eq!(positions(&diagnostics), vec![(code, row, col), (6, after, 25)], "{}", bad);
+            assert_eq!(parser.ast.package.structs.get("After").unwrap().fields.len(), 1, "{}", bad);
         }
     }
-}
\ No newline at end of file
+
+    /// The declarations starting with an identifier also end the recovery, their errors are still found
+    #[test]
+    fn recovers_before_declarations_without_keyword() {
+        let cases = [
+            ("flags F { A; B = 3; }", 14, 3, 21),
+            ("union U { u8 a; }", 21, 3, 14),
+            ("const u8 C = 300;", 19, 3, 17),
+            ("import \"x.iris\";", 20, 3, 11)
+        ];
+
+        for (declaration, code, row, col) in cases {
+            let (_, diagnostics) = parse(format!("42 {}\n", declaration).as_str(), 0);
+            assert_eq!(positions(&diagnostics), vec![(2, 3, 1), (code, row, col)], "{}", declaration);
+        }
+    }
+
+    #[test]
+    fn stops_at_max_errors() {
+        let src = "42;\n43;\n44;\n45;\nstruct S { Unknown u; }\n";
+
+        let (_, diagnostics) = parse(src, 2);
+        assert_eq!(positions(&diagnostics), vec![(2, 3, 1), (2, 4, 1)]);
+        assert!(diagnostics.truncated);
+
+        // The semantic analysis is skipped when the parser stopped early, its errors would come from a partial AST
+        let (_, diagnostics) = parse(src, 4);
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics.truncated);
+
+        let (_, diagnostics) = parse(src, 0);
+        assert_eq!(positions(&diagnostics), vec![(2, 3, 1), (2, 4, 1), (2, 5, 1), (2, 6, 1), (10, 7, 12)]);
+        assert!(!diagnostics.truncated);
+    }
+
+    /// The caret of a rendered error is under the token that caused it
+    #[test]
+    fn rendered_error_points_at_the_token() {
+        let src = "struct Engine {\n    u16 rpm = 70000;\n}\n";
+        let (_, diagnostics) = parse(src, 0);
+
+        let rendered = diagnostics.list[0].render("engine.iris", format!("version 4.0.0;\npackage Test;\n{}", src).as_str());
+        assert!(rendered.contains("4 |     u16 rpm = 70000;\n  |               ^^^^^\n"), "{}", rendered);
+    }
+
+    /// The name of a constant is an identifier that isn't a keyword of a generated language, its span is the one of the name
+    #[test]
+    fn constant_names() {
+        let (parser, diagnostics) = parse("const f32 GRAVITY = 9.81;\nconst u8 GRAVITY = 1;\n", 0);
+        assert_eq!(parser.ast.package.constants.get("GRAVITY").unwrap().span, Span { row: 3, col: 11, len: 7 });
+        assert_eq!(diagnostics.list.iter().map(|d| (d.code, d.span)).collect::<Vec<_>>(), vec![(6, Span { row: 4, col: 10, len: 7 })]);
+
+        for name in ["true", "false", "type", "class", "None", "nullptr"] {
+            let (parser, diagnostics) = parse(format!("const u8 {} = 1;\n", name).as_str(), 0);
+            assert_eq!(diagnostics.list.iter().map(|d| (d.code, d.span)).collect::<Vec<_>>(), vec![(19, Span { row: 3, col: 10, len: name.len() as u32 })], "{}", name);
+            assert!(parser.ast.package.constants.is_empty(), "{}", name);
+        }
+    }
+
+    /// The first variant of an enum is the default value of its fields, flags without any are empty
+    #[test]
+    fn enums_need_a_variant() {
+        let (_, diagnostics) = parse("enum E { }\nstruct S { E e; }\n", 0);
+        assert_eq!(diagnostics.list.iter().map(|d| (d.code, d.span)).collect::<Vec<_>>(), vec![(22, Span { row: 3, col: 6, len: 1 })]);
+
+        let (_, diagnostics) = parse("enum E { A = -1; }\n", 0);
+        assert_eq!(positions(&diagnostics), vec![(14, 3, 14)]);
+
+        let (parser, diagnostics) = parse("flags F { }\nstruct S { F f; }\n", 0);
+        assert!(diagnostics.is_empty());
+        assert!(parser.ast.package.enums.get("F").unwrap().variants.is_empty());
+    }
+
+    /// Two variants of a union can't become the same Rust variant
+    #[test]
+    fn union_variant_names() {
+        let (parser, diagnostics) = parse("struct A { u8 a; }\nunion U { A a_b; A aB; A a__b; A ab; }\n", 0);
+        assert_eq!(positions(&diagnostics), vec![(6, 4, 20), (6, 4, 26)]);
+        assert_eq!(parser.ast.package.unions.get("U").unwrap().variants.keys().collect::<Vec<_>>(), vec!["a_b", "ab"]);
+    }
+
+    /// The errors of the semantic analysis also count for the limit
+    #[test]
+    fn max_errors_includes_semantic_errors() {
+        let (_, diagnostics) = parse("42;\nstruct S { Unknown a; Unknown b; }\n", 2);
+
+        assert_eq!(positions(&diagnostics), vec![(2, 3, 1), (10, 4, 12)]);
+        assert!(diagnostics.truncated);
+    }
+}
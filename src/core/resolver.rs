@@ -0,0 +1,476 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{ast::{ComplexTypes, Enum, FieldType, Limits, Package, Struct, Value}, error::{error_at, Diagnostic, Diagnostics, ErrorType, Span}};
+
+/// Semantic analysis, run after the whole file is parsed.
+/// Resolve the types used by the fields, check that no struct contains itself and that every struct has a different id,
+/// also from the structs of the imported packages. `imports` has the position of every import of the file.
+/// When an import failed, the types of packages that are not imported are not reported, they could be declared by that file.
+pub fn resolve(package: &mut Package, imports: &[(String, Span)], failed_import: bool) -> Result<(), Diagnostics> {
+    let mut diagnostics = Diagnostics::new();
+
+    resolve_types(package, failed_import, &mut diagnostics);
+    check_variant_limits(package, &mut diagnostics);
+    check_variant_defaults(package, &mut diagnostics);
+    check_recursion(package, &mut diagnostics);
+    check_import_ids(package, imports, &mut diagnostics);
+    let imported: Vec<&Package> = package.all_packages().into_iter().filter(|p| !std::ptr::eq(*p, &*package)).collect();
+    check_ids(package, &imported, &mut diagnostics);
+
+    if diagnostics.has_errors() {
+        diagnostics.sort();
+        Err(diagnostics)
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// Replace every `ComplexTypes::Unknown` with the struct, enum or union with the same name,
+/// the types of the imported packages are qualified by the name of the package
+fn resolve_types(package: &mut Package, failed_import: bool, diagnostics: &mut Diagnostics) {
+    let mut structs: HashSet<String> = package.structs.keys().cloned().collect();
+    let mut enums: HashSet<String> = package.enums.keys().cloned().collect();
+    let mut unions: HashSet<String> = package.unions.keys().cloned().collect();
+    for (name, p) in package.imports.iter() {
+        structs.extend(p.structs.keys().map(|s| format!("{}.{}", name, s)));
+        enums.extend(p.enums.keys().map(|e| format!("{}.{}", name, e)));
+        unions.extend(p.unions.keys().map(|u| format!("{}.{}", name, u)));
+    }
+    let imports: HashSet<String> = package.imports.keys().cloned().collect();
+
+    for s in package.structs.values_mut() {
+        for f in s.fields.values_mut() {
+            if let FieldType::COMPLEX(ComplexTypes::Unknown(name)) = &f.t {
+                if structs.contains(name) {
+                    f.t = FieldType::COMPLEX(ComplexTypes::Struct(name.clone()));
+                }
+                else if enums.contains(name) {
+                    f.t = FieldType::COMPLEX(ComplexTypes::Enum(name.clone()));
+                }
+                else if unions.contains(name) {
+                    f.t = FieldType::COMPLEX(ComplexTypes::Union(name.clone()));
+                }
+                else if let Some(d) = unknown_type(name, &imports, failed_import, f.type_span) {
+                    diagnostics.push(d);
+                }
+            }
+        }
+    }
+
+    for u in package.unions.values_mut() {
+        for v in u.variants.values_mut() {
+            if let FieldType::COMPLEX(ComplexTypes::Unknown(name)) = &v.t {
+                if structs.contains(name) {
+                    v.t = FieldType::COMPLEX(ComplexTypes::Struct(name.clone()));
+                }
+                else if enums.contains(name) || unions.contains(name) {
+                    diagnostics.push(error_at(ErrorType::Semantic, "Union variants must be structs.", 21, v.type_span)
+                        .with_help(format!("declare a struct with a `{}` field and use it as the variant.", name).as_str()));
+                }
+                else if let Some(d) = unknown_type(name, &imports, failed_import, v.type_span) {
+                    diagnostics.push(d);
+                }
+            }
+        }
+    }
+}
+
+/// Error for a type that is not declared.
+/// When an import failed, the types of packages that are not imported are not reported.
+fn unknown_type(name: &str, imports: &HashSet<String>, failed_import: bool, span: Span) -> Option<Diagnostic> {
+    let d = error_at(ErrorType::Semantic, format!("Unknown type `{}`.", name).as_str(), 10, span);
+
+    match name.split_once('.') {
+        Some(_) if failed_import => None,
+        Some((package, _)) if !imports.contains(package) => Some(d.with_help(format!("`{}` is not imported, add `import \"file.iris\";` with the file declaring it.", package).as_str())),
+        Some((package, _)) => Some(d.with_help(format!("the package `{}` doesn't declare a struct, enum or union with this name.", package).as_str())),
+        None => Some(d.with_help("use a primitive type or declare a struct, enum or union with this name."))
+    }
+}
+
+/// `@min` and `@max` must be variants of the enum used by the field
+fn check_variant_limits(package: &Package, diagnostics: &mut Diagnostics) {
+    for s in package.structs.values() {
+        for f in s.fields.values() {
+            let Some(Limits::Variants(min, max)) = &f.limits else {
+                continue;
+            };
+            let limits = [("min", min), ("max", max)];
+
+            let enmn = match &f.t {
+                FieldType::COMPLEX(ComplexTypes::Enum(e)) => package.get_enum(e),
+                FieldType::COMPLEX(ComplexTypes::Struct(_) | ComplexTypes::Union(_)) => {
+                    for (name, limit) in limits {
+                        if let Some((_, span)) = limit {
+                            diagnostics.push(error_at(ErrorType::Semantic, format!("Attribute `@{}` can only be used on enum fields.", name).as_str(), 13, *span));
+                        }
+                    }
+                    continue;
+                },
+                // Unknown types are already reported
+                _ => continue
+            };
+
+            if enmn.flags {
+                for (name, limit) in limits {
+                    if let Some((_, span)) = limit {
+                        diagnostics.push(error_at(ErrorType::Semantic, format!("Attribute `@{}` can't be used on flags.", name).as_str(), 13, *span)
+                            .with_help("any combination of flags is valid."));
+                    }
+                }
+                continue;
+            }
+
+            let mut values = Vec::new();
+            for (_, limit) in limits {
+                if let Some((variant, span)) = limit {
+                    match enmn.variants.get(variant) {
+                        Some(v) => values.push(v.value),
+                        None => diagnostics.push(unknown_variant(enmn, variant, 13, *span))
+                    }
+                }
+            }
+
+            if let (Some((_, span)), [min, max]) = (max, values.as_slice()) {
+                if min > max {
+                    diagnostics.push(error_at(ErrorType::Semantic, "Invalid range.", 13, *span)
+                        .with_help(format!("the value of `@max` is {}, smaller than the value of `@min` {}.", max, min).as_str()));
+                }
+            }
+        }
+    }
+}
+
+/// The default value of an enum must be one of its variants, between `@min` and `@max`
+fn check_variant_defaults(package: &Package, diagnostics: &mut Diagnostics) {
+    for s in package.structs.values() {
+        for f in s.fields.values() {
+            let Some((Value::Variant(variant), span)) = &f.default else {
+                continue;
+            };
+
+            let enmn = match &f.t {
+                FieldType::COMPLEX(ComplexTypes::Enum(e)) => package.get_enum(e),
+                FieldType::COMPLEX(ComplexTypes::Struct(_)) => {
+                    diagnostics.push(error_at(ErrorType::Semantic, "Struct fields can't have a default value.", 18, *span)
+                        .with_help("set the default values of the fields of the struct."));
+                    continue;
+                },
+                FieldType::COMPLEX(ComplexTypes::Union(_)) => {
+                    diagnostics.push(error_at(ErrorType::Semantic, "Union fields can't have a default value.", 18, *span)
+                        .with_help("the default is the first variant of the union."));
+                    continue;
+                },
+                _ => continue
+            };
+
+            let Some(value) = enmn.variants.get(variant).map(|v| v.value) else {
+                diagnostics.push(unknown_variant(enmn, variant, 18, *span));
+                continue;
+            };
+
+            if let Some(Limits::Variants(min, max)) = &f.limits {
+                let limit = |l: &Option<(String, _)>| l.as_ref().and_then(|(v, _)| enmn.variants.get(v)).map(|v| v.value);
+                if limit(min).is_some_and(|min| value < min) || limit(max).is_some_and(|max| value > max) {
+                    diagnostics.push(error_at(ErrorType::Semantic, "Default value out of the range.", 18, *span)
+                        .with_help("the default must be between the variants of `@min` and `@max`."));
+                }
+            }
+        }
+    }
+}
+
+fn unknown_variant(enmn: &Enum, variant: &str, code: u32, span: Span) -> Diagnostic {
+    error_at(ErrorType::Semantic, format!("Unknown variant `{}`.", variant).as_str(), code, span)
+        .with_help(format!("the variants of `{}` are {}.", enmn.name, enmn.variants.keys().map(|k| format!("`{}`", k)).collect::<Vec<_>>().join(", ")).as_str())
+}
+
+/// A struct containing itself, directly or through other structs and unions, would have infinite size.
+/// The structs of the imported packages can't contain the ones of this package, because imports can't be circular.
+fn check_recursion(package: &Package, diagnostics: &mut Diagnostics) {
+    let mut done: HashSet<&str> = HashSet::new();
+
+    for s in package.structs.keys() {
+        let mut path = Vec::new();
+        visit(package, s, &mut path, &mut done, diagnostics);
+    }
+}
+
+fn visit<'a>(package: &'a Package, name: &'a str, path: &mut Vec<&'a str>, done: &mut HashSet<&'a str>, diagnostics: &mut Diagnostics) {
+    if done.contains(name) {
+        return;
+    }
+
+    path.push(name);
+
+    for f in package.structs.get(name).unwrap().fields.values() {
+        // A union contains the structs of all its variants
+        let deps: Vec<&'a FieldType> = match &f.t {
+            FieldType::COMPLEX(c @ ComplexTypes::Union(u)) if c.package().is_none() => package.unions.get(u).unwrap().variants.values().map(|v| &v.t).collect(),
+            t => vec![t]
+        };
+
+        for dep in deps {
+            let FieldType::COMPLEX(c @ ComplexTypes::Struct(dep)) = dep else {
+                continue;
+            };
+            if c.package().is_some() {
+                continue;
+            }
+
+            if let Some(start) = path.iter().position(|s| *s == dep.as_str()) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(dep);
+
+                diagnostics.push(error_at(ErrorType::Semantic, format!("Recursive struct `{}`.", dep).as_str(), 11, f.type_span)
+                    .with_help(format!("`{}` would have infinite size, remove one of the fields in {}.", dep, cycle.join(" -> ")).as_str()));
+            }
+            else {
+                visit(package, dep, path, done, diagnostics);
+            }
+        }
+    }
+
+    path.pop();
+    done.insert(name);
+}
+
+/// Check that the structs of `package` don't have the same id of other structs in the package or in `others`,
+/// the decoder could not tell them apart.
+pub fn check_id_collisions(package: &Package, others: &[&Package]) -> Result<(), Diagnostics> {
+    let mut diagnostics = Diagnostics::new();
+
+    check_ids(package, others, &mut diagnostics);
+
+    if diagnostics.has_errors() {
+        Err(diagnostics)
+    }
+    else {
+        Ok(())
+    }
+}
+
+fn check_ids(package: &Package, others: &[&Package], diagnostics: &mut Diagnostics) {
+    let mut ids: HashMap<u32, String> = HashMap::new();
+
+    for other in others {
+        for s in other.structs.values() {
+            ids.entry(s.id()).or_insert(format!("{}.{}", other.name.as_ref().unwrap(), s.name));
+        }
+    }
+
+    for s in package.structs.values() {
+        match ids.get(&s.id()) {
+            Some(used_by) => diagnostics.push(id_collision(s, used_by)),
+            None => {
+                ids.insert(s.id(), s.name.clone());
+            }
+        }
+    }
+}
+
+/// Check that the structs of different imported packages have different ids.
+/// The packages imported by an imported file are already checked with it, a collision between two imports is shown on the second one.
+fn check_import_ids(package: &Package, imports: &[(String, Span)], diagnostics: &mut Diagnostics) {
+    let mut ids: HashMap<u32, String> = HashMap::new();
+    // The same package can be imported by more files
+    let mut checked: HashSet<&str> = HashSet::new();
+
+    for (name, span) in imports {
+        for p in package.imports.get(name).unwrap().all_packages() {
+            let p_name = p.name.as_ref().unwrap();
+            if !checked.insert(p_name) {
+                continue;
+            }
+
+            for s in p.structs.values() {
+                let qualified = format!("{}.{}", p_name, s.name);
+                match ids.get(&s.id()) {
+                    Some(used_by) => diagnostics.push(
+                        error_at(ErrorType::Semantic, format!("Struct `{}` has the same id {:#010X} of `{}`.", qualified, s.id(), used_by).as_str(), 12, *span)
+                            .with_help("the structs of the imported packages must have different ids, change `@id` or rename one of them.")
+                    ),
+                    None => {
+                        ids.insert(s.id(), qualified);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn id_collision(s: &Struct, used_by: &str) -> Diagnostic {
+    let d = error_at(ErrorType::Semantic, format!("Struct `{}` has the same id {:#010X} of `{}`.", s.name, s.id(), used_by).as_str(), 12, s.span);
+
+    if s.explicit_id.is_some() {
+        d.with_help("choose a different value for `@id`.")
+    }
+    else {
+        d.with_help("rename one of the structs or give this one a different id with `@id(...)`.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::parser::Parser;
+
+    fn package(src: &str) -> Package {
+        let mut parser = Parser::new(src.to_string());
+        // The errors are found again by `resolve`, which only changes the types that are still unknown
+        parser.generate_ast().ok();
+        parser.ast.package
+    }
+
+    /// Run the semantic analysis on `src` (after a version and a package) importing the packages of `imports`,
+    /// the position of every import is the line of the package.
+    fn check_with(src: &str, imports: &[&str], failed_import: bool) -> Vec<(u32, u32, u32, String)> {
+        let mut root = package(format!("version 4.0.0;\npackage Test;\n{}", src).as_str());
+        let mut positions = Vec::new();
+        for (i, import) in imports.iter().enumerate() {
+            let p = package(import);
+            let name = p.name.clone().unwrap();
+            positions.push((name.clone(), Span { row: 2, col: 1 + i as u32, len: 1 }));
+            root.imports.insert(name, p);
+        }
+
+        match resolve(&mut root, &positions, failed_import) {
+            Ok(()) => Vec::new(),
+            Err(d) => d.iter().map(|d| (d.code, d.span.row, d.span.col, d.help.clone().unwrap_or_default())).collect()
+        }
+    }
+
+    fn check(src: &str) -> Vec<(u32, u32, u32)> {
+        check_with(src, &[], false).into_iter().map(|(code, row, col, _)| (code, row, col)).collect()
+    }
+
+    fn helps(src: &str) -> Vec<String> {
+        check_with(src, &[], false).into_iter().map(|(_, _, _, help)| help).collect()
+    }
+
+    const GNSS: &str = "version 4.0.0;\npackage Gnss;\n@id(0x10)\nstruct Fix { u8 sats; }\nenum Mode { NO_FIX; FIX; }\n";
+    const IMU: &str = "version 4.0.0;\npackage Imu;\n@id(0x10)\nstruct Sample { i16 x; }\n";
+
+    #[test]
+    fn resolves_declared_types() {
+        let src = "enum Mode { IDLE; }\nflags Valves { MAIN; }\nstruct Ignite { u8 delay; }\nunion Payload { Ignite ignite; }\nstruct Engine { Mode mode; Valves valves; Payload payload; Ignite[2] ignite; Gnss.Fix fix; Gnss.Mode gnss; }\n";
+        let mut root = package(format!("version 4.0.0;\npackage Test;\n{}", src).as_str());
+        root.imports.insert(String::from("Gnss"), package(GNSS));
+
+        assert!(resolve(&mut root, &[], false).is_ok());
+        let types: Vec<String> = root.structs.get("Engine").unwrap().fields.values().map(|f| format!("{:?}", f.t)).collect();
+        assert_eq!(types, [
+            "COMPLEX(Enum(\"Mode\"))",
+            "COMPLEX(Enum(\"Valves\"))",
+            "COMPLEX(Union(\"Payload\"))",
+            "COMPLEX(Struct(\"Ignite\"))",
+            "COMPLEX(Struct(\"Gnss.Fix\"))",
+            "COMPLEX(Enum(\"Gnss.Mode\"))"
+        ]);
+    }
+
+    #[test]
+    fn unknown_types() {
+        let src = "struct Engine { Pump pump; }\nunion Payload { Abort abort; }\n";
+
+        assert_eq!(check(src), [(10, 3, 17), (10, 4, 17)]);
+        assert!(helps(src)[0].starts_with("use a primitive type"));
+    }
+
+    #[test]
+    fn unknown_imported_types() {
+        let src = "struct Engine { Gnss.Time time; Imu.Sample sample; }\n";
+        let errors = check_with(src, &[GNSS], false);
+
+        assert_eq!(errors.iter().map(|e| (e.0, e.1, e.2)).collect::<Vec<_>>(), [(10, 3, 17), (10, 3, 33)]);
+        assert!(errors[0].3.starts_with("the package `Gnss` doesn't declare"), "{}", errors[0].3);
+        assert!(errors[1].3.starts_with("`Imu` is not imported"), "{}", errors[1].3);
+    }
+
+    /// The types of a package that could not be imported are not reported, the local ones are
+    #[test]
+    fn unknown_types_after_failed_import() {
+        let src = "struct Engine { Gnss.Fix fix; Pump pump; }\n";
+
+        assert_eq!(check_with(src, &[], true).iter().map(|e| (e.0, e.1, e.2)).collect::<Vec<_>>(), [(10, 3, 31)]);
+    }
+
+    #[test]
+    fn union_variants_must_be_structs() {
+        let src = "enum Mode { IDLE; }\nstruct Ignite { u8 delay; }\nunion Inner { Ignite ignite; }\nunion Payload { Mode mode; Inner inner; }\n";
+
+        assert_eq!(check(src), [(21, 6, 17), (21, 6, 28)]);
+        assert!(helps(src)[0].contains("a `Mode` field"));
+    }
+
+    #[test]
+    fn recursive_structs() {
+        // Direct, through another struct and through a union
+        let src = "struct A { A a; }\nstruct B { C c; }\nstruct C { B[2] b; }\nstruct D { Payload p; }\nunion Payload { D d; }\n";
+
+        assert_eq!(check(src), [(11, 3, 12), (11, 5, 12), (11, 6, 12)]);
+        assert_eq!(helps(src)[1], "`B` would have infinite size, remove one of the fields in B -> C -> B.");
+        assert_eq!(helps(src)[2], "`D` would have infinite size, remove one of the fields in D -> D.");
+    }
+
+    /// A struct used by more fields is not a cycle
+    #[test]
+    fn shared_structs_are_not_recursive() {
+        assert_eq!(check("struct Fix { u8 sats; }\nstruct A { Fix a; Fix b; }\nstruct B { A a; Fix[2] fix; }\n"), []);
+    }
+
+    #[test]
+    fn variant_limits() {
+        let src = concat!(
+            "enum Mode { IDLE; ARMED; FIRING; }\nflags Valves { MAIN; VENT; }\nstruct Fix { u8 sats; }\n",
+            "struct S { Fix fix @min(IDLE); Valves v @max(VENT); Mode a @min(OFF); Mode b @min(FIRING) @max(ARMED); Mode c @min(ARMED) @max(FIRING); }\n"
+        );
+
+        assert_eq!(check(src), [(13, 6, 20), (13, 6, 41), (13, 6, 60), (13, 6, 91)]);
+        assert_eq!(helps(src)[1], "any combination of flags is valid.");
+        assert_eq!(helps(src)[2], "the variants of `Mode` are `IDLE`, `ARMED`, `FIRING`.");
+        assert_eq!(helps(src)[3], "the value of `@max` is 1, smaller than the value of `@min` 2.");
+    }
+
+    #[test]
+    fn variant_defaults() {
+        let src = concat!(
+            "enum Mode { IDLE; ARMED; FIRING; }\nstruct Fix { u8 sats; }\nstruct Ignite { u8 delay; }\nunion Payload { Ignite ignite; }\n",
+            "struct S { Fix fix = IDLE; Payload p = ignite; Mode a = OFF; Mode b = IDLE @min(ARMED); Mode c = ARMED @min(ARMED) @max(FIRING); }\n"
+        );
+
+        assert_eq!(check(src), [(18, 7, 22), (18, 7, 40), (18, 7, 57), (18, 7, 71)]);
+        assert!(helps(src)[0].starts_with("set the default values"));
+        assert!(helps(src)[1].starts_with("the default is the first variant"));
+        assert!(helps(src)[2].starts_with("the variants of `Mode`"));
+        assert!(helps(src)[3].starts_with("the default must be between"));
+    }
+
+    #[test]
+    fn id_collisions() {
+        let src = "struct A { u8 a; }\n@id(0x20)\nstruct B { u8 b; }\n@id(0x20)\nstruct C { u8 c; }\n";
+
+        assert_eq!(check(src), [(12, 7, 8)]);
+        assert_eq!(helps(src)[0], "choose a different value for `@id`.");
+    }
+
+    #[test]
+    fn id_collisions_with_imports() {
+        let errors = check_with("@id(0x10)\nstruct Engine { u8 rpm; }\n", &[GNSS], false);
+        assert_eq!(errors.iter().map(|e| (e.0, e.1, e.2)).collect::<Vec<_>>(), [(12, 4, 8)]);
+
+        // Shown on the second import
+        let errors = check_with("struct Engine { u8 rpm; }\n", &[GNSS, IMU], false);
+        assert_eq!(errors.iter().map(|e| (e.0, e.1, e.2)).collect::<Vec<_>>(), [(12, 2, 2)]);
+    }
+
+    #[test]
+    fn check_id_collisions_with_other_packages() {
+        let root = package("version 4.0.0;\npackage Test;\n@id(0x10)\nstruct Engine { u8 rpm; }\n");
+
+        assert!(check_id_collisions(&root, &[]).is_ok());
+        let d = check_id_collisions(&root, &[&package(GNSS)]).unwrap_err();
+        assert_eq!(d.list[0].message, "Struct `Engine` has the same id 0x00000010 of `Gnss.Fix`.");
+    }
+}
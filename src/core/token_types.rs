@@ -6,10 +6,20 @@ pub enum TokenTypes {
     CloseSquareBracket,
     OpenCurlyBracket,
     CloseCurlyBracket,
+    OpenParenthesis,
+    CloseParenthesis,
+    Comma,
+    At,
+    Equal,
+    Minus,
+    Dot,
+    LessEqual,
 
     Identifier,
     UInt,
     SemanticVersion,
+    String,
+    DocComment,
 
     Version,
     Struct,
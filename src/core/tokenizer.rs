@@ -1,8 +1,7 @@
-use std::collections::HashSet;
-
-use super::{error::{error, ErrorType}, token_types::TokenTypes};
+use super::{error::{error, Diagnostic, Diagnostics, ErrorType, Span}, token_types::TokenTypes};
 
 
+#[derive(Debug, Clone)]
 pub struct Token {
     pub t: TokenTypes,
     pub value: Option<String>,
@@ -10,11 +9,20 @@ pub struct Token {
     pub col: u32
 }
 
+impl Token {
+    /// Position of the token in the source file
+    pub fn span(&self) -> Span {
+        Span {
+            row: self.row,
+            col: self.col,
+            len: self.value.as_ref().map_or(1, |v| v.chars().count() as u32)
+        }
+    }
+}
+
 pub struct Tokenizer {
-    src: String,
+    src: Vec<char>,
     pub tokens: Vec<Token>,
-    pub structs: HashSet<String>,
-    pub enums: HashSet<String>,
     row: u32,
     col: u32,
     pos: u32,
@@ -25,14 +33,12 @@ impl Tokenizer {
     /// Constructor
     pub fn new(src: String) -> Tokenizer {
         Tokenizer { 
-            src: src.clone(),
+            src: src.chars().collect(),
             tokens: Vec::new(),
-            structs: HashSet::new(),
-            enums: HashSet::new(),
             row: 1,
             col: 1,
             pos: 0,
-            current_c: Some(src.chars().nth(0).unwrap())
+            current_c: src.chars().next()
         }
     }
 
@@ -43,64 +49,120 @@ impl Tokenizer {
         }
     }*/
 
-    /// Divide the source file into tokens and save them in a vector
-    pub fn tokenize(&mut self) {
-        let mut token = self.get_next_token();
-        self.tokens.push(token);
+    /// Divide the source file into tokens and save them in a vector.
+    /// Unknown characters are reported and skipped, so that the parser can still run on the rest of the file.
+    pub fn tokenize(&mut self) -> Result<(), Diagnostics> {
+        let mut diagnostics = Diagnostics::new();
+
+        while self.tokens.last().is_none_or(|t| t.t != TokenTypes::EndOfStream) {
+            match self.get_next_token() {
+                Ok(token) => self.tokens.push(token),
+                Err(d) => {
+                    diagnostics.push(d);
+                    self.advance();
+                }
+            }
+        }
 
-        while self.tokens.last().unwrap().t != TokenTypes::EndOfStream {
-            token = self.get_next_token();
-            self.tokens.push(token);
+        if diagnostics.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(diagnostics)
         }
     }
 
     /// Get the next token in the input
-    pub fn get_next_token(&mut self) -> Token {
+    pub fn get_next_token(&mut self) -> Result<Token, Diagnostic> {
         while let Some(c) = self.current_c {
             if c.is_ascii_whitespace() {
                 self.skip_whitespace();
                 continue;
             }
             if c == '#' {
+                let (row, col) = (self.row, self.col);
                 self.advance();
+                // `##` starts a doc comment, `#` and `###...` are normal comments
+                if self.current_c == Some('#') && self.src.get(self.pos as usize + 1) != Some(&'#') {
+                    self.advance();
+                    return Ok(self.doc_comment(row, col));
+                }
                 self.skip_comment();
                 continue;
             }
+            if c == '"' {
+                return self.string();
+            }
             if c.is_ascii_alphabetic() {
-                return self.id();
+                return Ok(self.id());
             }
             if c.is_ascii_digit() {
-                return self.number();
+                return Ok(self.number());
             }
-            if c  == ':' {
+
+            let (row, col) = (self.row, self.col);
+            if c == ':' {
                 self.advance();
-                return Token { t: TokenTypes::Colon, value: None, row: self.row, col: self.col };
+                return Ok(Token { t: TokenTypes::Colon, value: None, row, col });
             }
             if c == ';' {
                 self.advance();
-                return Token { t: TokenTypes::SemiColon, value: None, row: self.row, col: self.col };
+                return Ok(Token { t: TokenTypes::SemiColon, value: None, row, col });
             }
             if c == '[' {
                 self.advance();
-                return Token { t: TokenTypes::OpenSquareBracket, value: None, row: self.row, col: self.col };
+                return Ok(Token { t: TokenTypes::OpenSquareBracket, value: None, row, col });
             }
             if c == ']' {
                 self.advance();
-                return Token { t: TokenTypes::CloseSquareBracket, value: None, row: self.row, col: self.col };
+                return Ok(Token { t: TokenTypes::CloseSquareBracket, value: None, row, col });
             }
             if c == '{' {
                 self.advance();
-                return Token { t: TokenTypes::OpenCurlyBracket, value: None, row: self.row, col: self.col };
+                return Ok(Token { t: TokenTypes::OpenCurlyBracket, value: None, row, col });
             }
             if c == '}' {
                 self.advance();
-                return Token { t: TokenTypes::CloseCurlyBracket, value: None, row: self.row, col: self.col };
+                return Ok(Token { t: TokenTypes::CloseCurlyBracket, value: None, row, col });
+            }
+            if c == '(' {
+                self.advance();
+                return Ok(Token { t: TokenTypes::OpenParenthesis, value: None, row, col });
+            }
+            if c == ')' {
+                self.advance();
+                return Ok(Token { t: TokenTypes::CloseParenthesis, value: None, row, col });
+            }
+            if c == ',' {
+                self.advance();
+                return Ok(Token { t: TokenTypes::Comma, value: None, row, col });
+            }
+            if c == '@' {
+                self.advance();
+                return Ok(Token { t: TokenTypes::At, value: None, row, col });
+            }
+            if c == '=' {
+                self.advance();
+                return Ok(Token { t: TokenTypes::Equal, value: None, row, col });
+            }
+            if c == '-' {
+                self.advance();
+                return Ok(Token { t: TokenTypes::Minus, value: None, row, col });
+            }
+            if c == '.' {
+                self.advance();
+                return Ok(Token { t: TokenTypes::Dot, value: None, row, col });
+            }
+            if c == '<' && self.src.get(self.pos as usize + 1) == Some(&'=') {
+                self.advance();
+                self.advance();
+                return Ok(Token { t: TokenTypes::LessEqual, value: None, row, col });
             }
             
-            error(ErrorType::Tokenizer, "Syntax error, unknown token.", 1, self.row, self.col);
+            return Err(error(ErrorType::Tokenizer, format!("Syntax error, unknown token `{}`.", c).as_str(), 1, row, col));
         }
 
-        return Token { t: TokenTypes::EndOfStream, value: None, row: self.row, col: self.col };
+        Ok(Token { t: TokenTypes::EndOfStream, value: None, row: self.row, col: self.col })
     }
 
     /// Advance to the next character and set the current character 
@@ -111,11 +173,8 @@ impl Tokenizer {
         }
 
         self.pos += 1;
-        if self.pos >= self.src.len() as u32 {
-            self.current_c = None;
-        }
-        else {
-            self.current_c = Some(self.src.chars().nth(self.pos as usize).unwrap());
+        self.current_c = self.src.get(self.pos as usize).copied();
+        if self.current_c.is_some() {
             self.col += 1;
         }
     }
@@ -128,43 +187,93 @@ impl Tokenizer {
 
     /// Skip whitespaces until the next token
     fn skip_whitespace(&mut self) {
-        while self.current_c.unwrap().is_ascii_whitespace() {
+        while self.current_c.unwrap_or_default().is_ascii_whitespace() {
             self.advance();
         }
     }
 
     /// Skip the comment
     fn skip_comment(&mut self) {
-        while self.current_c.unwrap_or_default() != '\n' {
+        while self.current_c.is_some_and(|c| c != '\n') {
             self.advance();
         }
     }
 
-    /// Return an unsigned integer or semantic version token consumed from the input
+    /// Return the text of a doc comment, without `##` and the first space
+    fn doc_comment(&mut self, row: u32, col: u32) -> Token {
+        let mut buf = String::new();
+        while self.current_c.is_some_and(|c| c != '\n') {
+            buf.push(self.current_c.unwrap());
+            self.advance();
+        }
+
+        let text = buf.strip_prefix(' ').unwrap_or(&buf).trim_end().to_string();
+        Token { t: TokenTypes::DocComment, value: Some(text), row, col }
+    }
+
+    /// Return a string token, the value is the text between the double quotes
+    fn string(&mut self) -> Result<Token, Diagnostic> {
+        let (row, col) = (self.row, self.col);
+        self.advance();
+
+        let mut buf = String::new();
+        while self.current_c.is_some_and(|c| c != '"' && c != '\n') {
+            buf.push(self.current_c.unwrap());
+            self.advance();
+        }
+
+        if self.current_c != Some('"') {
+            return Err(error(ErrorType::Tokenizer, "String not closed.", 1, row, col)
+                .with_help("add `\"` at the end of the string, strings can't span multiple lines."));
+        }
+        self.advance();
+
+        Ok(Token { t: TokenTypes::String, value: Some(buf), row, col })
+    }
+
+    /// Return an unsigned integer (decimal or hexadecimal) or semantic version token consumed from the input
     fn number(&mut self) -> Token {
         let num_row = self.row;
         let num_col = self.col;
 
         let mut buf = String::new();
-        while self.current_c.unwrap().is_ascii_digit() {
+        if self.current_c == Some('0') && self.src.get(self.pos as usize + 1).is_some_and(|c| *c == 'x' || *c == 'X') {
+            buf.push_str("0x");
+            self.advance();
+            self.advance();
+
+            while self.current_c.unwrap_or_default().is_ascii_hexdigit() {
+                buf.push(self.current_c.unwrap());
+                self.advance();
+            }
+
+            return Token {
+                t: TokenTypes::UInt,
+                value: Some(buf),
+                row: num_row,
+                col: num_col
+            };
+        }
+
+        while self.current_c.unwrap_or_default().is_ascii_digit() {
             buf.push(self.current_c.unwrap());
             self.advance();
         }
 
-        if self.current_c.unwrap() == '.' {
+        if self.current_c == Some('.') {
             buf.push(self.current_c.unwrap());
             self.advance();
 
-            while self.current_c.unwrap().is_ascii_digit() {
+            while self.current_c.unwrap_or_default().is_ascii_digit() {
                 buf.push(self.current_c.unwrap());
                 self.advance();
             }
 
-            if self.current_c.unwrap() == '.' {
+            if self.current_c == Some('.') {
                 buf.push(self.current_c.unwrap());
                 self.advance();
 
-                while self.current_c.unwrap().is_ascii_digit() {
+                while self.current_c.unwrap_or_default().is_ascii_digit() {
                     buf.push(self.current_c.unwrap());
                     self.advance();
                 }
@@ -177,13 +286,13 @@ impl Tokenizer {
                 col: num_col
             };
         }
-        
-        return Token {
+
+        Token {
             t: TokenTypes::UInt,
             value: Some(buf),
             row: num_row,
             col: num_col
-        };
+        }
     }
 
     /// Return an identifier or keyword token
@@ -192,7 +301,7 @@ impl Tokenizer {
         let num_col = self.col;
 
         let mut buf = String::new();
-        while self.current_c.unwrap_or_default().is_ascii_alphanumeric() {
+        while self.current_c.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
             buf.push(self.current_c.unwrap());
             self.advance();
         }
@@ -202,18 +311,7 @@ impl Tokenizer {
             "package" => Token { t: TokenTypes::Package, value: None, row: num_row, col: num_col },
             "struct" => Token { t: TokenTypes::Struct, value: None, row: num_row, col: num_col },
             "enum" => Token { t: TokenTypes::Enum, value: None, row: num_row, col: num_col },
-            _ => {
-                if self.tokens.len() > 0 {
-                    if self.tokens.last().unwrap().t == TokenTypes::Struct {
-                        self.structs.insert(buf.clone());
-                    }
-                    else if self.tokens.last().unwrap().t == TokenTypes::Enum {
-                        self.enums.insert(buf.clone());
-                    }
-                }
-
-                Token { t: TokenTypes::Identifier, value: Some(buf), row: num_row, col: num_col }
-            }
+            _ => Token { t: TokenTypes::Identifier, value: Some(buf), row: num_row, col: num_col }
         }
     }
 }
\ No newline at end of file
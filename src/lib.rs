@@ -0,0 +1,69 @@
+//! iris compiler.
+//!
+//! Parse a .iris file and generate the code to encode and decode its structs:
+//! ```no_run
+//! use iris::{CodeGen, Langs};
+//!
+//! let src = std::fs::read_to_string("telemetry.iris").unwrap();
+//! let ast = match iris::parse(&src) {
+//!     Ok(ast) => ast,
+//!     Err(diagnostics) => panic!("{}", diagnostics.render("telemetry.iris", &src))
+//! };
+//!
+//! let code = Langs::from_string("rust").unwrap().gen_code(&ast.package);
+//! ```
+#![allow(clippy::upper_case_acronyms)]
+
+mod core;
+
+use std::path::{Path, PathBuf};
+
+pub use crate::core::ast;
+pub use crate::core::build;
+pub use crate::core::compat;
+pub use crate::core::kronos_code_gen;
+pub use crate::core::error::{Diagnostic, Diagnostics, ErrorType, FileDiagnostics, Severity, Span};
+pub use crate::core::generators::code_gen::{CodeGen, Langs};
+pub use crate::core::generators::{cpp::CPP, python::Python, rust::Rust};
+pub use crate::core::parser::DEFAULT_MAX_ERRORS;
+pub use crate::core::resolver::check_id_collisions;
+
+/// Parse the content of a .iris file, reporting up to `DEFAULT_MAX_ERRORS` errors.
+pub fn parse(src: &str) -> Result<ast::AST, Diagnostics> {
+    parse_with_max_errors(src, DEFAULT_MAX_ERRORS)
+}
+
+/// Parse the content of a .iris file, reporting up to `max_errors` errors (0 means no limit).
+pub fn parse_with_max_errors(src: &str, max_errors: usize) -> Result<ast::AST, Diagnostics> {
+    let mut parser = core::parser::Parser::new(src.to_string());
+    parser.max_errors = max_errors;
+    parser.generate_ast()?;
+
+    Ok(parser.ast)
+}
+
+/// Parse the content of the .iris file at `path` and the files it imports, reporting up to `max_errors` errors for every file.
+/// Imported files are searched from the directory of the importing file, then from every directory of `include_dirs`.
+/// The errors of the imported files come before the ones of this file.
+pub fn parse_with_imports(path: &Path, src: &str, include_dirs: &[PathBuf], max_errors: usize) -> Result<ast::AST, Vec<FileDiagnostics>> {
+    let mut loader = core::loader::Loader::new(path, include_dirs, max_errors);
+    let (res, ast) = {
+        let mut parser = core::parser::Parser::new(src.to_string());
+        parser.max_errors = max_errors;
+        parser.loader = Some(&mut loader);
+        let res = parser.generate_ast();
+        (res, parser.ast)
+    };
+
+    let mut errors = loader.diagnostics;
+    if let Err(diagnostics) = res {
+        errors.push(FileDiagnostics { path: path.display().to_string(), src: src.to_string(), diagnostics });
+    }
+
+    if errors.is_empty() {
+        Ok(ast::AST { imports: loader.files, ..ast })
+    }
+    else {
+        Err(errors)
+    }
+}
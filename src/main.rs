@@ -1,16 +1,18 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-use core::{generators::code_gen::{CodeGen, Langs}, parser, kronos_code_gen};
-use std::{collections::HashMap, fs, io::Write, path::Path};
-
-mod core;
+use iris::{ast, compat, kronos_code_gen, CodeGen, Langs};
+use std::{fs, io::Write, path::{Path, PathBuf}, process::ExitCode};
 
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// .iris file to use for code generation
-    src: String,
+    #[arg(required = true)]
+    src: Option<String>,
 
     /// Directory to use for code generation output.
     #[arg(short, long, default_value_t = String::from("."))]
@@ -20,36 +22,109 @@ struct Args {
     #[arg(short, long, default_value_t = String::from("rust"))]
     lang: String,
 
+    /// Directory where the imported files are searched, after the directory of the importing file. Can be repeated.
+    #[arg(short = 'I', long = "include", global = true)]
+    include: Vec<String>,
+
+    /// Stop after this many errors, 0 to report all of them.
+    #[arg(long, default_value_t = iris::DEFAULT_MAX_ERRORS, global = true)]
+    max_errors: usize,
+
     /// If set, a kronos-code-gen.json file will be created in the same directory as the .iris file
     /// You can ignore this flag, it only exists because is needed by kronos: https://github.com/PoliBa-Space-Program/kronos
     #[arg(short, long)]
     kronos_code_gen: bool
 }
 
-fn main() {
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Report the changes of a schema that break the messages encoded with an older version of it
+    Compat {
+        /// Previous version of the .iris file
+        old: String,
+        /// New version of the .iris file
+        new: String
+    }
+}
+
+/// Read and parse a .iris file with its imports, the errors are printed
+fn load(path: &str, include_dirs: &[PathBuf], max_errors: usize) -> Option<(String, ast::AST)> {
+    let src = match fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("error: can't read `{}`: {}", path, e);
+            return None;
+        }
+    };
+
+    match iris::parse_with_imports(Path::new(path), &src, include_dirs, max_errors) {
+        Ok(ast) => Some((src, ast)),
+        Err(files) => {
+            for f in files {
+                eprint!("{}", f.render());
+            }
+            None
+        }
+    }
+}
+
+/// Compare two versions of a schema, the exit code is non-zero if there are breaking changes
+fn compat(old_path: &str, new_path: &str, include_dirs: &[PathBuf], max_errors: usize) -> ExitCode {
+    let (Some((old_src, old)), Some((new_src, new))) = (load(old_path, include_dirs, max_errors), load(new_path, include_dirs, max_errors)) else {
+        return ExitCode::FAILURE;
+    };
+
+    let report = compat::check(&old.package, &new.package);
+    if report.is_empty() {
+        println!("`{}` is compatible with `{}`.", new_path, old_path);
+        return ExitCode::SUCCESS;
+    }
+
+    for d in &report.old {
+        eprintln!("{}", d.render(old_path, &old_src));
+    }
+    for d in &report.new {
+        eprintln!("{}", d.render(new_path, &new_src));
+    }
+    eprintln!(
+        "error: `{}` is not compatible with `{}`, {} breaking change{}",
+        new_path,
+        old_path,
+        report.len(),
+        if report.len() == 1 { "" } else { "s" }
+    );
+
+    ExitCode::FAILURE
+}
+
+fn main() -> ExitCode {
     let args = Args::parse();
+    let include_dirs: Vec<PathBuf> = args.include.iter().map(PathBuf::from).collect();
+
+    if let Some(Command::Compat { old, new }) = &args.command {
+        return compat(old, new, &include_dirs, args.max_errors);
+    }
 
-    let src = fs::read_to_string(args.src).unwrap();
-    let mut parser = parser::Parser::new(src);
-    parser.generate_ast();
+    let path = args.src.unwrap();
+    let Some(lang) = Langs::from_string(&args.lang) else {
+        eprintln!("error: language `{}` is not supported, use one of: {}.", args.lang, Langs::NAMES.join(", "));
+        return ExitCode::FAILURE;
+    };
+
+    let Some((_, ast)) = load(&path, &include_dirs, args.max_errors) else {
+        return ExitCode::FAILURE;
+    };
 
-    let lang = Langs::from_string(&args.lang);
     let mut out = fs::File::create(
         Path::new(&args.out).join(format!("iris.{}", lang.ext()))
     ).unwrap();
 
-    out.write_all(match lang {
-        Langs::RUST(l) => l.gen_code(&parser.ast.package),
-        Langs::PYTHON(l) => l.gen_code(&parser.ast.package),
-        Langs::CPP(l) => l.gen_code(&parser.ast.package)
-    }.as_bytes()).unwrap();
+    out.write_all(lang.gen_code(&ast.package).as_bytes()).unwrap();
 
     if args.kronos_code_gen {
-        let mut s = kronos_code_gen::KronosCodeGen {
-            package: String::new(),
-            data: HashMap::new(),
-            size: HashMap::new()
-        };
-        s.generate(args.out, &parser.ast);
+        let mut s = kronos_code_gen::KronosCodeGen::new();
+        s.generate(args.out, &ast).unwrap();
     }
+
+    ExitCode::SUCCESS
 }
\ No newline at end of file
@@ -0,0 +1,46 @@
+use std::{env, fs, io::Write, path::Path, process::{Command, Stdio}};
+
+use iris::{kronos_code_gen::KronosCodeGen, CodeGen, Langs};
+
+/// Command regenerating the examples, shown when one of them is stale
+const REGENERATE: &str = "for l in rust python cpp; do iris examples/telemetry.iris -o <dir> -l $l -k; done, \
+    then `rustfmt --edition 2021` the Rust code and copy the files to examples/";
+
+fn rustfmt(code: &str) -> String {
+    let mut child = Command::new(env::var("RUSTFMT").unwrap_or_else(|_| String::from("rustfmt")))
+        .args(["--edition", "2021"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(code.as_bytes()).unwrap();
+
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    String::from_utf8(out.stdout).unwrap()
+}
+
+/// The generated files of the examples are the output of the current generators
+#[test]
+fn examples_are_up_to_date() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let src = fs::read_to_string(examples.join("telemetry.iris")).unwrap();
+    let ast = iris::parse(&src).unwrap_or_else(|d| panic!("{}", d.render("telemetry.iris", &src)));
+
+    for (lang, file) in [("rust", "rust/iris.rs"), ("python", "python/iris.py"), ("cpp", "cpp/iris.hpp")] {
+        let mut code = Langs::from_string(lang).unwrap().gen_code(&ast.package);
+        if lang == "rust" {
+            code = rustfmt(&code);
+        }
+
+        assert!(fs::read_to_string(examples.join(file)).unwrap() == code, "examples/{} is stale, regenerate it with: {}", file, REGENERATE);
+    }
+
+    let dir = env::temp_dir().join(format!("iris-examples-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    KronosCodeGen::new().generate(dir.to_string_lossy().to_string(), &ast).unwrap();
+    let kronos = fs::read_to_string(dir.join("kronos-code-gen.json")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(fs::read_to_string(examples.join("rust/kronos-code-gen.json")).unwrap() == kronos, "examples/rust/kronos-code-gen.json is stale, regenerate it with: {}", REGENERATE);
+}
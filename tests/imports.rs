@@ -0,0 +1,110 @@
+use std::{env, fs, path::PathBuf};
+
+/// Write the files in a new temporary directory named after the test
+fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = env::temp_dir().join(format!("iris-imports-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, src) in files {
+        fs::write(dir.join(name), src).unwrap();
+    }
+
+    dir
+}
+
+/// Parse `main.iris` of the directory, returning the diagnostics of every file as `(path, code, row)`
+fn parse_main(dir: &PathBuf) -> Vec<(String, u32, u32)> {
+    let path = dir.join("main.iris");
+    let src = fs::read_to_string(&path).unwrap();
+    let res = iris::parse_with_imports(&path, &src, &[], iris::DEFAULT_MAX_ERRORS);
+    fs::remove_dir_all(dir).unwrap();
+
+    match res {
+        Ok(_) => Vec::new(),
+        Err(files) => files.iter()
+            .flat_map(|f| f.diagnostics.list.iter().map(move |d| (f.path.clone(), d.code, d.span.row)))
+            .map(|(path, code, row)| (PathBuf::from(path).file_name().unwrap().to_string_lossy().to_string(), code, row))
+            .collect()
+    }
+}
+
+#[test]
+fn struct_id_collides_with_imported_struct() {
+    let dir = write_files("root", &[
+        ("gnss.iris", "version 4.0.0;\npackage Gnss;\n@id(0x10)\nstruct Fix { u8 sats; }\n"),
+        ("main.iris", "version 4.0.0;\npackage Rocket;\nimport \"gnss.iris\";\n@id(0x10)\nstruct Engine { u8 rpm; }\n")
+    ]);
+
+    assert_eq!(parse_main(&dir), vec![(String::from("main.iris"), 12, 5)]);
+}
+
+#[test]
+fn struct_id_collides_with_indirectly_imported_struct() {
+    let dir = write_files("indirect", &[
+        ("time.iris", "version 4.0.0;\npackage Time;\n@id(0x10)\nstruct Stamp { u64 us; }\n"),
+        ("gnss.iris", "version 4.0.0;\npackage Gnss;\nimport \"time.iris\";\nstruct Fix { Time.Stamp time; }\n"),
+        ("main.iris", "version 4.0.0;\npackage Rocket;\nimport \"gnss.iris\";\n@id(0x10)\nstruct Engine { u8 rpm; }\n")
+    ]);
+
+    assert_eq!(parse_main(&dir), vec![(String::from("main.iris"), 12, 5)]);
+}
+
+/// Two imported files can't see each other, the collision is shown on the second import
+#[test]
+fn imported_struct_ids_collide() {
+    let dir = write_files("siblings", &[
+        ("gnss.iris", "version 4.0.0;\npackage Gnss;\n@id(0x10)\nstruct Fix { u8 sats; }\n"),
+        ("imu.iris", "version 4.0.0;\npackage Imu;\n@id(0x10)\nstruct Sample { i16 x; }\n"),
+        ("main.iris", "version 4.0.0;\npackage Rocket;\nimport \"gnss.iris\";\nimport \"imu.iris\";\nstruct Engine { u8 rpm; }\n")
+    ]);
+
+    assert_eq!(parse_main(&dir), vec![(String::from("main.iris"), 12, 4)]);
+}
+
+/// A package imported by more files is checked once
+#[test]
+fn shared_import_is_not_a_collision() {
+    let dir = write_files("shared", &[
+        ("time.iris", "version 4.0.0;\npackage Time;\nstruct Stamp { u64 us; }\n"),
+        ("gnss.iris", "version 4.0.0;\npackage Gnss;\nimport \"time.iris\";\nstruct Fix { Time.Stamp time; }\n"),
+        ("imu.iris", "version 4.0.0;\npackage Imu;\nimport \"time.iris\";\nstruct Sample { Time.Stamp time; }\n"),
+        ("main.iris", "version 4.0.0;\npackage Rocket;\nimport \"gnss.iris\";\nimport \"imu.iris\";\nstruct Engine { u8 rpm; }\n")
+    ]);
+
+    assert_eq!(parse_main(&dir), Vec::new());
+}
+
+/// A file that can't be read is reported on the import with the cause
+#[test]
+fn unreadable_import_shows_the_cause() {
+    let dir = write_files("unreadable", &[
+        ("main.iris", "version 4.0.0;\npackage Rocket;\nimport \"gnss.iris\";\nstruct Engine { u8 rpm; }\n")
+    ]);
+    fs::write(dir.join("gnss.iris"), [0xff, 0xfe, 0x00]).unwrap();
+
+    let path = dir.join("main.iris");
+    let src = fs::read_to_string(&path).unwrap();
+    let files = iris::parse_with_imports(&path, &src, &[], iris::DEFAULT_MAX_ERRORS).err().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let d = &files.last().unwrap().diagnostics.list[0];
+    assert_eq!((d.code, d.span.row, d.span.col), (20, 3, 8));
+    assert!(d.help.as_deref().unwrap().contains("UTF-8"), "{:?}", d.help);
+}
+
+/// The build helper compares every schema with the packages imported by the previous ones
+#[test]
+fn build_checks_ids_of_previous_imports() {
+    let dir = write_files("build", &[
+        ("gnss.iris", "version 4.0.0;\npackage Gnss;\n@id(0x10)\nstruct Fix { u8 sats; }\n"),
+        ("rocket.iris", "version 4.0.0;\npackage Rocket;\nimport \"gnss.iris\";\nstruct Engine { u8 rpm; }\n"),
+        ("ground.iris", "version 4.0.0;\npackage Ground;\n@id(0x10)\nstruct Station { u8 antennas; }\n")
+    ]);
+
+    let res = iris::build::Config::new()
+        .out_dir(&dir)
+        .compile(&[dir.join("rocket.iris"), dir.join("ground.iris")]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let err = res.unwrap_err();
+    assert!(err.to_string().contains("`Gnss.Fix`"), "{}", err);
+}
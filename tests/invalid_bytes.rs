@@ -0,0 +1,340 @@
+use std::{env, fs, path::{Path, PathBuf}, process::Command};
+
+use iris::{CodeGen, Langs};
+
+const SCHEMA: &str = r#"
+version 4.0.0;
+package Probe;
+
+enum Status : u8 { SLEEP; FLIGHT = 3; }
+
+struct Sensor {
+    Status status;
+    u8 level @range(0, 50);
+}
+
+struct Abort { u8 code; }
+
+union Command : u8 {
+    Sensor probe;
+    Abort abort = 5;
+}
+
+struct Packet {
+    Sensor sensor;
+    Status[2] history;
+    optional Status last;
+    Command command;
+    Status[<=3] log;
+    u16[<=2] samples;
+}
+"#;
+
+/// Bytes of a `Packet` after the name hash, with the field reported by the validated decode
+const CASES: &[(&[u8], &str)] = &[
+    (&[0, 3, 10, 0, 3, 0, 0, 10, 0], "ok"),
+    (&[0, 0, 10, 0, 0, 5, 42, 0, 0], "ok"),
+    (&[0, 99, 10, 0, 3, 0, 0, 10, 0], "sensor.status"),
+    (&[0, 0, 60, 0, 3, 0, 0, 10, 0], "sensor.level"),
+    (&[0, 0, 10, 3, 99, 0, 0, 10, 0], "history"),
+    (&[0x80, 0, 10, 0, 0, 99, 0, 0, 10, 0], "last"),
+    (&[0, 0, 10, 0, 0, 7, 0, 0, 0], "command"),
+    (&[0, 0, 10, 0, 0, 0, 99, 10, 0], "command.probe.status"),
+    (&[0, 0, 10, 0, 0, 0, 0, 60, 0], "command.probe.level"),
+    (&[0, 0, 10, 0, 0, 0, 0, 10, 3, 3, 99, 0], "log"),
+    (&[0, 0, 10, 0, 0, 0, 0, 10, 4, 0, 0, 0], "log"),
+    (&[0, 0, 10, 0, 0, 0, 0, 10, 0, 2, 0, 1, 0, 2], "ok"),
+    (&[0, 0, 10, 0, 0, 0, 0, 10, 0, 3, 0, 1, 0, 2], "samples")
+];
+
+/// Size of the decode buffer, the largest encoded `Packet`
+const MAX_BYTES_LENGTH: usize = 22;
+
+fn gen(schema: &str, lang: &str) -> String {
+    let ast = iris::parse(schema).unwrap_or_else(|d| panic!("{}", d.render("probe.iris", schema)));
+    Langs::from_string(lang).unwrap().gen_code(&ast.package)
+}
+
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("iris-invalid-bytes-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Comma separated bytes of every case, the missing bytes up to the largest `Packet` are zero
+fn case_bytes(open: &str, close: &str) -> String {
+    CASES.iter()
+        .map(|(bytes, _)| {
+            let mut all = bytes.to_vec();
+            all.resize(MAX_BYTES_LENGTH - 4, 0);
+            format!("{}{}{}", open, all.iter().map(|b| b.to_string()).collect::<Vec<_>>().join(", "), close)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Run the command, returning the lines it printed, `None` if the tool isn't installed
+fn run(cmd: &mut Command, dir: &Path) -> Option<Vec<String>> {
+    let out = match cmd.output() {
+        Ok(out) => out,
+        Err(_) => {
+            fs::remove_dir_all(dir).unwrap();
+            return None;
+        }
+    };
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    Some(String::from_utf8(out.stdout).unwrap().lines().map(String::from).collect())
+}
+
+/// Lines printed by `main` with the Rust code of `schema`, the items of the package `Probe` are in scope
+fn rust_output(test: &str, schema: &str, main: &str) -> Vec<String> {
+    let dir = temp_dir(test);
+    let src = dir.join("main.rs");
+    fs::write(&src, format!(
+        "#[allow(non_snake_case, non_camel_case_types, dead_code, unused_variables, unused_assignments, unused_mut, clippy::all)]\nmod probe {{\n{}\n}}\n\
+        #[allow(unused_imports)]\nuse probe::Probe::*;\n\
+        fn main() {{\n{}\n}}\n",
+        gen(schema, "rust"),
+        main
+    )).unwrap();
+
+    let out = Command::new(env::var("RUSTC").unwrap_or_else(|_| String::from("rustc")))
+        .args(["--edition", "2021", "-o"])
+        .arg(dir.join("main"))
+        .arg(&src)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+
+    let lines = run(&mut Command::new(dir.join("main")), &dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    lines
+}
+
+/// Lines printed by `main` with the C++ code of `schema`, `None` without a compiler.
+/// `print_bytes` prints a buffer like the Rust `{:?}` of an array.
+fn cpp_output(test: &str, schema: &str, main: &str) -> Option<Vec<String>> {
+    let dir = temp_dir(test);
+    fs::write(dir.join("iris.hpp"), gen(schema, "cpp")).unwrap();
+    fs::write(dir.join("main.cpp"), format!(
+        "#include <cmath>\n#include <cstdio>\n#include \"iris.hpp\"\n\
+        using namespace iris::Probe;\n\
+        void print_bytes(const iris::byte *b, size_t n) {{\n\
+            std::printf(\"[\");\n\
+            for (size_t i = 0; i < n; i++) {{ std::printf(i ? \", %d\" : \"%d\", b[i]); }}\n\
+            std::printf(\"]\\n\");\n\
+        }}\n\
+        int main() {{\n{}\n}}\n",
+        main
+    )).unwrap();
+
+    let build = Command::new(env::var("CXX").unwrap_or_else(|_| String::from("g++")))
+        .args(["-std=c++17", "-o"])
+        .arg(dir.join("main"))
+        .arg(dir.join("main.cpp"))
+        .output();
+    match build {
+        Ok(out) => assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr)),
+        Err(_) => {
+            fs::remove_dir_all(&dir).unwrap();
+            return None;
+        }
+    }
+
+    let lines = run(&mut Command::new(dir.join("main")), &dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    Some(lines)
+}
+
+/// Lines printed by `main` with the Python code of `schema`, `None` without Python. `Probe` is the package.
+fn python_output(test: &str, schema: &str, main: &str) -> Option<Vec<String>> {
+    let dir = temp_dir(test);
+    fs::write(dir.join("iris.py"), gen(schema, "python")).unwrap();
+    fs::write(dir.join("main.py"), format!("import math, struct\nfrom iris import Iris\nProbe = Iris.Packages.Probe\n{}\n", main)).unwrap();
+
+    let lines = run(Command::new("python3").arg(dir.join("main.py")).current_dir(&dir), &dir)?;
+    fs::remove_dir_all(&dir).unwrap();
+    Some(lines)
+}
+
+/// Every language prints the `expected` lines, the languages without their tools are skipped
+fn assert_outputs(test: &str, schema: &str, [rust, cpp, python]: [&str; 3], expected: &[&str]) {
+    assert_eq!(rust_output(&format!("{}-rust", test), schema, rust), expected, "Rust");
+    if let Some(lines) = cpp_output(&format!("{}-cpp", test), schema, cpp) {
+        assert_eq!(lines, expected, "C++");
+    }
+    if let Some(lines) = python_output(&format!("{}-python", test), schema, python) {
+        assert_eq!(lines, expected, "Python");
+    }
+}
+
+fn expected() -> Vec<String> {
+    CASES.iter().map(|(_, field)| field.to_string()).collect()
+}
+
+/// Decoding never panics, the validated decode reports the first field that isn't valid
+#[test]
+fn rust_reports_invalid_values() {
+    let lines = rust_output("invalid-rust", SCHEMA, format!(
+        "for case in [{}] {{\n\
+            let mut data = Packet::NAME_HASH.to_be_bytes().to_vec();\n\
+            data.extend_from_slice(&case);\n\
+            Packet::decode(&data);\n\
+            println!(\"{{}}\", Packet::decode_validated(&data).err().unwrap_or(\"ok\"));\n\
+        }}",
+        case_bytes("[", "u8]")
+    ).as_str());
+
+    assert_eq!(lines, expected());
+}
+
+/// The C++ code keeps the received values, `validate` checks them
+#[test]
+fn cpp_reports_invalid_values() {
+    let lines = cpp_output("invalid-cpp", SCHEMA, format!(
+        "iris::byte cases[][{}] = {{{}}};\n\
+        for (auto &c : cases) {{\n\
+            iris::byte raw[{}];\n\
+            iris::to_be_bytes(Packet::NAME_HASH, raw);\n\
+            for (size_t i = 0; i < sizeof(c); i++) {{ raw[4 + i] = c[i]; }}\n\
+            Packet out;\n\
+            const char *field = Packet::decode_validated(raw, out);\n\
+            std::puts(field ? field : \"ok\");\n\
+        }}",
+        MAX_BYTES_LENGTH - 4,
+        case_bytes("{", "}"),
+        MAX_BYTES_LENGTH
+    ).as_str());
+
+    if let Some(lines) = lines {
+        assert_eq!(lines, expected());
+    }
+}
+
+/// The Python code keeps the received values, `validate` checks them
+#[test]
+fn python_reports_invalid_values() {
+    let lines = python_output("invalid-python", SCHEMA, format!(
+        "for case in [{}]:\n    \
+            raw = struct.pack('>I', Probe.Packet.NAME_HASH) + bytes(case)\n    \
+            print(Probe.Packet.decode(raw).validate() or 'ok')",
+        case_bytes("[", "]")
+    ).as_str());
+
+    if let Some(lines) = lines {
+        assert_eq!(lines, expected());
+    }
+}
+
+/// Multi-dimensional arrays are encoded in row-major order, the last index changes first
+#[test]
+fn array_index_order() {
+    let schema = "version 4.0.0;\npackage Probe;\nstruct Grid { u8[2][3] cells; u16[2][2] words; }\n";
+    let rust = "let grid = Grid { cells: [[0, 1, 2], [10, 11, 12]], words: [[0x0102, 0x0304], [0x0506, 0x0708]] };\n\
+        println!(\"{:?}\", grid.to_be_bytes());\n\
+        let d = Grid::from_be_bytes([1, 2, 3, 4, 5, 6, 0, 7, 0, 8, 0, 9, 0, 10]);\n\
+        println!(\"{} {} {} {}\", d.cells[0][2], d.cells[1][0], d.words[0][1], d.words[1][0]);";
+    let cpp = "uint8_t cells[2][3] = {{0, 1, 2}, {10, 11, 12}};\n\
+        uint16_t words[2][2] = {{0x0102, 0x0304}, {0x0506, 0x0708}};\n\
+        Grid grid(cells, words);\n\
+        iris::byte b[Grid::BYTES_LENGTH - 4];\n\
+        print_bytes(grid.to_be_bytes(b), sizeof(b));\n\
+        iris::byte raw[] = {1, 2, 3, 4, 5, 6, 0, 7, 0, 8, 0, 9, 0, 10};\n\
+        Grid d = Grid::from_be_bytes(raw);\n\
+        std::printf(\"%d %d %d %d\\n\", d.cells[0][2], d.cells[1][0], d.words[0][1], d.words[1][0]);";
+    let python = "grid = Probe.Grid([[0, 1, 2], [10, 11, 12]], [[0x0102, 0x0304], [0x0506, 0x0708]])\n\
+        print(list(grid.to_be_bytes()))\n\
+        d = Probe.Grid.from_be_bytes(bytes([1, 2, 3, 4, 5, 6, 0, 7, 0, 8, 0, 9, 0, 10]))\n\
+        print(d.cells[0][2], d.cells[1][0], d.words[0][1], d.words[1][0])";
+
+    assert_outputs("arrays", schema, [rust, cpp, python], &[
+        "[0, 1, 2, 10, 11, 12, 1, 2, 3, 4, 5, 6, 7, 8]",
+        "3 4 8 9"
+    ]);
+}
+
+/// Bitfields are packed MSB-first, only the lowest bits of a value are encoded and the group is padded to a whole byte
+#[test]
+fn bitfield_packing() {
+    let schema = "version 4.0.0;\npackage Probe;\nstruct Bits { u8:3 mode; bool:1 armed; u16:12 count; u8:4 tail; u8 after; }\n";
+    let rust = "let bits = Bits { mode: 13, armed: true, count: 0xABC, tail: 3, after: 7 };\n\
+        println!(\"{:?}\", bits.to_be_bytes());\n\
+        let d = Bits::from_be_bytes([0x2F, 0xFF, 0xF0, 9]);\n\
+        println!(\"{} {} {} {} {}\", d.mode, d.armed as u8, d.count, d.tail, d.after);";
+    let cpp = "Bits bits(13, true, 0xABC, 3, 7);\n\
+        iris::byte b[Bits::BYTES_LENGTH - 4];\n\
+        print_bytes(bits.to_be_bytes(b), sizeof(b));\n\
+        iris::byte raw[] = {0x2F, 0xFF, 0xF0, 9};\n\
+        Bits d = Bits::from_be_bytes(raw);\n\
+        std::printf(\"%d %d %d %d %d\\n\", d.mode, d.armed, d.count, d.tail, d.after);";
+    let python = "bits = Probe.Bits(13, True, 0xABC, 3, 7)\n\
+        print(list(bits.to_be_bytes()))\n\
+        d = Probe.Bits.from_be_bytes(bytes([0x2F, 0xFF, 0xF0, 9]))\n\
+        print(d.mode, int(d.armed), d.count, d.tail, d.after)";
+
+    assert_outputs("bitfields", schema, [rust, cpp, python], &[
+        "[186, 188, 48, 7]",
+        "1 0 4095 15 9"
+    ]);
+}
+
+/// Scaled values are rounded half away from zero, saturated to the integer type and NaN is 0
+#[test]
+fn scaled_rounding_and_saturation() {
+    let schema = "version 4.0.0;\npackage Probe;\nstruct Scaled { i16 t @scale(0.1) @offset(-40); u8 p @scale(2); i8 n @scale(2); }\n";
+    let rust = "for (t, p, n) in [(20.5, 5.0, -3.0), (1e9, 1000.0, 1000.0), (-1e9, -10.0, f64::NAN)] {\n\
+            println!(\"{:?}\", Scaled { t, p, n }.to_be_bytes());\n\
+        }\n\
+        for raw in [[2, 93, 3, 254], [128, 0, 255, 128]] {\n\
+            let d = Scaled::from_be_bytes(raw);\n\
+            println!(\"{:.2} {:.2} {:.2}\", d.t, d.p, d.n);\n\
+        }";
+    let cpp = "double values[][3] = {{20.5, 5, -3}, {1e9, 1000, 1000}, {-1e9, -10, NAN}};\n\
+        for (auto &v : values) {\n\
+            iris::byte b[Scaled::BYTES_LENGTH - 4];\n\
+            print_bytes(Scaled(v[0], v[1], v[2]).to_be_bytes(b), sizeof(b));\n\
+        }\n\
+        iris::byte raws[][4] = {{2, 93, 3, 254}, {128, 0, 255, 128}};\n\
+        for (auto &raw : raws) {\n\
+            Scaled d = Scaled::from_be_bytes(raw);\n\
+            std::printf(\"%.2f %.2f %.2f\\n\", d.t, d.p, d.n);\n\
+        }";
+    let python = "for t, p, n in [(20.5, 5, -3), (1e9, 1000, 1000), (-1e9, -10, math.nan)]:\n    \
+            print(list(Probe.Scaled(t, p, n).to_be_bytes()))\n\
+        for raw in [[2, 93, 3, 254], [128, 0, 255, 128]]:\n    \
+            d = Probe.Scaled.from_be_bytes(bytes(raw))\n    \
+            print(f'{d.t:.2f} {d.p:.2f} {d.n:.2f}')";
+
+    assert_outputs("scaled", schema, [rust, cpp, python], &[
+        "[2, 93, 3, 254]",
+        "[127, 255, 255, 127]",
+        "[128, 0, 0, 0]",
+        "20.50 6.00 -4.00",
+        "-3316.80 510.00 -256.00"
+    ]);
+}
+
+/// A new struct has the declared default values and zero, or the first variant, in the other fields, also after being encoded and decoded
+#[test]
+fn default_values() {
+    let schema = "version 4.0.0;\npackage Probe;\nenum Mode : u8 { IDLE = 1; ARMED = 4; }\n\
+        struct Defaults { u16 rate = 50; i16 temp = 20.5 @scale(0.1); Mode mode = ARMED; string[4] name = \"ab\"; u8[2] retries = 3; u8:4 nib = 9; u8 zero; Mode first; f32 level; }\n";
+    let rust = "let bytes = Defaults::default().to_be_bytes();\n\
+        println!(\"{:?}\", bytes);\n\
+        let d = Defaults::from_be_bytes(bytes);\n\
+        println!(\"{} {:.2} {} {} {} {} {} {} {} {:.2}\", d.rate, d.temp, d.mode as u8, d.name.as_str(), d.retries[0], d.retries[1], d.nib, d.zero, d.first as u8, d.level);";
+    let cpp = "Defaults defaults;\n\
+        iris::byte b[Defaults::BYTES_LENGTH - 4];\n\
+        print_bytes(defaults.to_be_bytes(b), sizeof(b));\n\
+        Defaults d = Defaults::from_be_bytes(b);\n\
+        std::printf(\"%d %.2f %d %s %d %d %d %d %d %.2f\\n\", d.rate, d.temp, d.mode.value, d.name, d.retries[0], d.retries[1], d.nib, d.zero, d.first.value, d.level);";
+    let python = "b = Probe.Defaults().to_be_bytes()\n\
+        print(list(b))\n\
+        d = Probe.Defaults.from_be_bytes(b)\n\
+        print(f'{d.rate} {d.temp:.2f} {int(d.mode)} {d.name} {d.retries[0]} {d.retries[1]} {d.nib} {d.zero} {int(d.first)} {d.level:.2f}')";
+
+    assert_outputs("defaults", schema, [rust, cpp, python], &[
+        "[0, 50, 0, 205, 4, 97, 98, 0, 0, 3, 3, 144, 0, 1, 0, 0, 0, 0]",
+        "50 20.50 4 ab 3 3 9 0 1 0.00"
+    ]);
+}
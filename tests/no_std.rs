@@ -0,0 +1,92 @@
+use std::{env, fs, process::Command};
+
+use iris::{CodeGen, Langs};
+
+/// Schema using every kind of declaration and field
+const SCHEMA: &str = r#"
+version 4.0.0;
+package Everything;
+
+## Engines on the rocket
+const u8 MAX_ENGINES = 2;
+const u8 SPARE = 3;
+
+enum Mode : u8 { IDLE; ARMED; FLIGHT = 10; }
+flags Valves : u8 { MAIN; VENT; PURGE = 0x10; }
+enum Sensor : u32 { NONE; WIDE = 4000000000; }
+
+struct Ignite { u16 delay_ms @range(0, 5000); }
+struct Abort { u8 code; }
+
+union Payload : u8 {
+    Ignite ignite;
+    Abort abort = 5;
+}
+
+## Status of an engine
+@id(0x10)
+struct Engine {
+    u16 rate = 50 @unit("Hz");
+    i16 temperature @scale(0.1) @offset(-40) @range(-20, 120.5);
+    u8:3 stage;
+    bool:1 lit;
+    reserved SPARE;
+    string[8] name = "main";
+    Mode mode @min(ARMED);
+    Valves valves;
+    Sensor sensor = WIDE;
+    f32[2][MAX_ENGINES] grid;
+    u16[<=4] samples;
+    optional u64 time;
+    optional i32 pressure @scale(0.5);
+    Payload payload;
+    u8 legacy @deprecated("use `rate`");
+}
+
+struct Rocket {
+    Engine[MAX_ENGINES] engines;
+    optional Mode mode;
+}
+"#;
+
+/// Generate the Rust code of the schema and build it without `std`.
+/// It's included like the documentation of `iris::build` shows, which must allow every warning of the generated code.
+fn build_without_std(name: &str, schema: &str) {
+    let ast = iris::parse(schema).unwrap_or_else(|d| panic!("{}", d.render(format!("{}.iris", name).as_str(), schema)));
+    let code = Langs::from_string("rust").unwrap().gen_code(&ast.package);
+
+    let dir = env::temp_dir().join(format!("iris-no-std-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let src = dir.join("lib.rs");
+    fs::write(&src, format!(
+        "#![no_std]\n#[allow(non_snake_case, non_camel_case_types, dead_code, unused_variables, unused_assignments, unused_mut, clippy::all)]\nmod {} {{\n{}\n}}\n",
+        name, code
+    )).unwrap();
+
+    let out = Command::new(env::var("RUSTC").unwrap_or_else(|_| String::from("rustc")))
+        .args(["--edition", "2021", "--crate-type", "lib", "--emit", "metadata", "-D", "warnings", "-o"])
+        .arg(dir.join(format!("lib{}.rmeta", name)))
+        .arg(&src)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+}
+
+/// The generated Rust code is used by firmware without `std` and without a heap
+#[test]
+fn generated_rust_builds_without_std() {
+    build_without_std("everything", SCHEMA);
+}
+
+/// The packages are modules next to `FixedString` and `BoundedArray`, their names can be the ones of the generic parameters
+#[test]
+fn package_names_dont_shadow_the_helpers() {
+    for package in ["N", "T", "CAP", "Item"] {
+        build_without_std(
+            package.to_lowercase().as_str(),
+            format!("version 4.0.0;\npackage {};\nstruct Log {{ string[8] name; u16[<=4] samples; }}\n", package).as_str()
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use std::{env, fs, path::Path};
+
+/// Blocks of the README marked as `iris`, with the line where they start
+fn iris_blocks(readme: &str) -> Vec<(usize, String)> {
+    let mut out = Vec::new();
+    let mut block: Option<(usize, String)> = None;
+
+    for (i, line) in readme.lines().enumerate() {
+        match block.as_mut() {
+            Some((start, src)) => {
+                if line.starts_with("```") {
+                    out.push((*start, std::mem::take(src)));
+                    block = None;
+                }
+                else {
+                    src.push_str(line);
+                    src.push('\n');
+                }
+            },
+            None if line == "```iris" => block = Some((i + 2, String::new())),
+            None => {}
+        }
+    }
+
+    out
+}
+
+/// Every schema in the README compiles. The snippets without a version and a package get them,
+/// a snippet starting with `# path.iris` is a file imported by the other ones.
+#[test]
+fn readme_schemas_compile() {
+    let readme = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("README.md")).unwrap();
+    let blocks = iris_blocks(&readme);
+    assert!(!blocks.is_empty());
+
+    let dir = env::temp_dir().join(format!("iris-readme-{}", std::process::id()));
+    let (imported, schemas): (Vec<_>, Vec<_>) = blocks.into_iter().partition(|(_, src)| src.starts_with("# ") && src.lines().next().unwrap().ends_with(".iris"));
+    for (_, src) in &imported {
+        let path = dir.join(src.lines().next().unwrap().trim_start_matches("# "));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, src).unwrap();
+    }
+
+    let mut failed = Vec::new();
+    for (line, snippet) in imported.iter().chain(schemas.iter()) {
+        let src = match (snippet.contains("version "), snippet.contains("package ")) {
+            (false, false) => format!("version 4.0.0;\npackage Readme;\n{}", snippet),
+            (false, true) => format!("version 4.0.0;\n{}", snippet),
+            (true, false) => format!("{}package Readme;\n", snippet),
+            (true, true) => snippet.clone()
+        };
+
+        let path = dir.join(format!("readme-{}.iris", line));
+        if let Err(files) = iris::parse_with_imports(&path, &src, &[], iris::DEFAULT_MAX_ERRORS) {
+            failed.push(format!("README.md:{}\n{}", line, files.iter().map(|f| f.render()).collect::<String>()));
+        }
+    }
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}